@@ -0,0 +1,106 @@
+use std::path::Path;
+
+use cgmath::prelude::*;
+use cgmath::{Deg, Matrix3, Matrix4};
+
+use model::load_scene;
+use utils::gltf::GltfScene;
+use utils::mesh_data::{MeshData, Semantic};
+use utils::mesh_file::save_mesh;
+use utils::mesh_optimize::optimize;
+use utils::tangent_space::{generate_normals, generate_tangents};
+
+/// Convert any model `load_scene` can load into a mesh file, returns a summary of what was written.
+///
+/// Every mesh of the scene is baked into one at the place its node puts it, keeping the streams
+/// all of them have. Materials are dropped, but normal mapped primitives without tangents get
+/// them generated first. The result is optimized for the vertex cache.
+pub fn convert(input: &Path, output: &Path) -> Result<String, String> {
+    let scene = load_scene(input).map_err(|e| format!("Failed to load {}: {}", input.display(), e))?;
+    let mut data = bake(&scene).map_err(|e| format!("Failed to bake {}: {}", input.display(), e))?;
+    if data.vertex_count() == 0 {
+        return Err(format!("{} has no triangles to convert", input.display()));
+    }
+    let report = optimize(&mut data).map_err(|e| format!("Failed to optimize {}: {}", input.display(), e))?;
+    save_mesh(output, &data).map_err(|e| format!("Failed to write {}: {}", output.display(), e))?;
+    Ok(format!(
+        "{} -> {}: {} ({})",
+        input.display(),
+        output.display(),
+        report,
+        data.semantics()
+            .iter()
+            .map(|semantic| semantic.name())
+            .collect::<Vec<&str>>()
+            .join(", ")
+    ))
+}
+
+/// every primitive of every mesh instance in world space, merged, fails on a primitive
+/// that needs normals or tangents generated but isn't a valid mesh
+fn bake(scene: &GltfScene) -> Result<MeshData, String> {
+    let mut parts: Vec<MeshData> = Vec::new();
+    for instance in scene.instances() {
+        let mesh = &scene.meshes[instance.mesh];
+        let name = mesh.name.as_ref().map_or("mesh", |name| name.as_str());
+        for primitive in &mesh.primitives {
+            let mut data = primitive.data.clone();
+            // glTF asks for flat shading when a primitive comes without normals
+            if !data.has(Semantic::Normal) {
+                generate_normals(&mut data, Deg(0.0)).map_err(|e| format!("{}: {}", name, e))?;
+            }
+            // and for tangents when a normal texture needs them
+            let normal_mapped = primitive
+                .material
+                .is_some_and(|material| scene.materials[material].normal_texture.is_some());
+            if normal_mapped && !data.has(Semantic::Tangent) && data.has(Semantic::TexCoord) {
+                generate_tangents(&mut data).map_err(|e| format!("{}: {}", name, e))?;
+            }
+            transform(&mut data, &instance.transform);
+            parts.push(data);
+        }
+    }
+
+    // streams only some parts have can't be merged
+    let partial: Vec<Semantic> = parts
+        .iter()
+        .flat_map(|part| part.semantics())
+        .filter(|&semantic| !parts.iter().all(|part| part.has(semantic)))
+        .collect();
+    let mut merged = MeshData::default();
+    for mut part in parts {
+        for &semantic in &partial {
+            match semantic {
+                Semantic::Position => (),
+                Semantic::TexCoord => part.tex_coords.clear(),
+                Semantic::Normal => part.normals.clear(),
+                Semantic::Tangent => part.tangents.clear(),
+                Semantic::Color => part.colors.clear(),
+            }
+        }
+        merged.append(&part);
+    }
+    Ok(merged)
+}
+
+/// move a mesh by a model matrix, normals by its inverse transpose, mirroring flips the winding
+fn transform(data: &mut MeshData, model: &Matrix4<f32>) {
+    let linear = Matrix3::from_cols(model.x.truncate(), model.y.truncate(), model.z.truncate());
+    let normal_matrix = linear.invert().map_or(linear, |inverse| inverse.transpose());
+    let mirrored = linear.determinant() < 0.0;
+    for position in data.positions.iter_mut() {
+        *position = model.transform_point(*position);
+    }
+    for normal in data.normals.iter_mut() {
+        *normal = (normal_matrix * *normal).normalize();
+    }
+    for tangent in data.tangents.iter_mut() {
+        let direction = (linear * tangent.truncate()).normalize();
+        *tangent = direction.extend(if mirrored { -tangent.w } else { tangent.w });
+    }
+    if mirrored {
+        for triangle in data.indices.chunks_mut(3) {
+            triangle.swap(1, 2);
+        }
+    }
+}
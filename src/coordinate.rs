@@ -1,149 +1,275 @@
 use std::ffi::CString;
-use std::mem;
-use std::os::raw::c_void;
-use std::ptr;
-use std::str;
 use std::path::Path;
+use std::time::Duration;
 
-use gl;
-use gl::types::{GLchar, GLfloat, GLint, GLsizei, GLsizeiptr, GLuint};
-
-use image;
-use image::GenericImageView;
-
-pub unsafe fn create_vertex_array_object() -> GLuint {
-    let vertices: [f32; 180] = [
-         -0.5, -0.5, -0.5,  0.0, 0.0,
-          0.5, -0.5, -0.5,  1.0, 0.0,
-          0.5,  0.5, -0.5,  1.0, 1.0,
-          0.5,  0.5, -0.5,  1.0, 1.0,
-         -0.5,  0.5, -0.5,  0.0, 1.0,
-         -0.5, -0.5, -0.5,  0.0, 0.0,
-
-         -0.5, -0.5,  0.5,  0.0, 0.0,
-          0.5, -0.5,  0.5,  1.0, 0.0,
-          0.5,  0.5,  0.5,  1.0, 1.0,
-          0.5,  0.5,  0.5,  1.0, 1.0,
-         -0.5,  0.5,  0.5,  0.0, 1.0,
-         -0.5, -0.5,  0.5,  0.0, 0.0,
-
-         -0.5,  0.5,  0.5,  1.0, 0.0,
-         -0.5,  0.5, -0.5,  1.0, 1.0,
-         -0.5, -0.5, -0.5,  0.0, 1.0,
-         -0.5, -0.5, -0.5,  0.0, 1.0,
-         -0.5, -0.5,  0.5,  0.0, 0.0,
-         -0.5,  0.5,  0.5,  1.0, 0.0,
-
-          0.5,  0.5,  0.5,  1.0, 0.0,
-          0.5,  0.5, -0.5,  1.0, 1.0,
-          0.5, -0.5, -0.5,  0.0, 1.0,
-          0.5, -0.5, -0.5,  0.0, 1.0,
-          0.5, -0.5,  0.5,  0.0, 0.0,
-          0.5,  0.5,  0.5,  1.0, 0.0,
-
-         -0.5, -0.5, -0.5,  0.0, 1.0,
-          0.5, -0.5, -0.5,  1.0, 1.0,
-          0.5, -0.5,  0.5,  1.0, 0.0,
-          0.5, -0.5,  0.5,  1.0, 0.0,
-         -0.5, -0.5,  0.5,  0.0, 0.0,
-         -0.5, -0.5, -0.5,  0.0, 1.0,
-
-         -0.5,  0.5, -0.5,  0.0, 1.0,
-          0.5,  0.5, -0.5,  1.0, 1.0,
-          0.5,  0.5,  0.5,  1.0, 0.0,
-          0.5,  0.5,  0.5,  1.0, 0.0,
-         -0.5,  0.5,  0.5,  0.0, 0.0,
-         -0.5,  0.5, -0.5,  0.0, 1.0
-    ];
-
-    let (mut vbo, mut vao) = (0, 0);
-    gl::GenVertexArrays(1, &mut vao);
-    gl::GenBuffers(1, &mut vbo);
-
-    gl::BindVertexArray(vao);
-
-    gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
-    gl::BufferData(
-        gl::ARRAY_BUFFER,
-        (vertices.len() * mem::size_of::<GLfloat>()) as GLsizeiptr,
-        &vertices[0] as *const f32 as *const c_void,
-        gl::STATIC_DRAW,
-    );
-
-    let stride = 5 * mem::size_of::<GLfloat>() as GLsizei;
-    // position attribute
-    gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, stride, ptr::null());
-    gl::EnableVertexAttribArray(0);
-    // texture coord attribute
-    gl::VertexAttribPointer(
-        1,
-        2,
-        gl::FLOAT,
-        gl::FALSE,
-        stride,
-        (3 * mem::size_of::<GLfloat>()) as *const c_void,
-    );
-    gl::EnableVertexAttribArray(1);
-
-    return vao;
+use glutin::VirtualKeyCode;
+
+use cgmath::{vec4, Matrix4, Vector4};
+
+use demo::{Demo, RenderContext};
+use simulation::CubeField;
+use utils::debug_lines::DebugLines;
+use utils::frustum::{CullStats, Frustum};
+use utils::instance_buffer::InstanceBuffer;
+use utils::mesh::Mesh;
+use utils::mesh_data::{MeshData, Semantic};
+use utils::mesh_optimize::weld_vertices;
+use utils::shader::Shader;
+use utils::shapes;
+use utils::texture::{Texture, TextureOptions, TextureUnit};
+
+/// Unit cube with only positions and texture coords, as `coordinate.vs` expects.
+///
+/// Without the normals and tangents the faces share some of their corners, welding takes it
+/// from 24 vertices down to 20.
+pub fn cube_data() -> MeshData {
+    let mut data = shapes::cube(1.0);
+    data.normals.clear();
+    data.tangents.clear();
+    // can't fail, the cube is a valid mesh
+    weld_vertices(&mut data).unwrap();
+    data
+}
+
+/// `cube_data` uploaded to the GPU
+pub unsafe fn create_cube_mesh() -> Mesh {
+    cube_data()
+        .upload(&[(Semantic::Position, 0), (Semantic::TexCoord, 1)])
+        .unwrap()
 }
 
-pub unsafe fn load_and_create_textures(file_path_1: &Path, file_path_2: &Path) -> (GLuint, GLuint) {
-    let (mut texture_1, mut texture_2) = (0, 0);
-
-    //texture 1
-    gl::GenTextures(1, &mut texture_1);
-    gl::BindTexture(gl::TEXTURE_2D, texture_1);
-    // set texture wrapping parameters
-    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
-    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
-    // set texture filtering parameters
-    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
-    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
-    // load image, create texture and generate mipmaps
-    let img = image::open(file_path_1).expect("Failed to load texture");
-    let img = img.flipv();
-    let data = img.raw_pixels();
-
-    gl::TexImage2D(
-        gl::TEXTURE_2D,
-        0,
-        gl::RGB as i32,
-        img.width() as i32,
-        img.height() as i32,
-        0,
-        gl::RGB,
-        gl::UNSIGNED_BYTE,
-        &data[0] as *const u8 as *const c_void,
-    );
-    gl::GenerateMipmap(gl::TEXTURE_2D);
-
-    //texture 2
-    gl::GenTextures(1, &mut texture_2);
-    gl::BindTexture(gl::TEXTURE_2D, texture_2);
-    // set texture wrapping parameters
-    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
-    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
-    // set texture filtering parameters
-    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
-    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
-    // load image, create texture and generate mipmaps
-    let img = image::open(file_path_2).expect("Failed to load texture");
-    let img = img.flipv();
-    let data = img.raw_pixels();
-
-    gl::TexImage2D(
-        gl::TEXTURE_2D,
-        0,
-        gl::RGB as i32,
-        img.width() as i32,
-        img.height() as i32,
-        0,
-        gl::RGB,
-        gl::UNSIGNED_BYTE,
-        &data[0] as *const u8 as *const c_void,
-    );
-    gl::GenerateMipmap(gl::TEXTURE_2D);
-
-    return (texture_1, texture_2);
+/// textures from two image files, repeating and filtered linearly
+pub unsafe fn load_and_create_textures(file_path_1: &Path, file_path_2: &Path) -> (Texture, Texture) {
+    (
+        Texture::load(file_path_1, &TextureOptions::default()).expect("Failed to load texture"),
+        Texture::load(file_path_2, &TextureOptions::default()).expect("Failed to load texture"),
+    )
+}
+
+/// units `texture_1` and `texture_2` of `coordinate.fs` are read from
+const TEXTURE_1_UNIT: TextureUnit = TextureUnit(0);
+const TEXTURE_2_UNIT: TextureUnit = TextureUnit(1);
+
+/// cubes in the stress field, switched to with C
+pub const STRESS_CUBE_COUNT: usize = 40_000;
+
+/// how the visible cubes are submitted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DrawMode {
+    /// one draw call for all of them, model matrices and colors come from an instance buffer
+    #[default]
+    Instanced,
+    /// a `model` uniform and a draw call per cube
+    PerCube,
+}
+
+/// Field of spinning textured cubes viewed through the camera.
+///
+/// I switches between instanced and per cube draws, C between the ten cubes of the chapter
+/// and a stress field of `STRESS_CUBE_COUNT` tinted cubes. B shows the bounds of the drawn cubes,
+/// the box rotating with the cube in green and the world space box culling uses in yellow.
+#[derive(Default)]
+pub struct CoordinateDemo {
+    shader: Option<Shader>,
+    instanced_shader: Option<Shader>,
+    mesh: Option<Mesh>,
+    instances: Option<InstanceBuffer>,
+    debug_lines: Option<DebugLines>,
+    show_bounds: bool,
+    textures: Option<(Texture, Texture)>,
+    cubes: CubeField,
+    /// tint of each cube, only the instanced path draws them
+    colors: Vec<Vector4<f32>>,
+    stress: bool,
+    draw_mode: DrawMode,
+    cull_stats: CullStats,
+}
+
+impl CoordinateDemo {
+    fn reset_cubes(&mut self) {
+        if self.stress {
+            self.cubes = CubeField::grid(STRESS_CUBE_COUNT);
+            self.colors = (0..STRESS_CUBE_COUNT)
+                .map(|i| {
+                    let hue = i as f32 * 0.01;
+                    vec4(
+                        0.6 + 0.4 * hue.sin(),
+                        0.6 + 0.4 * (hue + 2.1).sin(),
+                        0.6 + 0.4 * (hue + 4.2).sin(),
+                        1.0,
+                    )
+                }).collect();
+        } else {
+            self.cubes = CubeField::new();
+            self.colors = vec![vec4(1.0, 1.0, 1.0, 1.0); self.cubes.len()];
+        }
+    }
+}
+
+impl Demo for CoordinateDemo {
+    fn name(&self) -> &'static str {
+        "coordinate"
+    }
+
+    unsafe fn setup(&mut self) {
+        let shader = Shader::new(
+            Path::new("src/shaders/coordinate.vs"),
+            Path::new("src/shaders/coordinate.fs"),
+        );
+        let mesh = create_cube_mesh();
+        let instances = InstanceBuffer::new(true);
+        instances.attach(&mesh);
+        self.mesh = Some(mesh);
+        self.instances = Some(instances);
+        self.debug_lines = Some(DebugLines::new());
+        self.textures = Some(load_and_create_textures(
+            Path::new("resources/crate.jpg"),
+            Path::new("resources/pumpkin.jpg"),
+        ));
+
+        // tell opengl for each sampler to which texture unit it belongs to (only has to be done once)
+        // -------------------------------------------------------------------------------------------
+        shader.use_program();
+        shader.set_int(&CString::new("texture_1").unwrap(), TEXTURE_1_UNIT.sampler());
+        shader.set_int(&CString::new("texture_2").unwrap(), TEXTURE_2_UNIT.sampler());
+        self.shader = Some(shader);
+
+        let instanced_shader = Shader::new(
+            Path::new("src/shaders/coordinate_instanced.vs"),
+            Path::new("src/shaders/coordinate.fs"),
+        );
+        instanced_shader.use_program();
+        instanced_shader.set_int(&CString::new("texture_1").unwrap(), TEXTURE_1_UNIT.sampler());
+        instanced_shader.set_int(&CString::new("texture_2").unwrap(), TEXTURE_2_UNIT.sampler());
+        self.instanced_shader = Some(instanced_shader);
+
+        self.reset_cubes();
+    }
+
+    fn update(&mut self, _step: Duration) {
+        self.cubes.update();
+    }
+
+    fn key_pressed(&mut self, key: VirtualKeyCode) {
+        match key {
+            VirtualKeyCode::I => {
+                self.draw_mode = match self.draw_mode {
+                    DrawMode::Instanced => DrawMode::PerCube,
+                    DrawMode::PerCube => DrawMode::Instanced,
+                }
+            }
+            VirtualKeyCode::B => self.show_bounds = !self.show_bounds,
+            VirtualKeyCode::C => {
+                self.stress = !self.stress;
+                self.reset_cubes();
+            }
+            _ => (),
+        }
+    }
+
+    unsafe fn render(&mut self, context: &RenderContext) {
+        let (texture_1, texture_2) = self.textures.as_ref().unwrap();
+        texture_1.bind(TEXTURE_1_UNIT);
+        texture_2.bind(TEXTURE_2_UNIT);
+
+        let shader = match self.draw_mode {
+            DrawMode::Instanced => self.instanced_shader.as_ref().unwrap(),
+            DrawMode::PerCube => self.shader.as_ref().unwrap(),
+        };
+        shader.use_program();
+
+        let view: Matrix4<f32> = context.camera.view_matrix();
+        let projection: Matrix4<f32> = context.camera.projection_matrix(context.aspect_ratio);
+        shader.set_matrix4(&CString::new("view").unwrap(), &view);
+        shader.set_matrix4(&CString::new("projection").unwrap(), &projection);
+        shader.set_float(&CString::new("time").unwrap(), context.time);
+
+        let mesh = self.mesh.as_ref().unwrap();
+
+        // bounds of the cube in model space, used for frustum culling
+        let cube_aabb = mesh.bounds().unwrap();
+        let cube_sphere = mesh.bounding_sphere().unwrap();
+        let debug_lines = self.debug_lines.as_mut().unwrap();
+
+        // only submit cubes inside the view frustum
+        let frustum = Frustum::from_matrix(&(projection * view));
+        self.cull_stats = CullStats::default();
+        let mut visible_models: Vec<Matrix4<f32>> = Vec::new();
+        let mut visible_colors: Vec<Vector4<f32>> = Vec::new();
+        for (cube_model, color) in self.cubes.models().interpolated(context.alpha).iter().zip(&self.colors) {
+            let cube_model = cube_model.matrix();
+            let world_aabb = cube_aabb.transform(&cube_model);
+            let visible = frustum.intersects_sphere(&cube_sphere.transform(&cube_model))
+                && frustum.intersects_aabb(&world_aabb);
+            self.cull_stats.record(visible);
+            if !visible {
+                continue;
+            }
+            if self.show_bounds {
+                debug_lines.add_box(&cube_aabb, &cube_model, vec4(0.0, 1.0, 0.0, 1.0));
+                debug_lines.add_aabb(&world_aabb, vec4(1.0, 1.0, 0.0, 1.0));
+            }
+            match self.draw_mode {
+                DrawMode::Instanced => {
+                    visible_models.push(cube_model);
+                    visible_colors.push(*color);
+                }
+                DrawMode::PerCube => {
+                    shader.set_matrix4(&CString::new("model").unwrap(), &cube_model);
+                    mesh.draw();
+                }
+            }
+        }
+
+        if self.draw_mode == DrawMode::Instanced {
+            let instances = self.instances.as_mut().unwrap();
+            instances.upload(&visible_models, Some(&visible_colors));
+            mesh.draw_instanced(instances.len());
+        }
+        debug_lines.draw(&view, &projection);
+    }
+
+    unsafe fn teardown(&mut self) {
+        if let Some(mesh) = self.mesh.take() {
+            mesh.delete();
+        }
+        if let Some(instances) = self.instances.take() {
+            instances.delete();
+        }
+        if let Some(debug_lines) = self.debug_lines.take() {
+            debug_lines.delete();
+        }
+        self.textures = None;
+        for shader in self.shader.take().into_iter().chain(self.instanced_shader.take()) {
+            shader.delete();
+        }
+    }
+
+    fn status(&self) -> String {
+        let mode = match self.draw_mode {
+            DrawMode::Instanced => "instanced",
+            DrawMode::PerCube => "per cube",
+        };
+        format!(
+            "{} cubes {} - drawn: {} culled: {}",
+            self.cubes.len(),
+            mode,
+            self.cull_stats.drawn,
+            self.cull_stats.culled
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cube_is_welded() {
+        let data = cube_data();
+        assert_eq!(data.semantics(), vec![Semantic::Position, Semantic::TexCoord]);
+        assert_eq!(data.vertex_count(), 20);
+        assert_eq!(data.triangle_count(), 12);
+        assert!(data.validate().is_ok());
+    }
 }
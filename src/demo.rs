@@ -0,0 +1,104 @@
+use std::path::Path;
+use std::time::Duration;
+
+use gl;
+use gl::types::{GLint, GLuint};
+
+use glutin::VirtualKeyCode;
+
+use utils::camera::Camera;
+
+use coordinate::CoordinateDemo;
+use model::ModelDemo;
+use shaders::{UniformColorDemo, VertexColorsDemo};
+use textures::{TexturesDemo, TransformDemo};
+use triangle::{TriangleDemo, TwoTrianglesDemo};
+
+/// what a demo needs to know to draw a frame
+pub struct RenderContext<'a> {
+    /// camera interpolated for this frame
+    pub camera: &'a Camera,
+    pub aspect_ratio: f32,
+    /// simulation time in seconds
+    pub time: f32,
+    /// how far the simulation is into the next update, for interpolating demo state
+    pub alpha: f32,
+}
+
+/// A runnable chapter of the tutorial.
+///
+/// `setup` creates all GL resources the demo needs and `teardown` deletes them again,
+/// so demos can be switched at runtime without leaking objects.
+pub trait Demo {
+    /// name used to select the demo on the command line
+    fn name(&self) -> &'static str;
+
+    unsafe fn setup(&mut self);
+
+    /// advance the demo by one fixed simulation step
+    fn update(&mut self, _step: Duration) {}
+
+    /// a key the scene doesn't use itself was pressed
+    fn key_pressed(&mut self, _key: VirtualKeyCode) {}
+
+    unsafe fn render(&mut self, context: &RenderContext);
+
+    unsafe fn teardown(&mut self);
+
+    /// extra information shown in the window title
+    fn status(&self) -> String {
+        String::new()
+    }
+}
+
+/// builds a demo, the model demo shows the glTF file at the given path
+pub type DemoConstructor = fn(&Path) -> Box<dyn Demo>;
+
+/// name and constructor of every demo, in chapter order, so a demo can be picked without building the others
+pub const DEMOS: [(&str, DemoConstructor); 8] = [
+    ("triangle", |_| Box::new(TriangleDemo::default())),
+    ("two-triangles", |_| Box::new(TwoTrianglesDemo::default())),
+    ("uniform-color", |_| Box::new(UniformColorDemo::default())),
+    ("vertex-colors", |_| Box::new(VertexColorsDemo::default())),
+    ("textures", |_| Box::new(TexturesDemo::default())),
+    ("transform", |_| Box::new(TransformDemo::default())),
+    ("coordinate", |_| Box::new(CoordinateDemo::default())),
+    ("model", |model| Box::new(ModelDemo::new(model))),
+];
+
+/// delete a vertex array object together with the buffers its attributes and indices were sourced from
+pub unsafe fn delete_vertex_array(vao: GLuint) {
+    gl::BindVertexArray(vao);
+
+    let mut buffers: Vec<GLuint> = Vec::new();
+    let mut max_attributes: GLint = 0;
+    gl::GetIntegerv(gl::MAX_VERTEX_ATTRIBS, &mut max_attributes);
+    for attribute in 0..max_attributes as GLuint {
+        let mut buffer: GLint = 0;
+        gl::GetVertexAttribiv(attribute, gl::VERTEX_ATTRIB_ARRAY_BUFFER_BINDING, &mut buffer);
+        if buffer != 0 && !buffers.contains(&(buffer as GLuint)) {
+            buffers.push(buffer as GLuint);
+        }
+    }
+    let mut element_buffer: GLint = 0;
+    gl::GetIntegerv(gl::ELEMENT_ARRAY_BUFFER_BINDING, &mut element_buffer);
+    if element_buffer != 0 {
+        buffers.push(element_buffer as GLuint);
+    }
+
+    gl::BindVertexArray(0);
+    gl::DeleteBuffers(buffers.len() as i32, buffers.as_ptr());
+    gl::DeleteVertexArrays(1, &vao);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn demo_names_match() {
+        for &(name, constructor) in DEMOS.iter() {
+            assert_eq!(constructor(Path::new("model.gltf")).name(), name);
+        }
+    }
+}
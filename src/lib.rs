@@ -0,0 +1,11 @@
+// the unsafe functions are all GL calls, they need a current context and nothing else
+#![allow(clippy::missing_safety_doc)]
+
+extern crate cgmath;
+extern crate gl;
+extern crate glutin;
+extern crate image;
+extern crate json;
+
+pub mod simulation;
+pub mod utils;
@@ -1,216 +1,363 @@
-#![allow(dead_code)]
-
 extern crate cgmath;
 extern crate gl;
 extern crate glutin;
 extern crate image;
+extern crate json;
+extern crate learn_opengl;
 
+mod convert;
 mod coordinate;
+mod demo;
+mod model;
+mod scene;
 mod shaders;
 mod textures;
 mod triangle;
-mod utils;
 
-use std::ffi::{CStr, CString};
+use learn_opengl::{simulation, utils};
+
+use std::env;
 use std::path::Path;
+use std::process;
 use std::time::{Duration, Instant};
 
 use glutin::dpi::*;
 use glutin::ElementState::{Pressed, Released};
-use glutin::VirtualKeyCode;
 use glutin::WindowEvent::*;
 use glutin::DeviceEvent::*;
 use glutin::{Api, Event, EventsLoop, GlContext, GlRequest, GlWindow};
 
-use cgmath::prelude::*;
-use cgmath::{vec3, Deg, Matrix4, Point3, Rad, Vector3};
-
+use cgmath::Point3;
+
+use convert::convert;
+use demo::{RenderContext, DEMOS};
+use model::DEFAULT_MODEL;
+use scene::{DemoScene, SceneStack};
+use utils::bookmarks::Bookmarks;
+use utils::clock::SimulationClock;
+use utils::frame_limiter::FrameLimiter;
+use utils::frame_stats::{FrameCsv, FrameStats, FrameTiming};
+use simulation::{CameraRig, Simulation};
+use utils::camera::Camera;
+use utils::camera_path::CameraPath;
 use utils::input::Input;
-use utils::shader::Shader;
+use utils::input_script::InputScript;
+use utils::settings::Settings;
 
-// settings
-const SCR_WIDTH: f64 = 800.0;
-const SCR_HEIGHT: f64 = 600.0;
+// how often the frame statistics in the window title are refreshed
+const STATS_INTERVAL: Duration = Duration::from_millis(500);
 
-const DURATION_PER_UPDATE: Duration = Duration::from_millis(16);
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    // bake a model into a mesh file instead of opening a window: convert <input> <output>
+    if args.get(1).map(|arg| arg.as_str()) == Some("convert") {
+        match (args.get(2), args.get(3)) {
+            (Some(input), Some(output)) => match convert(Path::new(input), Path::new(output)) {
+                Ok(summary) => println!("{}", summary),
+                Err(e) => fail(e),
+            },
+            _ => fail("convert expects an input model and an output .mesh file".to_string()),
+        }
+        return;
+    }
 
-// camera
-const CAMERA_UP: Vector3<f32> = Vector3 {
-    x: 0.0,
-    y: 1.0,
-    z: 0.0,
-};
+    // window, context and loop settings: defaults, then --config <file>, then flags like --width
+    let mut settings = match arg_value(&args, "--config") {
+        Some(file) => Settings::load(Path::new(file))
+            .unwrap_or_else(|e| fail(format!("Failed to load settings {}: {}", file, e))),
+        None => Settings::default(),
+    };
+    settings
+        .apply_args(&args)
+        .unwrap_or_else(|e| fail(format!("Invalid setting {}", e)));
+
+    // optional scripted camera fly-through: --camera-path <file>
+    let camera_path = arg_value(&args, "--camera-path").map(|file| {
+        CameraPath::load(Path::new(file))
+            .unwrap_or_else(|e| fail(format!("Failed to load camera path {}: {}", file, e)))
+    });
+
+    // run the update step for a number of ticks without a window: --headless <ticks>,
+    // optionally feeding in input events from --input-script <file>
+    if let Some(ticks) = arg_value(&args, "--headless") {
+        let ticks = ticks
+            .parse::<u64>()
+            .unwrap_or_else(|_| fail(format!("--headless expects a number of ticks, got {}", ticks)));
+        let script = arg_value(&args, "--input-script")
+            .map(|file| {
+                InputScript::load(Path::new(file))
+                    .unwrap_or_else(|e| fail(format!("Failed to load input script {}: {}", file, e)))
+            }).unwrap_or_default();
+        run_headless(&settings, ticks, &script, camera_path);
+        return;
+    }
+
+    // optional per-frame timings: --frame-csv <file>
+    let frame_csv = arg_value(&args, "--frame-csv").map(|file| {
+        FrameCsv::create(Path::new(file))
+            .unwrap_or_else(|e| fail(format!("Failed to create frame timing file {}: {}", file, e)))
+    });
+
+    // model file for the model demo: --model <file>, starts with that demo unless --demo is given
+    let model = arg_value(&args, "--model");
+    let model_path = Path::new(model.unwrap_or(DEFAULT_MODEL));
+
+    // chapter to start with: --demo <name>, page up/down cross-fade through them at runtime
+    let default_demo = if model.is_some() { "model" } else { "coordinate" };
+    let demo_name = arg_value(&args, "--demo").unwrap_or(default_demo);
 
-fn main() {
     let mut events_loop = glutin::EventsLoop::new();
-    let gl_window = create_gl_window(&events_loop);
+    let gl_window = create_gl_window(&events_loop, &settings);
     unsafe {
-        configure_opengl(&gl_window);
+        configure_opengl(&gl_window, &settings);
     }
 
-    run_game_loop(&mut events_loop, &gl_window);
+    run_game_loop(
+        &mut events_loop,
+        &gl_window,
+        &settings,
+        demo_name,
+        model_path,
+        camera_path,
+        frame_csv,
+    );
+}
+
+/// value following `name` on the command line
+fn arg_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter().position(|arg| arg == name).map(|i| {
+        args.get(i + 1)
+            .unwrap_or_else(|| fail(format!("{} requires a value", name)))
+            .as_str()
+    })
 }
 
-fn create_gl_window(events_loop: &EventsLoop) -> GlWindow {
+/// print a command line or startup error and exit with a failure status
+fn fail(message: String) -> ! {
+    eprintln!("{}", message);
+    process::exit(1)
+}
+
+fn create_gl_window(events_loop: &EventsLoop, settings: &Settings) -> GlWindow {
     let window = glutin::WindowBuilder::new()
-        .with_title("SPOOKY")
-        .with_dimensions(LogicalSize::new(SCR_WIDTH, SCR_HEIGHT));
+        .with_title(settings.title.as_str())
+        .with_dimensions(LogicalSize::new(settings.width as f64, settings.height as f64));
 
     let context = glutin::ContextBuilder::new()
-        .with_gl(GlRequest::Specific(Api::OpenGl, (3, 3)))
-        .with_vsync(true);
+        .with_gl(GlRequest::Specific(Api::OpenGl, settings.gl_version))
+        .with_vsync(settings.vsync);
 
     let gl_window = glutin::GlWindow::new(window, context, events_loop).unwrap();
-    //gl_window.set_cursor_position(LogicalPosition::new(SCR_WIDTH / 2.0, SCR_HEIGHT / 2.0)).unwrap();
+    //gl_window.set_cursor_position(LogicalPosition::new(settings.width as f64 / 2.0, settings.height as f64 / 2.0)).unwrap();
     gl_window.grab_cursor(true).unwrap();
     gl_window.hide_cursor(true);
-    return gl_window;
+    gl_window
 }
 
-unsafe fn configure_opengl(gl_window: &GlWindow) {
+unsafe fn configure_opengl(gl_window: &GlWindow, settings: &Settings) {
     gl_window.make_current().unwrap();
     gl::load_with(|symbol| gl_window.get_proc_address(symbol) as *const _);
-    gl::ClearColor(0.2, 0.3, 0.3, 1.0);
+    let [red, green, blue, alpha] = settings.clear_color;
+    gl::ClearColor(red, green, blue, alpha);
     gl::Clear(gl::COLOR_BUFFER_BIT);
 }
 
-fn run_game_loop(events_loop: &mut EventsLoop, gl_window: &GlWindow) {
+fn run_headless(settings: &Settings, ticks: u64, script: &InputScript, camera_path: Option<CameraPath>) {
+    let mut simulation = Simulation::new(settings.tick_duration(), camera_path);
+    simulation.run(ticks, script);
+    print!("{}", simulation.report());
+}
+
+fn run_game_loop(
+    events_loop: &mut EventsLoop,
+    gl_window: &GlWindow,
+    settings: &Settings,
+    demo_name: &str,
+    model_path: &Path,
+    camera_path: Option<CameraPath>,
+    mut frame_csv: Option<FrameCsv>,
+) {
     // configure global opengl state
     // -----------------------------
     unsafe {
         gl::Enable(gl::DEPTH_TEST);
     }
 
-    // world space positions of our cubes
-    let cube_positions: [Vector3<f32>; 10] = [
-        vec3(0.0, 0.0, 0.0),
-        vec3(2.0, 5.0, -15.0),
-        vec3(-1.5, -2.2, -2.5),
-        vec3(-3.8, -2.0, -12.3),
-        vec3(2.4, -0.4, -3.5),
-        vec3(-1.7, 3.0, -7.5),
-        vec3(1.3, -2.0, -2.5),
-        vec3(1.5, 2.0, -2.5),
-        vec3(1.5, 0.2, -1.5),
-        vec3(-1.3, 1.0, -1.5),
-    ];
-
-    let mut cube_models: Vec<Matrix4<f32>> = cube_positions
-        .iter()
-        .map(|x| Matrix4::from_translation(*x))
-        .enumerate()
-        .map(|(i, x)| {
-            x * Matrix4::from_axis_angle(vec3(0.5, 1.0, 0.0).normalize(), Deg(i as f32 * 20.0))
-        }).collect();
-
-    let mut camera_position = Point3::new(0.0, 0.0, 3.0);
-    let mut camera_front: Vector3<f32> = Vector3 {
-        x: 0.0,
-        y: 0.0,
-        z: -1.0,
-    };
+    let mut input: Input = Input::new();
+    let initial_camera = Camera::from_yaw_pitch(Point3::new(0.0, 0.0, 3.0), input.yaw(), input.pitch());
+    let mut camera = CameraRig::new(initial_camera, camera_path);
+
+    // camera poses saved with ctrl + number and recalled with the number keys
+    let bookmarks_path = Bookmarks::default_path();
+    let (mut bookmarks, errors) = Bookmarks::load(&bookmarks_path);
+    for e in &errors {
+        println!("Failed to load camera bookmarks from {}: {}", bookmarks_path.display(), e);
+    }
+    if bookmarks.is_read_only() {
+        println!("New camera bookmarks won't be saved until {} is fixed", bookmarks_path.display());
+    }
 
     let mut running = true;
     let mut previous_time = Instant::now();
-    let mut lag = Duration::new(0, 0);
-    let shader_program = Shader::new(
-        Path::new("src/shaders/coordinate.vs"),
-        Path::new("src/shaders/coordinate.fs"),
-    );
-
-    let vao = unsafe { coordinate::create_vertex_array_object() };
-    let (texture_1, texture_2) = unsafe {
-        coordinate::load_and_create_textures(
-            Path::new("resources/crate.jpg"),
-            Path::new("resources/pumpkin.jpg"),
-        )
-    };
-
-    // tell opengl for each sampler to which texture unit it belongs to (only has to be done once)
-    // -------------------------------------------------------------------------------------------
-    unsafe {
-        shader_program.use_program();
-        shader_program.set_int(&CString::new("texture_1").unwrap(), 0);
-        shader_program.set_int(&CString::new("texture_2").unwrap(), 1);
-    }
-
-    let mut model: Matrix4<f32> =
-        Matrix4::from_axis_angle(vec3(0.5, 1.0, 0.0).normalize(), Deg(50.0));
-    let projection: Matrix4<f32> =
-        cgmath::perspective(Deg(45.0), SCR_WIDTH as f32 / SCR_HEIGHT as f32, 0.1, 100.0);
-
-    let mut input: Input = Input::new();
+    let mut frame_limiter = FrameLimiter::new(settings.frame_limit());
+    let mut window_title = String::new();
+
+    let mut frame_stats = FrameStats::new();
+    let mut stats_title = String::new();
+    let mut stats_updated = Instant::now();
+
+    // the simulation (spinning cubes) runs on its own clock, so it can be paused
+    // or slowed down while the camera keeps moving in real time
+    let tick_duration = settings.tick_duration();
+    let mut clock = SimulationClock::new(tick_duration);
+    let mut camera_clock = SimulationClock::new(tick_duration);
+
+    let demo_scene = DemoScene::with_name(demo_name, model_path).unwrap_or_else(|| {
+        let names: Vec<&str> = DEMOS.iter().map(|&(name, _)| name).collect();
+        fail(format!("Unknown demo {}, expected one of: {}", demo_name, names.join(", ")))
+    });
+    let mut scenes = unsafe { SceneStack::new(Box::new(demo_scene)) };
 
     while running {
-        let elapsed = previous_time.elapsed();
-        previous_time = Instant::now();
-        lag += elapsed;
+        let frame_start = Instant::now();
+        let elapsed = frame_start - previous_time;
+        previous_time = frame_start;
+        camera_clock.advance(elapsed);
+
+        let events = process_input(&mut input, events_loop, gl_window);
+        for event in events.iter() {
+            unsafe {
+                scenes.handle_event(event);
+            }
+        }
+        unsafe {
+            scenes.advance_fade(elapsed);
+        }
 
-        process_input(&mut input, events_loop, gl_window);
+        camera.apply_input(&mut input);
 
-        while lag >= DURATION_PER_UPDATE {
-            //create transformations
-            let camera_speed = 5.0 * DURATION_PER_UPDATE.subsec_millis() as f32 / 1000.0;
+        if input.paused() != clock.paused() {
+            clock.set_paused(input.paused());
+        }
+        if input.take_step() {
+            clock.request_step();
+        }
+        let time_scale_change = input.take_time_scale_change();
+        for _ in 0..time_scale_change {
+            clock.faster();
+        }
+        for _ in time_scale_change..0 {
+            clock.slower();
+        }
+        clock.advance(elapsed);
 
-            if input.up() {
-                camera_position += camera_speed * camera_front;
-            }
-            if input.down() {
-                camera_position += -(camera_speed * camera_front);
+        if let Some(slot) = input.take_save_bookmark() {
+            if let Err(e) = bookmarks.set(slot, *camera.current()) {
+                println!("Failed to save camera bookmark {}: {}", slot, e);
             }
-            if input.left() {
-                camera_position += -(camera_front.cross(CAMERA_UP).normalize() * camera_speed);
-            }
-            if input.right() {
-                camera_position += camera_front.cross(CAMERA_UP).normalize() * camera_speed;
+        }
+        if let Some(slot) = input.take_recall_bookmark() {
+            if let Some(bookmark) = bookmarks.get(slot) {
+                camera.transition_to(bookmark);
             }
+        }
+
+        let update_start = Instant::now();
+        while camera_clock.tick() {
+            camera.update(&mut input, tick_duration);
             if input.close() {
                 running = false;
             }
-
-            cube_models = cube_models
-                .iter()
-                .map(|x| x * Matrix4::from_axis_angle(vec3(0.5, 1.0, 0.0).normalize(), Deg(2.0)))
-                .collect();
-
-            lag -= DURATION_PER_UPDATE;
         }
-        unsafe {
-            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
-
-            gl::ActiveTexture(gl::TEXTURE0);
-            gl::BindTexture(gl::TEXTURE_2D, texture_1);
-            gl::ActiveTexture(gl::TEXTURE1);
-            gl::BindTexture(gl::TEXTURE_2D, texture_2);
 
-            shader_program.use_program();
-            shader_program.set_matrix4(&CString::new("model").unwrap(), &model);
+        while clock.tick() {
+            unsafe {
+                scenes.fixed_update(tick_duration);
+            }
+        }
 
-            camera_front = Vector3 {
-                x: input.yaw().to_radians().cos() * input.pitch().to_radians().cos(),
-                y: input.pitch().to_radians().sin(),
-                z: input.yaw().to_radians().sin() * input.pitch().to_radians().cos(),
-            };
+        let update_time = update_start.elapsed();
 
-            camera_front.normalize();
+        // render the state between the last two updates, by how far we are into the next one
+        let render_camera = camera.render_camera(camera_clock.alpha());
 
-            let view: Matrix4<f32> = Matrix4::look_at(camera_position, camera_position + camera_front, CAMERA_UP);
-            shader_program.set_matrix4(&CString::new("view").unwrap(), &view);
-            shader_program.set_matrix4(&CString::new("projection").unwrap(), &projection);
+        if scenes.is_empty() {
+            running = false;
+        }
 
-            gl::BindVertexArray(vao);
+        let render_start = Instant::now();
+        unsafe {
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+            scenes.render(&RenderContext {
+                camera: &render_camera,
+                aspect_ratio: settings.aspect_ratio(),
+                time: clock.time(),
+                alpha: clock.alpha(),
+            });
+        }
+        let render_time = render_start.elapsed();
 
-            for cube_model in cube_models.iter() {
-                shader_program.set_matrix4(&CString::new("model").unwrap(), &cube_model);
-                gl::DrawArrays(gl::TRIANGLES, 0, 36);
+        if stats_updated.elapsed() >= STATS_INTERVAL {
+            stats_updated = Instant::now();
+            if let Some(summary) = frame_stats.summary() {
+                stats_title = summary.title();
             }
         }
+
+        let title = format!(
+            "{} - {} - {} - dropped updates: {} - {}",
+            settings.title,
+            scenes.status(),
+            if clock.paused() {
+                "paused".to_string()
+            } else {
+                format!("{}x", clock.time_scale())
+            },
+            clock.dropped_updates(),
+            stats_title
+        );
+        if title != window_title {
+            gl_window.set_title(&title);
+            window_title = title;
+        }
+        let cpu_time = frame_start.elapsed();
+
+        let swap_start = Instant::now();
         gl_window.swap_buffers().unwrap();
+        let swap_time = swap_start.elapsed();
+        frame_limiter.wait();
+
+        let timing = FrameTiming {
+            frame: frame_start.elapsed(),
+            cpu: cpu_time,
+            update: update_time,
+            render: render_time,
+            swap: swap_time,
+        };
+        frame_stats.record(timing);
+        if let Some(Err(e)) = frame_csv.as_mut().map(|csv| csv.write(&timing)) {
+            println!("Failed to write frame timings, no more will be written: {}", e);
+            frame_csv = None;
+        }
+    }
+
+    if let Some(mut csv) = frame_csv {
+        if let Err(e) = csv.flush() {
+            println!("Failed to write frame timings: {}", e);
+        }
+    }
+
+    unsafe {
+        scenes.delete();
     }
 }
 
-fn process_input(input: &mut Input, events_loop: &mut EventsLoop, gl_window: &GlWindow) {
+/// update `input` from the pending events and return them, so scenes can handle them too
+fn process_input(input: &mut Input, events_loop: &mut EventsLoop, gl_window: &GlWindow) -> Vec<Event> {
+    let mut events = Vec::new();
     events_loop.poll_events(|event| {
+        events.push(event.clone());
         match event {
             Event::WindowEvent { event, .. } => match event {
                 CloseRequested => input.set_close(true),
@@ -223,17 +370,11 @@ fn process_input(input: &mut Input, events_loop: &mut EventsLoop, gl_window: &Gl
                         glutin::KeyboardInput {
                             virtual_keycode: Some(key),
                             state: Pressed,
+                            modifiers,
                             ..
                         },
                     ..
-                } => match key {
-                    VirtualKeyCode::W => input.set_up(true),
-                    VirtualKeyCode::S => input.set_down(true),
-                    VirtualKeyCode::A => input.set_left(true),
-                    VirtualKeyCode::D => input.set_right(true),
-                    VirtualKeyCode::Escape => input.set_close(true),
-                    _ => (),
-                },
+                } => input.press_key(key, modifiers.ctrl),
                 KeyboardInput {
                     input:
                         glutin::KeyboardInput {
@@ -242,29 +383,15 @@ fn process_input(input: &mut Input, events_loop: &mut EventsLoop, gl_window: &Gl
                             ..
                         },
                     ..
-                } => match key {
-                    VirtualKeyCode::W => input.set_up(false),
-                    VirtualKeyCode::S => input.set_down(false),
-                    VirtualKeyCode::A => input.set_left(false),
-                    VirtualKeyCode::D => input.set_right(false),
-                    _ => (),
-                },
-                _ => (),
-            },
-            Event::DeviceEvent { event, .. } => match event {
-                MouseMotion { delta } => {
-                    let (x_delta, y_delta) = (delta.0 as f32, delta.1 as f32);
-                    let sensitivity: f32 = 0.1;
-
-                    let current_yaw = input.yaw();
-                    input.set_yaw(current_yaw + x_delta * sensitivity);
-
-                    let current_pitch = input.pitch();
-                    input.set_pitch(current_pitch + (-y_delta) * sensitivity);
-                },
+                } => input.release_key(key),
                 _ => (),
             },
+            Event::DeviceEvent {
+                event: MouseMotion { delta },
+                ..
+            } => input.move_mouse(delta.0 as f32, delta.1 as f32),
             _ => (),
         }
     });
+    events
 }
@@ -0,0 +1,509 @@
+use std::ffi::CString;
+use std::path::{Path, PathBuf};
+
+use gl;
+use glutin::VirtualKeyCode;
+
+use image;
+
+use cgmath::prelude::*;
+use cgmath::{Deg, Matrix4, Point3};
+
+use demo::{Demo, RenderContext};
+use utils::bounds::{Aabb, BoundingSphere};
+use utils::gltf::{
+    load_gltf, AlphaMode, GltfMesh, GltfNode, GltfPrimitive, GltfScene, GltfTexture, ImageSource, MeshInstance,
+    PbrMaterial, Sampler, TextureRef,
+};
+use utils::lod::{lod_chain, select_lod, LOD_RATIOS};
+use utils::mesh::Mesh;
+use utils::mesh_data::Semantic;
+use utils::mesh_file::{read_mesh_file, MeshFile, MESH_FILE_EXTENSION};
+use utils::mesh_optimize::optimize;
+use utils::obj::load_obj;
+use utils::ply::load_ply;
+use utils::shader::Shader;
+use utils::stl::load_stl;
+use utils::tangent_space::generate_normals;
+use utils::texture::{Filter, Texture, TextureOptions, TextureUnit, Wrap};
+
+/// model shown when none is given with `--model`
+pub const DEFAULT_MODEL: &str = "resources/models/crates.gltf";
+
+/// normals computed for OBJ and PLY files without them are smooth across edges under this angle
+const SMOOTHING_ANGLE: Deg<f32> = Deg(60.0);
+
+/// models that don't come as a glTF scene are scaled to fit a box this big around the origin
+const FIT_SIZE: f32 = 2.0;
+
+/// anisotropic filtering for model textures, which are often seen at grazing angles
+const TEXTURE_ANISOTROPY: f32 = 8.0;
+
+/// unit `baseColorTexture` of `model.fs` is read from
+const BASE_COLOR_UNIT: TextureUnit = TextureUnit(0);
+
+/// a glTF primitive on the GPU with the material it's drawn with
+struct ModelPrimitive {
+    /// full detail first, then fewer and fewer triangles
+    lods: Vec<Mesh>,
+    /// triangles of every level of detail
+    triangle_counts: Vec<usize>,
+    /// triangles of every level of detail relative to the full one
+    triangle_ratios: Vec<f32>,
+    material: PbrMaterial,
+    has_colors: bool,
+}
+
+/// Model placed by its node hierarchy, drawn with the base color of its materials.
+///
+/// glTF files keep their own placement, OBJ, STL, PLY and mesh files are centered at the
+/// origin, in view of the camera, whatever units they were made in.
+///
+/// Every primitive gets simplified levels of detail, each instance is drawn with the one its
+/// size on screen asks for. Mesh files are uploaded as stored instead, at full detail only.
+pub struct ModelDemo {
+    path: PathBuf,
+    shader: Option<Shader>,
+    /// primitives of every glTF mesh
+    meshes: Vec<Vec<ModelPrimitive>>,
+    /// around all primitives of every glTF mesh, `None` for meshes without vertices
+    spheres: Vec<Option<BoundingSphere>>,
+    /// one per glTF texture, `None` for textures without an image
+    textures: Vec<Option<Texture>>,
+    instances: Vec<MeshInstance>,
+    triangle_count: usize,
+    /// triangles drawn last frame
+    triangles_drawn: usize,
+    /// draw the full detail of everything when off
+    use_lods: bool,
+}
+
+impl Default for ModelDemo {
+    fn default() -> Self {
+        ModelDemo::new(Path::new(DEFAULT_MODEL))
+    }
+}
+
+impl ModelDemo {
+    /// demo for any file `load_scene` can load, loaded once the demo is set up
+    pub fn new(path: &Path) -> ModelDemo {
+        ModelDemo {
+            path: path.to_path_buf(),
+            shader: None,
+            meshes: Vec::new(),
+            textures: Vec::new(),
+            spheres: Vec::new(),
+            instances: Vec::new(),
+            triangle_count: 0,
+            triangles_drawn: 0,
+            use_lods: true,
+        }
+    }
+
+    /// Textures, meshes with their levels of detail and instances of a scene.
+    ///
+    /// Textures that fail to load are reported and left out, a primitive that fails to upload
+    /// fails the whole scene.
+    unsafe fn upload_scene(&mut self, scene: &GltfScene) -> Result<(), String> {
+        self.textures = scene
+            .textures
+            .iter()
+            .map(|texture| {
+                match create_texture(&scene.images[texture.image?], &texture.sampler) {
+                    Ok(texture) => Some(texture),
+                    Err(e) => {
+                        eprintln!("{}, drawing without it", e);
+                        None
+                    }
+                }
+            }).collect();
+
+        // primitives go straight into the demo, so the ones uploaded before a failure are deleted with the rest
+        for mesh in &scene.meshes {
+            let name = mesh.name.as_ref().map_or("mesh", |name| name.as_str());
+            self.meshes.push(Vec::with_capacity(mesh.primitives.len()));
+            for primitive in &mesh.primitives {
+                let uploaded = upload_primitive(scene, name, primitive)?;
+                self.meshes.last_mut().unwrap().push(uploaded);
+            }
+        }
+        self.spheres = scene
+            .meshes
+            .iter()
+            .map(|mesh| {
+                let points = mesh.primitives.iter().flat_map(|primitive| primitive.data.positions.iter().cloned());
+                BoundingSphere::from_points(points)
+            }).collect();
+        self.instances = scene.instances();
+        self.triangle_count = 0;
+        for instance in &self.instances {
+            self.triangle_count += scene.meshes[instance.mesh]
+                .primitives
+                .iter()
+                .map(|primitive| primitive.data.triangle_count())
+                .sum::<usize>();
+        }
+        Ok(())
+    }
+
+    /// a mesh file as a single instance fit to the view, drawn at full detail with the default material
+    unsafe fn upload_mesh_file(&mut self) -> Result<(), String> {
+        let bytes = read_mesh_file(&self.path).map_err(|e| e.to_string())?;
+        let file = MeshFile::parse(&bytes).map_err(|e| e.to_string())?;
+        let mesh = file.upload().map_err(|e| e.to_string())?;
+        self.triangle_count = file.index_count() / 3;
+        self.meshes = vec![vec![ModelPrimitive {
+            lods: vec![mesh],
+            triangle_counts: vec![self.triangle_count],
+            triangle_ratios: vec![1.0],
+            material: PbrMaterial::default(),
+            has_colors: file.semantics().contains(&Semantic::Color),
+        }]];
+        self.spheres = vec![Some(file.bounding_sphere())];
+        self.instances = vec![MeshInstance {
+            mesh: 0,
+            node: 0,
+            transform: fit_transform(&file.bounds()),
+        }];
+        Ok(())
+    }
+
+    /// delete whatever of the model is on the GPU, leaving nothing to draw
+    unsafe fn delete_model(&mut self) {
+        for primitive in self.meshes.drain(..).flatten() {
+            for mesh in primitive.lods {
+                mesh.delete();
+            }
+        }
+        self.spheres.clear();
+        self.textures.clear();
+        self.instances.clear();
+        self.triangle_count = 0;
+    }
+}
+
+/// Load a model by the extension of its file.
+///
+/// glTF files come as they are, OBJ, STL, PLY and mesh files become a scene with a single node
+/// and get smooth normals if they have none.
+pub fn load_scene(path: &Path) -> Result<GltfScene, String> {
+    let name = path.file_stem().map(|stem| stem.to_string_lossy().into_owned());
+    match extension(path).as_deref() {
+        Some("gltf") | Some("glb") => load_gltf(path).map_err(|e| e.to_string()),
+        Some("obj") => {
+            let model = load_obj(path).map_err(|e| e.to_string())?;
+            let mut images = Vec::new();
+            let mut textures = Vec::new();
+            let materials = model
+                .materials
+                .iter()
+                .map(|material| PbrMaterial {
+                    name: Some(material.name.clone()),
+                    base_color_factor: material.diffuse.extend(material.dissolve),
+                    base_color_texture: material.diffuse_texture.as_ref().map(|texture| {
+                        images.push(ImageSource::File(texture.clone()));
+                        textures.push(GltfTexture {
+                            image: Some(images.len() - 1),
+                            sampler: Sampler::default(),
+                        });
+                        TextureRef {
+                            texture: textures.len() - 1,
+                            tex_coord: 0,
+                        }
+                    }),
+                    alpha_mode: if material.dissolve < 1.0 {
+                        AlphaMode::Blend
+                    } else {
+                        AlphaMode::Opaque
+                    },
+                    ..PbrMaterial::default()
+                }).collect();
+            let primitives = model
+                .meshes
+                .into_iter()
+                .map(|mesh| GltfPrimitive {
+                    material: mesh.material,
+                    data: mesh.data,
+                }).collect();
+            single_node_scene(name, primitives, materials, textures, images)
+        }
+        Some("stl") => {
+            let data = load_stl(path).map_err(|e| e.to_string())?;
+            single_node_scene(name, vec![GltfPrimitive { material: None, data }], Vec::new(), Vec::new(), Vec::new())
+        }
+        Some("ply") => {
+            let model = load_ply(path).map_err(|e| e.to_string())?;
+            if !model.properties.is_empty() {
+                let names: Vec<&str> = model.properties.iter().map(|property| property.name.as_str()).collect();
+                println!("{} has other vertex properties: {}", path.display(), names.join(", "));
+            }
+            let primitive = GltfPrimitive {
+                material: None,
+                data: model.data,
+            };
+            single_node_scene(name, vec![primitive], Vec::new(), Vec::new(), Vec::new())
+        }
+        Some(MESH_FILE_EXTENSION) => {
+            let bytes = read_mesh_file(path).map_err(|e| e.to_string())?;
+            let data = MeshFile::parse(&bytes).map_err(|e| e.to_string())?.to_mesh_data();
+            single_node_scene(name, vec![GltfPrimitive { material: None, data }], Vec::new(), Vec::new(), Vec::new())
+        }
+        _ => Err(format!("expected a .gltf, .glb, .obj, .stl, .ply or .{} file", MESH_FILE_EXTENSION)),
+    }
+}
+
+fn is_gltf(path: &Path) -> bool {
+    matches!(extension(path).as_deref(), Some("gltf") | Some("glb"))
+}
+
+/// lower case extension of a file
+fn extension(path: &Path) -> Option<String> {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_lowercase())
+}
+
+/// scene with a single untransformed node holding all the primitives, which get smooth normals if they have none
+fn single_node_scene(
+    name: Option<String>,
+    mut primitives: Vec<GltfPrimitive>,
+    materials: Vec<PbrMaterial>,
+    textures: Vec<GltfTexture>,
+    images: Vec<ImageSource>,
+) -> Result<GltfScene, String> {
+    for primitive in primitives.iter_mut() {
+        if !primitive.data.has(Semantic::Normal) {
+            generate_normals(&mut primitive.data, SMOOTHING_ANGLE).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(GltfScene {
+        nodes: vec![GltfNode {
+            name: name.clone(),
+            local: Matrix4::identity(),
+            children: Vec::new(),
+            mesh: Some(0),
+        }],
+        roots: vec![0],
+        meshes: vec![GltfMesh { name, primitives }],
+        materials,
+        textures,
+        images,
+    })
+}
+
+/// transform that moves and scales `bounds` to fit `FIT_SIZE` around the origin
+fn fit_transform(bounds: &Aabb) -> Matrix4<f32> {
+    let half_extents = bounds.half_extents();
+    let largest = half_extents.x.max(half_extents.y).max(half_extents.z);
+    let scale = if largest > 0.0 { FIT_SIZE * 0.5 / largest } else { 1.0 };
+    Matrix4::from_scale(scale) * Matrix4::from_translation(-bounds.center().to_vec())
+}
+
+/// move and scale the roots of a scene so all of it fits `FIT_SIZE` around the origin
+fn fit_to_view(scene: &mut GltfScene) {
+    let corners: Vec<Point3<f32>> = scene
+        .instances()
+        .iter()
+        .flat_map(|instance| {
+            scene.meshes[instance.mesh]
+                .primitives
+                .iter()
+                .filter_map(|primitive| primitive.data.bounds())
+                .flat_map(move |bounds| bounds.transform(&instance.transform).corners().to_vec())
+        }).collect();
+    let bounds = match Aabb::from_points(corners) {
+        Some(bounds) => bounds,
+        None => return,
+    };
+    let fit = fit_transform(&bounds);
+    for &root in &scene.roots {
+        scene.nodes[root].local = fit * scene.nodes[root].local;
+    }
+}
+
+/// optimized levels of detail of a glTF primitive, uploaded with the material it's drawn with
+unsafe fn upload_primitive(scene: &GltfScene, name: &str, primitive: &GltfPrimitive) -> Result<ModelPrimitive, String> {
+    let mut data = primitive.data.clone();
+    // glTF asks for flat shading when a primitive comes without normals,
+    // the other formats have them by now
+    if !data.has(Semantic::Normal) {
+        generate_normals(&mut data, Deg(0.0)).map_err(|e| format!("{}: {}", name, e))?;
+    }
+    let report = optimize(&mut data).map_err(|e| format!("{}: {}", name, e))?;
+    println!("Optimized {}: {}", name, report);
+    let chain = lod_chain(&data, &LOD_RATIOS).map_err(|e| format!("{}: {}", name, e))?;
+    let triangle_counts: Vec<usize> = chain.iter().map(|lod| lod.triangle_count()).collect();
+    println!(
+        "Levels of detail of {}: {} triangles",
+        name,
+        triangle_counts
+            .iter()
+            .map(|count| count.to_string())
+            .collect::<Vec<String>>()
+            .join(", ")
+    );
+    let mut lods = Vec::with_capacity(chain.len());
+    for lod in &chain {
+        match lod.upload(&lod.default_attributes()) {
+            Ok(mesh) => lods.push(mesh),
+            Err(e) => {
+                for mesh in lods {
+                    mesh.delete();
+                }
+                return Err(format!("{}: {}", name, e));
+            }
+        }
+    }
+    Ok(ModelPrimitive {
+        lods,
+        triangle_ratios: triangle_counts
+            .iter()
+            .map(|&count| count as f32 / triangle_counts[0].max(1) as f32)
+            .collect(),
+        triangle_counts,
+        material: scene.material(primitive.material),
+        has_colors: data.has(Semantic::Color),
+    })
+}
+
+/// texture from an image file or an encoded image, with the wrap modes and filters of a glTF sampler
+unsafe fn create_texture(source: &ImageSource, sampler: &Sampler) -> Result<Texture, String> {
+    let img = match source {
+        ImageSource::File(path) => {
+            image::open(path).map_err(|e| format!("Failed to load texture {}: {}", path.display(), e))?
+        }
+        ImageSource::Embedded { data, .. } => {
+            image::load_from_memory(data).map_err(|e| format!("Failed to decode embedded texture: {}", e))?
+        }
+    };
+    Ok(Texture::from_image(&img, &texture_options(sampler)))
+}
+
+/// sampling options of a glTF sampler, which leaves filters it doesn't set to the renderer
+fn texture_options(sampler: &Sampler) -> TextureOptions {
+    let options = TextureOptions::default()
+        .wrap_s(Wrap::from_gl(sampler.wrap_s).unwrap_or(Wrap::Repeat))
+        .wrap_t(Wrap::from_gl(sampler.wrap_t).unwrap_or(Wrap::Repeat))
+        .anisotropy(TEXTURE_ANISOTROPY);
+    let options = match sampler.mag_filter {
+        Some(gl::NEAREST) => options.mag_filter(Filter::Nearest),
+        _ => options,
+    };
+    match sampler.min_filter {
+        Some(gl::NEAREST) => options.min_filter(Filter::Nearest).mipmaps(false),
+        Some(gl::LINEAR) => options.mipmaps(false),
+        Some(gl::NEAREST_MIPMAP_NEAREST) => options.min_filter(Filter::Nearest).mipmap_filter(Filter::Nearest),
+        Some(gl::LINEAR_MIPMAP_NEAREST) => options.mipmap_filter(Filter::Nearest),
+        Some(gl::NEAREST_MIPMAP_LINEAR) => options.min_filter(Filter::Nearest),
+        _ => options,
+    }
+}
+
+impl Demo for ModelDemo {
+    fn name(&self) -> &'static str {
+        "model"
+    }
+
+    unsafe fn setup(&mut self) {
+        // mesh files go to the GPU as they are stored, everything else through a scene
+        let uploaded = if extension(&self.path).as_deref() == Some(MESH_FILE_EXTENSION) {
+            self.upload_mesh_file()
+        } else {
+            load_scene(&self.path).and_then(|mut scene| {
+                // glTF scenes are in meters and placed on purpose, other files can be in any unit
+                if !is_gltf(&self.path) {
+                    fit_to_view(&mut scene);
+                }
+                self.upload_scene(&scene)
+            })
+        };
+        // the demo stays empty rather than taking the other demos down with it
+        if let Err(e) = uploaded {
+            eprintln!("Failed to load model {}: {}", self.path.display(), e);
+            self.delete_model();
+        }
+
+        let shader = Shader::new(Path::new("src/shaders/model.vs"), Path::new("src/shaders/model.fs"));
+        shader.use_program();
+        shader.set_int(&CString::new("baseColorTexture").unwrap(), BASE_COLOR_UNIT.sampler());
+        self.shader = Some(shader);
+    }
+
+    fn key_pressed(&mut self, key: VirtualKeyCode) {
+        if key == VirtualKeyCode::L {
+            self.use_lods = !self.use_lods;
+        }
+    }
+
+    unsafe fn render(&mut self, context: &RenderContext) {
+        let shader = self.shader.as_ref().unwrap();
+        shader.use_program();
+
+        let view: Matrix4<f32> = context.camera.view_matrix();
+        let projection: Matrix4<f32> = context.camera.projection_matrix(context.aspect_ratio);
+        shader.set_matrix4(&CString::new("view").unwrap(), &view);
+        shader.set_matrix4(&CString::new("projection").unwrap(), &projection);
+
+        self.triangles_drawn = 0;
+        for instance in &self.instances {
+            shader.set_matrix4(&CString::new("model").unwrap(), &instance.transform);
+            let projected_size = match self.spheres[instance.mesh] {
+                Some(sphere) if self.use_lods => context.camera.projected_size(&sphere.transform(&instance.transform)),
+                _ => f32::INFINITY,
+            };
+            for primitive in &self.meshes[instance.mesh] {
+                let lod = select_lod(projected_size, &primitive.triangle_ratios);
+                let material = &primitive.material;
+                let color = material.base_color_factor;
+                shader.set_vec4(&CString::new("baseColorFactor").unwrap(), color.x, color.y, color.z, color.w);
+
+                let texture = material
+                    .base_color_texture
+                    .and_then(|texture| self.textures[texture.texture].as_ref());
+                match texture {
+                    Some(texture) => texture.bind(BASE_COLOR_UNIT),
+                    None => BASE_COLOR_UNIT.unbind(),
+                }
+                shader.set_bool(&CString::new("hasBaseColorTexture").unwrap(), texture.is_some());
+                shader.set_bool(&CString::new("hasColors").unwrap(), primitive.has_colors);
+
+                // a negative cutoff keeps every fragment
+                let alpha_cutoff = match material.alpha_mode {
+                    AlphaMode::Mask => material.alpha_cutoff,
+                    AlphaMode::Opaque | AlphaMode::Blend => -1.0,
+                };
+                shader.set_float(&CString::new("alphaCutoff").unwrap(), alpha_cutoff);
+                if material.alpha_mode == AlphaMode::Blend {
+                    gl::Enable(gl::BLEND);
+                    gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+                }
+
+                primitive.lods[lod].draw();
+                self.triangles_drawn += primitive.triangle_counts[lod];
+
+                if material.alpha_mode == AlphaMode::Blend {
+                    gl::Disable(gl::BLEND);
+                }
+            }
+        }
+        BASE_COLOR_UNIT.unbind();
+    }
+
+    unsafe fn teardown(&mut self) {
+        self.delete_model();
+        if let Some(shader) = self.shader.take() {
+            shader.delete();
+        }
+    }
+
+    fn status(&self) -> String {
+        format!(
+            "{}: {} instances, {} of {} triangles drawn{}",
+            self.path.display(),
+            self.instances.len(),
+            self.triangles_drawn,
+            self.triangle_count,
+            if self.use_lods { "" } else { " (levels of detail off)" }
+        )
+    }
+}
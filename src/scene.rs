@@ -0,0 +1,445 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use gl;
+use glutin::ElementState::Pressed;
+use glutin::{Event, KeyboardInput, VirtualKeyCode, WindowEvent};
+
+use demo::{Demo, RenderContext, DEMOS};
+use utils::render_target::RenderTarget;
+use utils::screen_quad::ScreenQuad;
+
+const DEMO_FADE: Duration = Duration::from_millis(500);
+
+/// What the scene stack should do after a scene handled an event or update.
+pub enum Transition {
+    None,
+    /// put a scene on top of the current one
+    Push(Box<dyn Scene>),
+    /// remove the current scene, the application quits once the stack is empty
+    Pop,
+    /// swap the current scene for another one
+    Replace(Box<dyn Scene>),
+    /// swap the current scene for another one, fading from one to the other
+    CrossFade(Box<dyn Scene>, Duration),
+}
+
+/// A layer of the application, managed by a `SceneStack`.
+///
+/// Only the top scene receives events and fixed updates. Scenes below an overlay keep
+/// being rendered underneath it, but are frozen until the overlay is popped.
+pub trait Scene {
+    /// the scene became part of the stack, create GL resources here
+    unsafe fn on_enter(&mut self) {}
+
+    /// the scene left the stack, delete GL resources here
+    unsafe fn on_exit(&mut self) {}
+
+    fn handle_event(&mut self, _event: &Event) -> Transition {
+        Transition::None
+    }
+
+    fn fixed_update(&mut self, _step: Duration) -> Transition {
+        Transition::None
+    }
+
+    unsafe fn render(&mut self, context: &RenderContext);
+
+    /// overlays are drawn on top of the scene below them instead of replacing it
+    fn is_overlay(&self) -> bool {
+        false
+    }
+
+    /// extra information shown in the window title
+    fn status(&self) -> String {
+        String::new()
+    }
+}
+
+struct Fade {
+    outgoing: Box<dyn Scene>,
+    duration: Duration,
+    elapsed: Duration,
+    target: RenderTarget,
+}
+
+/// Stack of scenes, applying the transitions they return.
+pub struct SceneStack {
+    scenes: Vec<Box<dyn Scene>>,
+    fade: Option<Fade>,
+    /// created with the first cross-fade, which is the only thing drawing with it
+    screen_quad: Option<ScreenQuad>,
+}
+
+impl SceneStack {
+    pub unsafe fn new(mut initial: Box<dyn Scene>) -> SceneStack {
+        initial.on_enter();
+        SceneStack {
+            scenes: vec![initial],
+            fade: None,
+            screen_quad: None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scenes.is_empty()
+    }
+
+    pub unsafe fn handle_event(&mut self, event: &Event) {
+        let transition = match self.scenes.last_mut() {
+            Some(scene) => scene.handle_event(event),
+            None => return,
+        };
+        self.apply(transition);
+    }
+
+    pub unsafe fn fixed_update(&mut self, step: Duration) {
+        let transition = match self.scenes.last_mut() {
+            Some(scene) => scene.fixed_update(step),
+            None => return,
+        };
+        self.apply(transition);
+    }
+
+    /// let real time pass for a running cross-fade
+    pub unsafe fn advance_fade(&mut self, elapsed: Duration) {
+        let finished = match self.fade {
+            Some(ref mut fade) => {
+                fade.elapsed += elapsed;
+                fade.elapsed >= fade.duration
+            }
+            None => false,
+        };
+        if finished {
+            self.finish_fade();
+        }
+    }
+
+    /// end a running cross-fade, leaving only the new scene
+    pub unsafe fn finish_fade(&mut self) {
+        if let Some(mut fade) = self.fade.take() {
+            fade.outgoing.on_exit();
+            fade.target.delete();
+        }
+    }
+
+    pub unsafe fn apply(&mut self, transition: Transition) {
+        match transition {
+            Transition::None => (),
+            Transition::Push(mut scene) => {
+                scene.on_enter();
+                self.scenes.push(scene);
+            }
+            Transition::Pop => {
+                if let Some(mut scene) = self.scenes.pop() {
+                    scene.on_exit();
+                }
+            }
+            Transition::Replace(mut scene) => {
+                if let Some(mut old) = self.scenes.pop() {
+                    old.on_exit();
+                }
+                scene.on_enter();
+                self.scenes.push(scene);
+            }
+            Transition::CrossFade(mut scene, duration) => {
+                // a fade still running is cut short
+                self.finish_fade();
+                if self.screen_quad.is_none() {
+                    self.screen_quad = Some(ScreenQuad::new());
+                }
+                scene.on_enter();
+                if let Some(outgoing) = self.scenes.pop() {
+                    self.fade = Some(Fade {
+                        outgoing,
+                        duration,
+                        elapsed: Duration::new(0, 0),
+                        target: RenderTarget::with_viewport_size(),
+                    });
+                }
+                self.scenes.push(scene);
+            }
+        }
+    }
+
+    pub unsafe fn render(&mut self, context: &RenderContext) {
+        match self.fade {
+            Some(ref mut fade) => {
+                // old scene to the window, new scene offscreen, then blend the new one over the old
+                fade.outgoing.render(context);
+
+                fade.target.bind();
+                gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+                render_visible(&mut self.scenes, context);
+                fade.target.unbind();
+
+                let alpha = fade.elapsed.as_secs_f32() / fade.duration.as_secs_f32();
+                if let Some(ref screen_quad) = self.screen_quad {
+                    screen_quad.draw_texture(fade.target.color_texture(), alpha.min(1.0));
+                }
+            }
+            None => render_visible(&mut self.scenes, context),
+        }
+    }
+
+    /// status of every visible scene, for the window title
+    pub fn status(&self) -> String {
+        let first_visible = first_visible(&self.scenes);
+        self.scenes[first_visible..]
+            .iter()
+            .map(|scene| scene.status())
+            .filter(|status| !status.is_empty())
+            .collect::<Vec<String>>()
+            .join(" - ")
+    }
+
+    /// exit every scene and delete the stack's own GL resources
+    pub unsafe fn delete(mut self) {
+        self.finish_fade();
+        while let Some(mut scene) = self.scenes.pop() {
+            scene.on_exit();
+        }
+        if let Some(screen_quad) = self.screen_quad.take() {
+            screen_quad.delete();
+        }
+    }
+}
+
+/// index of the lowest scene that isn't hidden by a scene above it
+fn first_visible(scenes: &[Box<dyn Scene>]) -> usize {
+    let mut first = scenes.len().saturating_sub(1);
+    while first > 0 && scenes[first].is_overlay() {
+        first -= 1;
+    }
+    first
+}
+
+unsafe fn render_visible(scenes: &mut [Box<dyn Scene>], context: &RenderContext) {
+    let first = first_visible(scenes);
+    for (i, scene) in scenes.iter_mut().enumerate().skip(first) {
+        if i > first {
+            // overlays only see the color of the scenes below, not their depth
+            gl::Clear(gl::DEPTH_BUFFER_BIT);
+        }
+        scene.render(context);
+    }
+}
+
+/// key presses, ignoring everything else
+fn pressed_key(event: &Event) -> Option<VirtualKeyCode> {
+    match event {
+        Event::WindowEvent {
+            event:
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            virtual_keycode: Some(key),
+                            state: Pressed,
+                            ..
+                        },
+                    ..
+                },
+            ..
+        } => Some(*key),
+        _ => None,
+    }
+}
+
+/// Runs one of the tutorial demos. Page up/down cross-fade to the previous/next demo,
+/// home switches straight back to the first one and tab pauses it behind an overlay.
+pub struct DemoScene {
+    index: usize,
+    demo: Box<dyn Demo>,
+    /// model file shown by the model demo, kept when switching demos
+    model: PathBuf,
+}
+
+impl DemoScene {
+    /// scene for the demo at `index` in `DEMOS`, wrapping around
+    pub fn new(index: usize, model: &Path) -> DemoScene {
+        let index = index % DEMOS.len();
+        DemoScene {
+            index,
+            demo: (DEMOS[index].1)(model),
+            model: model.to_path_buf(),
+        }
+    }
+
+    /// scene for the demo called `name`
+    pub fn with_name(name: &str, model: &Path) -> Option<DemoScene> {
+        DEMOS
+            .iter()
+            .position(|&(demo, _)| demo == name)
+            .map(|index| DemoScene::new(index, model))
+    }
+}
+
+impl Scene for DemoScene {
+    unsafe fn on_enter(&mut self) {
+        self.demo.setup();
+    }
+
+    unsafe fn on_exit(&mut self) {
+        self.demo.teardown();
+    }
+
+    fn handle_event(&mut self, event: &Event) -> Transition {
+        match pressed_key(event) {
+            Some(VirtualKeyCode::PageUp) => Transition::CrossFade(
+                Box::new(DemoScene::new(self.index + DEMOS.len() - 1, &self.model)),
+                DEMO_FADE,
+            ),
+            Some(VirtualKeyCode::PageDown) => {
+                Transition::CrossFade(Box::new(DemoScene::new(self.index + 1, &self.model)), DEMO_FADE)
+            }
+            Some(VirtualKeyCode::Home) => Transition::Replace(Box::new(DemoScene::new(0, &self.model))),
+            Some(VirtualKeyCode::Tab) => Transition::Push(Box::new(PauseOverlay::default())),
+            Some(key) => {
+                self.demo.key_pressed(key);
+                Transition::None
+            }
+            None => Transition::None,
+        }
+    }
+
+    fn fixed_update(&mut self, step: Duration) -> Transition {
+        self.demo.update(step);
+        Transition::None
+    }
+
+    unsafe fn render(&mut self, context: &RenderContext) {
+        self.demo.render(context);
+    }
+
+    fn status(&self) -> String {
+        format!("{} {}", self.demo.name(), self.demo.status())
+    }
+}
+
+/// Darkens the scene below it, which stays frozen until tab is pressed again.
+#[derive(Default)]
+pub struct PauseOverlay {
+    screen_quad: Option<ScreenQuad>,
+}
+
+impl Scene for PauseOverlay {
+    unsafe fn on_enter(&mut self) {
+        self.screen_quad = Some(ScreenQuad::new());
+    }
+
+    unsafe fn on_exit(&mut self) {
+        if let Some(screen_quad) = self.screen_quad.take() {
+            screen_quad.delete();
+        }
+    }
+
+    fn handle_event(&mut self, event: &Event) -> Transition {
+        match pressed_key(event) {
+            Some(VirtualKeyCode::Tab) => Transition::Pop,
+            _ => Transition::None,
+        }
+    }
+
+    unsafe fn render(&mut self, _context: &RenderContext) {
+        if let Some(ref screen_quad) = self.screen_quad {
+            screen_quad.draw_color(0.0, 0.0, 0.0, 0.6);
+        }
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+
+    fn status(&self) -> String {
+        "paused (tab to resume)".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// scene writing its hooks into a shared log, returning `transition` from its next update
+    struct Recorder {
+        name: &'static str,
+        log: Rc<RefCell<Vec<String>>>,
+        transition: Option<Transition>,
+    }
+
+    impl Recorder {
+        fn new(name: &'static str, log: &Rc<RefCell<Vec<String>>>) -> Box<Recorder> {
+            Box::new(Recorder {
+                name,
+                log: log.clone(),
+                transition: None,
+            })
+        }
+
+        fn then(mut self: Box<Self>, transition: Transition) -> Box<Recorder> {
+            self.transition = Some(transition);
+            self
+        }
+
+        fn record(&self, hook: &str) {
+            self.log.borrow_mut().push(format!("{} {}", self.name, hook));
+        }
+    }
+
+    impl Scene for Recorder {
+        unsafe fn on_enter(&mut self) {
+            self.record("enter");
+        }
+
+        unsafe fn on_exit(&mut self) {
+            self.record("exit");
+        }
+
+        fn fixed_update(&mut self, _step: Duration) -> Transition {
+            self.record("update");
+            self.transition.take().unwrap_or(Transition::None)
+        }
+
+        unsafe fn render(&mut self, _context: &RenderContext) {}
+    }
+
+    #[test]
+    fn hooks_run_in_order() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let step = Duration::from_millis(16);
+        unsafe {
+            let menu = Recorder::new("menu", &log).then(Transition::Pop);
+            let game = Recorder::new("game", &log).then(Transition::Replace(Recorder::new("credits", &log)));
+            let title = Recorder::new("title", &log).then(Transition::Push(menu));
+            let mut stack = SceneStack::new(title);
+            // only the top scene updates: title pushes menu, menu pops itself, title is back on top
+            stack.fixed_update(step);
+            stack.fixed_update(step);
+            stack.fixed_update(step);
+            stack.apply(Transition::Replace(game));
+            stack.fixed_update(step);
+            stack.fixed_update(step);
+            stack.apply(Transition::Pop);
+            assert!(stack.is_empty());
+        }
+        assert_eq!(
+            *log.borrow(),
+            vec![
+                "title enter",
+                "title update",
+                "menu enter",
+                "menu update",
+                "menu exit",
+                "title update",
+                "title exit",
+                "game enter",
+                "game update",
+                "game exit",
+                "credits enter",
+                "credits update",
+                "credits exit",
+            ]
+        );
+    }
+}
@@ -7,17 +7,8 @@ use std::os::raw::c_void;
 use gl;
 use gl::types::{GLchar, GLfloat, GLint, GLsizei, GLsizeiptr, GLuint};
 
-const VERTEX_SHADER_SOURCE: &str = r#"
-    #version 330 core
-    layout (location = 0) in vec3 aPos; // the position variable has attribute position 0
-  
-    out vec4 vertexColor; // specify a color output to the fragment shader
-
-    void main() {
-        gl_Position = vec4(aPos, 1.0); // see how we directly give a vec3 to vec4's constructor
-        vertexColor = vec4(0.5, 0.0, 0.0, 1.0); // set the output variable to a dark-red color
-    }
-"#;
+use demo::{delete_vertex_array, Demo, RenderContext};
+use triangle;
 
 const VERTEX_SHADER_SOURCE_UNIFORM: &str = r#"
     #version 330 core
@@ -41,17 +32,6 @@ const VERTEX_SHADER_SOURCE_WITH_COLOR: &str = r#"
     }
 "#;
 
-const FRAGMENT_SHADER_SOURCE: &str = r#"
-    #version 330 core
-    out vec4 FragColor;
-  
-    in vec4 vertexColor; // the input variable from the vertex shader (same name and same type)  
-
-    void main() {
-        FragColor = vertexColor;
-    } 
-"#;
-
 const FRAGMENT_SHADER_SOURCE_UNIFORM: &str = r#"
     #version 330 core
     out vec4 FragColor;
@@ -73,54 +53,6 @@ const FRAGMENT_SHADER_SOURCE_WITH_COLOR: &str = r#"
     } 
 "#;
 
-pub unsafe fn create_shader_program() -> GLuint {
-    // build and compile our shader program
-    // ------------------------------------
-    // vertex shader
-    let vertex_shader = gl::CreateShader(gl::VERTEX_SHADER);
-    let c_str_vert = CString::new(VERTEX_SHADER_SOURCE.as_bytes()).unwrap();
-    gl::ShaderSource(vertex_shader, 1, &c_str_vert.as_ptr(), ptr::null());
-    gl::CompileShader(vertex_shader);
-
-    // check for shader compile errors
-    let mut success = gl::FALSE as GLint;
-    let mut info_log = Vec::with_capacity(512);
-    info_log.set_len(512 - 1); // subtract 1 to skip the trailing null character
-    gl::GetShaderiv(vertex_shader, gl::COMPILE_STATUS, &mut success);
-    if success != gl::TRUE as GLint {
-        gl::GetShaderInfoLog(vertex_shader, 512,ptr::null_mut(), info_log.as_mut_ptr() as *mut GLchar);
-        println!("ERROR::SHADER::VERTEX::COMPILATION_FAILED\n{}", str::from_utf8(&info_log).unwrap());
-    }
-
-    // fragment shader
-    let fragment_shader = gl::CreateShader(gl::FRAGMENT_SHADER);
-    let c_str_frag = CString::new(FRAGMENT_SHADER_SOURCE.as_bytes()).unwrap();
-    gl::ShaderSource(fragment_shader, 1, &c_str_frag.as_ptr(), ptr::null());
-    gl::CompileShader(fragment_shader);
-    // check for shader compile errors
-    gl::GetShaderiv(fragment_shader, gl::COMPILE_STATUS, &mut success);
-    if success != gl::TRUE as GLint {
-        gl::GetShaderInfoLog(fragment_shader, 512, ptr::null_mut(), info_log.as_mut_ptr() as *mut GLchar);
-        println!("ERROR::SHADER::FRAGMENT::COMPILATION_FAILED\n{}", str::from_utf8(&info_log).unwrap());
-    }
-
-    // link shaders
-    let shader_program = gl::CreateProgram();
-    gl::AttachShader(shader_program, vertex_shader);
-    gl::AttachShader(shader_program, fragment_shader);
-    gl::LinkProgram(shader_program);
-    // check for linking errors
-    gl::GetProgramiv(shader_program, gl::LINK_STATUS, &mut success);
-    if success != gl::TRUE as GLint {
-        gl::GetProgramInfoLog(shader_program, 512, ptr::null_mut(), info_log.as_mut_ptr() as *mut GLchar);
-        println!("ERROR::SHADER::PROGRAM::COMPILATION_FAILED\n{}", str::from_utf8(&info_log).unwrap());
-    }
-    gl::DeleteShader(vertex_shader);
-    gl::DeleteShader(fragment_shader);
-
-    return shader_program;
-}
-
 pub unsafe fn create_shader_program_uniform() -> GLuint {
     // build and compile our shader program
     // ------------------------------------
@@ -132,8 +64,7 @@ pub unsafe fn create_shader_program_uniform() -> GLuint {
 
     // check for shader compile errors
     let mut success = gl::FALSE as GLint;
-    let mut info_log = Vec::with_capacity(512);
-    info_log.set_len(512 - 1); // subtract 1 to skip the trailing null character
+    let mut info_log = vec![0u8; 512];
     gl::GetShaderiv(vertex_shader, gl::COMPILE_STATUS, &mut success);
     if success != gl::TRUE as GLint {
         gl::GetShaderInfoLog(vertex_shader, 512,ptr::null_mut(), info_log.as_mut_ptr() as *mut GLchar);
@@ -166,7 +97,7 @@ pub unsafe fn create_shader_program_uniform() -> GLuint {
     gl::DeleteShader(vertex_shader);
     gl::DeleteShader(fragment_shader);
 
-    return shader_program;
+    shader_program
 }
 
 pub unsafe fn create_shader_program_with_color() -> GLuint {
@@ -180,8 +111,7 @@ pub unsafe fn create_shader_program_with_color() -> GLuint {
 
     // check for shader compile errors
     let mut success = gl::FALSE as GLint;
-    let mut info_log = Vec::with_capacity(512);
-    info_log.set_len(512 - 1); // subtract 1 to skip the trailing null character
+    let mut info_log = vec![0u8; 512];
     gl::GetShaderiv(vertex_shader, gl::COMPILE_STATUS, &mut success);
     if success != gl::TRUE as GLint {
         gl::GetShaderInfoLog(vertex_shader, 512,ptr::null_mut(), info_log.as_mut_ptr() as *mut GLchar);
@@ -214,7 +144,7 @@ pub unsafe fn create_shader_program_with_color() -> GLuint {
     gl::DeleteShader(vertex_shader);
     gl::DeleteShader(fragment_shader);
 
-    return shader_program;
+    shader_program
 }
 
 pub unsafe fn create_vertex_array_object_with_colors() -> GLuint {
@@ -256,5 +186,69 @@ pub unsafe fn create_vertex_array_object_with_colors() -> GLuint {
     // uncomment this call to draw in wireframe polygons.
     // gl::PolygonMode(gl::FRONT_AND_BACK, gl::LINE);
 
-    return vao;
+    vao
+}
+
+/// triangle colored through a uniform that pulses with the simulation time
+#[derive(Default)]
+pub struct UniformColorDemo {
+    shader_program: GLuint,
+    vao: GLuint,
+}
+
+impl Demo for UniformColorDemo {
+    fn name(&self) -> &'static str {
+        "uniform-color"
+    }
+
+    unsafe fn setup(&mut self) {
+        self.shader_program = create_shader_program_uniform();
+        self.vao = triangle::create_vertex_array_object();
+    }
+
+    unsafe fn render(&mut self, context: &RenderContext) {
+        gl::UseProgram(self.shader_program);
+        // update the uniform color
+        let green_value = context.time.sin() / 2.0 + 0.5;
+        let our_color = CString::new("ourColor").unwrap();
+        let vertex_color_location = gl::GetUniformLocation(self.shader_program, our_color.as_ptr());
+        gl::Uniform4f(vertex_color_location, 0.0, green_value, 0.0, 1.0);
+
+        gl::BindVertexArray(self.vao);
+        gl::DrawArrays(gl::TRIANGLES, 0, 3);
+    }
+
+    unsafe fn teardown(&mut self) {
+        delete_vertex_array(self.vao);
+        gl::DeleteProgram(self.shader_program);
+    }
+}
+
+/// triangle with a color per vertex, interpolated across the face
+#[derive(Default)]
+pub struct VertexColorsDemo {
+    shader_program: GLuint,
+    vao: GLuint,
+}
+
+impl Demo for VertexColorsDemo {
+    fn name(&self) -> &'static str {
+        "vertex-colors"
+    }
+
+    unsafe fn setup(&mut self) {
+        self.shader_program = create_shader_program_with_color();
+        self.vao = create_vertex_array_object_with_colors();
+    }
+
+    unsafe fn render(&mut self, _context: &RenderContext) {
+        gl::UseProgram(self.shader_program);
+        gl::BindVertexArray(self.vao);
+        gl::DrawArrays(gl::TRIANGLES, 0, 3);
+    }
+
+    unsafe fn teardown(&mut self) {
+        delete_vertex_array(self.vao);
+        gl::DeleteProgram(self.shader_program);
+    }
 }
@@ -2,10 +2,14 @@
 out vec4 FragColor;
   
 in vec2 TexCoord;
+in vec4 Color;
 
 uniform sampler2D texture_1;
 uniform sampler2D texture_2;
+uniform float time; // simulation time in seconds, stands still while paused
 
 void main() {
-    FragColor = mix(texture(texture_1, TexCoord), texture(texture_2, TexCoord), 0.5);
+    // fade back and forth between the two textures
+    float amount = 0.5 + 0.3 * sin(time);
+    FragColor = mix(texture(texture_1, TexCoord), texture(texture_2, TexCoord), amount) * Color;
 }
\ No newline at end of file
@@ -3,6 +3,7 @@ layout (location = 0) in vec3 aPos;
 layout (location = 1) in vec2 aTexCoord;
 
 out vec2 TexCoord;
+out vec4 Color;
 
 uniform mat4 model;
 uniform mat4 view;
@@ -11,4 +12,5 @@ uniform mat4 projection;
 void main() {
     gl_Position = projection * view * model * vec4(aPos, 1.0);
     TexCoord = aTexCoord;
+    Color = vec4(1.0);
 }
\ No newline at end of file
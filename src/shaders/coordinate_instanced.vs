@@ -0,0 +1,17 @@
+#version 330 core
+layout (location = 0) in vec3 aPos;
+layout (location = 1) in vec2 aTexCoord;
+layout (location = 5) in mat4 aModel; // per instance, takes locations 5 to 8
+layout (location = 9) in vec4 aColor; // per instance
+
+out vec2 TexCoord;
+out vec4 Color;
+
+uniform mat4 view;
+uniform mat4 projection;
+
+void main() {
+    gl_Position = projection * view * aModel * vec4(aPos, 1.0);
+    TexCoord = aTexCoord;
+    Color = aColor;
+}
\ No newline at end of file
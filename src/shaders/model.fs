@@ -0,0 +1,31 @@
+#version 330 core
+out vec4 FragColor;
+
+in vec2 TexCoord;
+in vec3 Normal;
+in vec4 Color;
+
+uniform vec4 baseColorFactor;
+uniform sampler2D baseColorTexture;
+uniform bool hasBaseColorTexture;
+uniform bool hasColors;
+uniform float alphaCutoff; // fragments below are discarded, negative keeps all
+
+const vec3 lightDirection = normalize(vec3(0.4, 1.0, 0.3));
+
+void main() {
+    vec4 color = baseColorFactor;
+    if (hasBaseColorTexture) {
+        color *= texture(baseColorTexture, TexCoord);
+    }
+    if (hasColors) {
+        color *= Color;
+    }
+    if (color.a < alphaCutoff) {
+        discard;
+    }
+
+    // simple directional light with some ambient
+    float light = 0.3 + 0.7 * max(dot(normalize(Normal), lightDirection), 0.0);
+    FragColor = vec4(color.rgb * light, color.a);
+}
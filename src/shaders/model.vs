@@ -0,0 +1,21 @@
+#version 330 core
+layout (location = 0) in vec3 aPos;
+layout (location = 1) in vec2 aTexCoord;
+layout (location = 2) in vec3 aNormal;
+layout (location = 4) in vec4 aColor;
+
+out vec2 TexCoord;
+out vec3 Normal;
+out vec4 Color;
+
+uniform mat4 model;
+uniform mat4 view;
+uniform mat4 projection;
+
+void main() {
+    gl_Position = projection * view * model * vec4(aPos, 1.0);
+    TexCoord = aTexCoord;
+    // normals stay perpendicular to the surface under non-uniform scaling
+    Normal = mat3(transpose(inverse(model))) * aNormal;
+    Color = aColor;
+}
@@ -0,0 +1,11 @@
+#version 330 core
+out vec4 FragColor;
+
+in vec2 TexCoord;
+
+uniform sampler2D screenTexture;
+uniform float alpha;
+
+void main() {
+    FragColor = vec4(texture(screenTexture, TexCoord).rgb, alpha);
+}
\ No newline at end of file
@@ -0,0 +1,308 @@
+use std::time::Duration;
+
+use cgmath::prelude::*;
+use cgmath::{vec3, Deg, Point3, Vector3};
+
+use utils::camera::{Camera, CameraTransition};
+use utils::camera_path::{CameraPath, CameraPathPlayer};
+use utils::input::{Input, MOUSE_SENSITIVITY};
+use utils::input_script::InputScript;
+use utils::interpolation::Snapshot;
+use utils::transform::Transform;
+
+// camera
+const CAMERA_SPEED: f32 = 5.0; // units per second
+const ROLL_SPEED: f32 = 90.0; // degrees per second in free look
+const VIEW_TRANSITION: Duration = Duration::from_millis(750);
+
+// world space positions of our cubes
+const CUBE_POSITIONS: [Vector3<f32>; 10] = [
+    Vector3 { x: 0.0, y: 0.0, z: 0.0 },
+    Vector3 { x: 2.0, y: 5.0, z: -15.0 },
+    Vector3 { x: -1.5, y: -2.2, z: -2.5 },
+    Vector3 { x: -3.8, y: -2.0, z: -12.3 },
+    Vector3 { x: 2.4, y: -0.4, z: -3.5 },
+    Vector3 { x: -1.7, y: 3.0, z: -7.5 },
+    Vector3 { x: 1.3, y: -2.0, z: -2.5 },
+    Vector3 { x: 1.5, y: 2.0, z: -2.5 },
+    Vector3 { x: 1.5, y: 0.2, z: -1.5 },
+    Vector3 { x: -1.3, y: 1.0, z: -1.5 },
+];
+
+// distance between neighbouring cubes of a stress grid
+const GRID_SPACING: f32 = 2.0;
+
+/// The camera driven by `Input`, a camera path or a transition between two views.
+///
+/// Mouse look is applied once per frame by `apply_input`, movement once per fixed update by `update`.
+pub struct CameraRig {
+    // camera before and after the last update, rendering interpolates between them
+    camera: Snapshot<Camera>,
+    initial: Camera,
+    transition: Option<CameraTransition>,
+    path_player: Option<CameraPathPlayer>,
+    // free look state seen by the last `apply_input`
+    free_look: bool,
+}
+
+impl CameraRig {
+    pub fn new(initial: Camera, camera_path: Option<CameraPath>) -> Self {
+        CameraRig {
+            camera: Snapshot::new(initial),
+            initial,
+            transition: None,
+            path_player: camera_path.map(CameraPathPlayer::new),
+            free_look: false,
+        }
+    }
+
+    pub fn current(&self) -> &Camera {
+        self.camera.current()
+    }
+
+    /// whether a camera path or view transition is moving the camera instead of the input
+    pub fn scripted(&self) -> bool {
+        self.transition.is_some() || self.path_player.as_ref().is_some_and(|player| !player.finished())
+    }
+
+    /// move smoothly from the current pose to `target`
+    pub fn transition_to(&mut self, target: Camera) {
+        self.transition = Some(CameraTransition::new(*self.camera.current(), target, VIEW_TRANSITION));
+    }
+
+    /// mouse look and view reset requests, applied once per frame
+    pub fn apply_input(&mut self, input: &mut Input) {
+        // mouse look, either around the camera's own axes or through the clamped yaw/pitch
+        let (x_delta, y_delta) = input.take_mouse_delta();
+        if input.free_look() {
+            self.camera
+                .current_mut()
+                .rotate_local(x_delta * MOUSE_SENSITIVITY, -y_delta * MOUSE_SENSITIVITY, 0.0);
+        } else {
+            if self.free_look {
+                // leaving free look drops any roll and continues from the current direction
+                let (yaw, pitch) = self.camera.current().yaw_pitch();
+                input.set_yaw(yaw);
+                input.set_pitch(pitch);
+            }
+            self.camera.current_mut().set_yaw_pitch(input.yaw(), input.pitch());
+        }
+        self.free_look = input.free_look();
+
+        if input.reset_view() {
+            input.set_reset_view(false);
+            let initial = self.initial;
+            self.transition_to(initial);
+        }
+    }
+
+    /// advance the camera by one fixed update step
+    pub fn update(&mut self, input: &mut Input, step: Duration) {
+        let seconds_per_update = step.as_secs_f32();
+        let camera_speed = CAMERA_SPEED * seconds_per_update;
+
+        self.camera.advance();
+
+        // a playing camera path or view transition replaces keyboard and mouse control
+        let mut scripted = false;
+        if let Some(ref mut player) = self.path_player {
+            if !player.finished() {
+                scripted = true;
+                player.update(step);
+                let pose = player.pose();
+                self.camera.current_mut().position = pose.position;
+                self.camera.current_mut().set_yaw_pitch(pose.yaw, pose.pitch);
+                input.set_yaw(pose.yaw);
+                input.set_pitch(pose.pitch);
+            }
+        }
+        if let Some(mut transition) = self.transition.take() {
+            transition.update(step);
+            *self.camera.current_mut() = transition.camera();
+            let (yaw, pitch) = self.camera.current().yaw_pitch();
+            input.set_yaw(yaw);
+            input.set_pitch(pitch);
+            if !transition.finished() {
+                self.transition = Some(transition);
+            }
+        } else if !scripted {
+            let camera = self.camera.current_mut();
+            if input.up() {
+                camera.position += camera_speed * camera.front();
+            }
+            if input.down() {
+                camera.position += -(camera_speed * camera.front());
+            }
+            if input.left() {
+                camera.position += -(camera.right() * camera_speed);
+            }
+            if input.right() {
+                camera.position += camera.right() * camera_speed;
+            }
+            if input.free_look() && input.roll_left() {
+                camera.rotate_local(0.0, 0.0, -ROLL_SPEED * seconds_per_update);
+            }
+            if input.free_look() && input.roll_right() {
+                camera.rotate_local(0.0, 0.0, ROLL_SPEED * seconds_per_update);
+            }
+        }
+    }
+
+    /// the camera to render with, `alpha` into the next update
+    pub fn render_camera(&self, alpha: f32) -> Camera {
+        let mut camera = self.camera.interpolated(alpha);
+        if !self.scripted() {
+            // mouse look is applied every frame, interpolating it would only add latency
+            camera.orientation = self.camera.current().orientation;
+        }
+        camera
+    }
+}
+
+/// The spinning cubes of the coordinate systems chapter.
+#[derive(Default)]
+pub struct CubeField {
+    // cube states before and after the last update, rendering interpolates between them
+    models: Snapshot<Vec<Transform>>,
+}
+
+impl CubeField {
+    pub fn new() -> Self {
+        CubeField {
+            models: Snapshot::new(
+                CUBE_POSITIONS
+                    .iter()
+                    .enumerate()
+                    .map(|(i, x)| {
+                        let mut transform = Transform::from_position(*x);
+                        transform.rotate_local(vec3(0.5, 1.0, 0.0).normalize(), Deg(i as f32 * 20.0));
+                        transform
+                    }).collect(),
+            ),
+        }
+    }
+
+    /// `count` cubes on a grid stretching away from the camera, for stress testing the renderer
+    pub fn grid(count: usize) -> Self {
+        let side = (count as f32).cbrt().ceil().max(1.0) as usize;
+        let offset = (side - 1) as f32 * GRID_SPACING * 0.5;
+        CubeField {
+            models: Snapshot::new(
+                (0..count)
+                    .map(|i| {
+                        let (x, y, z) = (i % side, i / side % side, i / (side * side));
+                        let position = vec3(
+                            x as f32 * GRID_SPACING - offset,
+                            y as f32 * GRID_SPACING - offset,
+                            -(z as f32) * GRID_SPACING - 5.0,
+                        );
+                        let mut transform = Transform::from_position(position);
+                        transform.rotate_local(vec3(0.5, 1.0, 0.0).normalize(), Deg(i as f32 * 20.0));
+                        transform
+                    }).collect(),
+            ),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.models.current().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.models.current().is_empty()
+    }
+
+    pub fn models(&self) -> &Snapshot<Vec<Transform>> {
+        &self.models
+    }
+
+    /// rotate every cube by one fixed update step
+    pub fn update(&mut self) {
+        self.models.advance();
+        for cube_model in self.models.current_mut().iter_mut() {
+            cube_model.rotate_local(vec3(0.5, 1.0, 0.0).normalize(), Deg(2.0));
+        }
+    }
+}
+
+/// The update step of the coordinate systems chapter without a window or GL context,
+/// advanced one fixed tick at a time.
+///
+/// Every tick applies the input like a frame would and then runs one fixed update.
+/// Pause and single steps freeze the cubes as in the windowed loop, there is no time
+/// scale because ticks are not tied to real time.
+pub struct Simulation {
+    pub input: Input,
+    pub camera: CameraRig,
+    pub cubes: CubeField,
+    step: Duration,
+    ticks: u64,
+}
+
+impl Simulation {
+    pub fn new(step: Duration, camera_path: Option<CameraPath>) -> Self {
+        let input = Input::new();
+        let initial_camera = Camera::from_yaw_pitch(Point3::new(0.0, 0.0, 3.0), input.yaw(), input.pitch());
+        Simulation {
+            input,
+            camera: CameraRig::new(initial_camera, camera_path),
+            cubes: CubeField::new(),
+            step,
+            ticks: 0,
+        }
+    }
+
+    /// number of ticks run so far
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    pub fn tick(&mut self) {
+        self.camera.apply_input(&mut self.input);
+        self.camera.update(&mut self.input, self.step);
+
+        // bookmarks live on disk, they are only available with a window
+        self.input.take_save_bookmark();
+        self.input.take_recall_bookmark();
+        self.input.take_time_scale_change();
+
+        // a step requested while running is used up, it mustn't fire once paused
+        let step = self.input.take_step();
+        if !self.input.paused() || step {
+            self.cubes.update();
+        }
+        self.ticks += 1;
+    }
+
+    /// run `ticks` more ticks, feeding in the script's input before each of them.
+    /// Stops early if the input asks to close.
+    pub fn run(&mut self, ticks: u64, script: &InputScript) {
+        for _ in 0..ticks {
+            for event in script.events_at(self.ticks) {
+                event.apply(&mut self.input);
+            }
+            if self.input.close() {
+                break;
+            }
+            self.tick();
+        }
+    }
+
+    /// human readable summary of the world state
+    pub fn report(&self) -> String {
+        let camera = self.camera.current();
+        let (yaw, pitch) = camera.yaw_pitch();
+        let mut report = format!(
+            "after {} ticks:\ncamera position ({:.3}, {:.3}, {:.3}) yaw {:.3} pitch {:.3}\n",
+            self.ticks, camera.position.x, camera.position.y, camera.position.z, yaw, pitch
+        );
+        for (i, cube) in self.cubes.models().current().iter().enumerate() {
+            let rotation = cube.rotation;
+            report += &format!(
+                "cube {} rotation ({:.4}, {:.4}, {:.4}, {:.4})\n",
+                i, rotation.s, rotation.v.x, rotation.v.y, rotation.v.z
+            );
+        }
+        report
+    }
+}
@@ -1,108 +1,115 @@
 use std::ffi::CString;
-use std::mem;
-use std::os::raw::c_void;
-use std::ptr;
-use std::str;
 use std::path::Path;
 
-use gl;
-use gl::types::{GLchar, GLfloat, GLint, GLsizei, GLsizeiptr, GLuint};
+use cgmath::{vec3, vec4, Matrix4, Rad};
 
-use image;
-use image::GenericImageView;
+use demo::{Demo, RenderContext};
+use utils::mesh::Mesh;
+use utils::mesh_data::Semantic;
+use utils::shader::Shader;
+use utils::shapes;
+use utils::texture::{Texture, TextureOptions, TextureUnit};
 
-pub unsafe fn create_vertex_array_object() -> GLuint {
-    let vertices: [f32; 32] = [
-        // positions     // colors        // texture coords
-        0.5, 0.5, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, // top right
-        0.5, -0.5, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, // bottom right
-        -0.5, -0.5, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, // bottom left
-        -0.5, 0.5, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, // top left
+/// textured rectangle with a different color in each corner, laid out as `texture.vs` expects
+pub unsafe fn create_rectangle_mesh() -> Mesh {
+    let mut rectangle = shapes::quad(1.0, 1.0);
+    rectangle.colors = vec![
+        vec4(0.0, 0.0, 1.0, 1.0), // bottom left
+        vec4(0.0, 1.0, 0.0, 1.0), // bottom right
+        vec4(1.0, 0.0, 0.0, 1.0), // top right
+        vec4(1.0, 1.0, 0.0, 1.0), // top left
     ];
+    rectangle
+        .upload(&[(Semantic::Position, 0), (Semantic::Color, 1), (Semantic::TexCoord, 2)])
+        .unwrap()
+}
 
-    let indices = [
-        0, 1, 3, // first Triangle
-        1, 2, 3, // second Triangle
-    ];
+/// texture from an image file, repeating and filtered linearly
+pub unsafe fn load_and_create_texture(file_path: &Path) -> Texture {
+    Texture::load(file_path, &TextureOptions::default()).expect("Failed to load texture")
+}
+
+/// textured rectangle, tinted by its vertex colors
+#[derive(Default)]
+pub struct TexturesDemo {
+    shader: Option<Shader>,
+    mesh: Option<Mesh>,
+    texture: Option<Texture>,
+}
+
+impl Demo for TexturesDemo {
+    fn name(&self) -> &'static str {
+        "textures"
+    }
+
+    unsafe fn setup(&mut self) {
+        self.shader = Some(Shader::new(
+            Path::new("src/shaders/texture.vs"),
+            Path::new("src/shaders/texture.fs"),
+        ));
+        self.mesh = Some(create_rectangle_mesh());
+        self.texture = Some(load_and_create_texture(Path::new("resources/crate.jpg")));
+    }
+
+    unsafe fn render(&mut self, _context: &RenderContext) {
+        self.texture.as_ref().unwrap().bind(TextureUnit(0));
 
-    let (mut vbo, mut vao, mut ebo) = (0, 0, 0);
-    gl::GenVertexArrays(1, &mut vao);
-    gl::GenBuffers(1, &mut vbo);
-    gl::GenBuffers(1, &mut ebo);
-
-    gl::BindVertexArray(vao);
-
-    gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
-    gl::BufferData(
-        gl::ARRAY_BUFFER,
-        (vertices.len() * mem::size_of::<GLfloat>()) as GLsizeiptr,
-        &vertices[0] as *const f32 as *const c_void,
-        gl::STATIC_DRAW,
-    );
-
-    gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo);
-    gl::BufferData(
-        gl::ELEMENT_ARRAY_BUFFER,
-        (indices.len() * mem::size_of::<GLfloat>()) as GLsizeiptr,
-        &indices[0] as *const i32 as *const c_void,
-        gl::STATIC_DRAW,
-    );
-
-    let stride = 8 * mem::size_of::<GLfloat>() as GLsizei;
-    // position attribute
-    gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, stride, ptr::null());
-    gl::EnableVertexAttribArray(0);
-    // color attribute
-    gl::VertexAttribPointer(
-        1,
-        3,
-        gl::FLOAT,
-        gl::FALSE,
-        stride,
-        (3 * mem::size_of::<GLfloat>()) as *const c_void,
-    );
-    gl::EnableVertexAttribArray(1);
-    // texture coord attribute
-    gl::VertexAttribPointer(
-        2,
-        2,
-        gl::FLOAT,
-        gl::FALSE,
-        stride,
-        (6 * mem::size_of::<GLfloat>()) as *const c_void,
-    );
-    gl::EnableVertexAttribArray(2);
-
-    return vao;
+        self.shader.as_ref().unwrap().use_program();
+        self.mesh.as_ref().unwrap().draw();
+    }
+
+    unsafe fn teardown(&mut self) {
+        if let Some(mesh) = self.mesh.take() {
+            mesh.delete();
+        }
+        self.texture = None;
+        if let Some(shader) = self.shader.take() {
+            shader.delete();
+        }
+    }
+}
+
+/// the textured rectangle spinning in the corner of the screen, from the transformations chapter
+#[derive(Default)]
+pub struct TransformDemo {
+    shader: Option<Shader>,
+    mesh: Option<Mesh>,
+    texture: Option<Texture>,
 }
 
-pub unsafe fn load_and_create_texture(file_path: &Path) -> GLuint {
-    let mut texture = 0;
-    gl::GenTextures(1, &mut texture);
-    gl::BindTexture(gl::TEXTURE_2D, texture); // all upcoming GL_TEXTURE_2D operations now have effect on this texture object
-                                              // set the texture wrapping parameters
-    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as i32); // set texture wrapping to gl::REPEAT (default wrapping method)
-    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
-    // set texture filtering parameters
-    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
-    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
-    // load image, create texture and generate mipmaps
-    let img = image::open(file_path).expect("Failed to load texture");
-    let img = img.flipv();
-    let data = img.raw_pixels();
-
-    gl::TexImage2D(
-        gl::TEXTURE_2D,
-        0,
-        gl::RGB as i32,
-        img.width() as i32,
-        img.height() as i32,
-        0,
-        gl::RGB,
-        gl::UNSIGNED_BYTE,
-        &data[0] as *const u8 as *const c_void,
-    );
-    gl::GenerateMipmap(gl::TEXTURE_2D);
-
-    return texture;
+impl Demo for TransformDemo {
+    fn name(&self) -> &'static str {
+        "transform"
+    }
+
+    unsafe fn setup(&mut self) {
+        self.shader = Some(Shader::new(
+            Path::new("src/shaders/transform.vs"),
+            Path::new("src/shaders/transform.fs"),
+        ));
+        self.mesh = Some(create_rectangle_mesh());
+        self.texture = Some(load_and_create_texture(Path::new("resources/crate.jpg")));
+    }
+
+    unsafe fn render(&mut self, context: &RenderContext) {
+        self.texture.as_ref().unwrap().bind(TextureUnit(0));
+
+        // first translate to the bottom right corner, then rotate over time
+        let transform = Matrix4::from_translation(vec3(0.5, -0.5, 0.0)) * Matrix4::from_angle_z(Rad(context.time));
+
+        let shader = self.shader.as_ref().unwrap();
+        shader.use_program();
+        shader.set_matrix4(&CString::new("transform").unwrap(), &transform);
+        self.mesh.as_ref().unwrap().draw();
+    }
+
+    unsafe fn teardown(&mut self) {
+        if let Some(mesh) = self.mesh.take() {
+            mesh.delete();
+        }
+        self.texture = None;
+        if let Some(shader) = self.shader.take() {
+            shader.delete();
+        }
+    }
 }
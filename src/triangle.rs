@@ -7,6 +7,8 @@ use std::os::raw::c_void;
 use gl;
 use gl::types::{GLchar, GLfloat, GLint, GLsizei, GLsizeiptr, GLuint};
 
+use demo::{delete_vertex_array, Demo, RenderContext};
+
 
 
 const VERTEX_SHADER_SOURCE: &str = r#"
@@ -44,8 +46,7 @@ pub unsafe fn create_shader_program() -> GLuint {
 
     // check for shader compile errors
     let mut success = gl::FALSE as GLint;
-    let mut info_log = Vec::with_capacity(512);
-    info_log.set_len(512 - 1); // subtract 1 to skip the trailing null character
+    let mut info_log = vec![0u8; 512];
     gl::GetShaderiv(vertex_shader, gl::COMPILE_STATUS, &mut success);
     if success != gl::TRUE as GLint {
         gl::GetShaderInfoLog(vertex_shader, 512,ptr::null_mut(), info_log.as_mut_ptr() as *mut GLchar);
@@ -78,7 +79,7 @@ pub unsafe fn create_shader_program() -> GLuint {
     gl::DeleteShader(vertex_shader);
     gl::DeleteShader(fragment_shader);
 
-    return shader_program;
+    shader_program
 }
 
 pub unsafe fn create_two_shader_programs() -> [GLuint; 2] {
@@ -92,8 +93,7 @@ pub unsafe fn create_two_shader_programs() -> [GLuint; 2] {
 
     // check for shader compile errors
     let mut success = gl::FALSE as GLint;
-    let mut info_log = Vec::with_capacity(512);
-    info_log.set_len(512 - 1); // subtract 1 to skip the trailing null character
+    let mut info_log = vec![0u8; 512];
     gl::GetShaderiv(vertex_shader, gl::COMPILE_STATUS, &mut success);
     if success != gl::TRUE as GLint {
         gl::GetShaderInfoLog(vertex_shader, 512,ptr::null_mut(), info_log.as_mut_ptr() as *mut GLchar);
@@ -152,7 +152,7 @@ pub unsafe fn create_two_shader_programs() -> [GLuint; 2] {
     gl::DeleteShader(vertex_shader);
     gl::DeleteShader(fragment_shader_2);
 
-    return [shader_program_1, shader_program_2];
+    [shader_program_1, shader_program_2]
 }
 
 pub unsafe fn create_vertex_array_object() -> GLuint {
@@ -190,48 +190,7 @@ pub unsafe fn create_vertex_array_object() -> GLuint {
     // uncomment this call to draw in wireframe polygons.
     // gl::PolygonMode(gl::FRONT_AND_BACK, gl::LINE);
 
-    return vao;
-}
-
-pub unsafe fn create_vertex_array_object_two_triangles() -> GLuint {
-    // set up vertex data (and buffer(s)) and configure vertex attributes
-    // ------------------------------------------------------------------
-    // HINT: type annotation is crucial since default for float literals is f64
-    let vertices: [f32; 18] = [
-        -1.0, -0.5, 0.0, 
-        0.0, -0.5, 0.0,
-        -0.5, 0.5, 0.0,
-        0.0, -0.5, 0.0, 
-        1.0, -0.5, 0.0,
-        0.5, 0.5, 0.0,
-    ];
-
-    let (mut vbo, mut vao) = (0, 0);
-    gl::GenVertexArrays(1, &mut vao);
-    gl::GenBuffers(1, &mut vbo);
-    // bind the Vertex Array Object first, then bind and set vertex buffer(s), and then configure vertex attributes(s).
-    gl::BindVertexArray(vao);
-
-    gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
-    gl::BufferData(gl::ARRAY_BUFFER, 
-                   (vertices.len() * mem::size_of::<GLfloat>()) as GLsizeiptr, 
-                   &vertices[0] as *const f32 as *const c_void, 
-                   gl::STATIC_DRAW);
-
-    gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, 3 * mem::size_of::<GLfloat>() as GLsizei, ptr::null());
-    gl::EnableVertexAttribArray(0);
-
-    // note that this is allowed, the call to gl::VertexAttribPointer registered VBO as the vertex attribute's bound vertex buffer object so afterwards we can safely unbind
-    gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-
-    // You can unbind the VAO afterwards so other VAO calls won't accidentally modify this VAO, but this rarely happens. Modifying other
-    // VAOs requires a call to glBindVertexArray anyways so we generally don't unbind VAOs (nor VBOs) when it's not directly necessary.
-    gl::BindVertexArray(0);
-
-    // uncomment this call to draw in wireframe polygons.
-    // gl::PolygonMode(gl::FRONT_AND_BACK, gl::LINE);
-
-    return vao;
+    vao
 }
 
 pub unsafe fn create_two_vertex_array_objects_two_triangles() -> [GLuint; 2] {
@@ -279,5 +238,67 @@ pub unsafe fn create_two_vertex_array_objects_two_triangles() -> [GLuint; 2] {
     // uncomment this call to draw in wireframe polygons.
     // gl::PolygonMode(gl::FRONT_AND_BACK, gl::LINE);
 
-    return vaos;
+    vaos
+}
+
+/// the orange triangle from the hello triangle chapter
+#[derive(Default)]
+pub struct TriangleDemo {
+    shader_program: GLuint,
+    vao: GLuint,
+}
+
+impl Demo for TriangleDemo {
+    fn name(&self) -> &'static str {
+        "triangle"
+    }
+
+    unsafe fn setup(&mut self) {
+        self.shader_program = create_shader_program();
+        self.vao = create_vertex_array_object();
+    }
+
+    unsafe fn render(&mut self, _context: &RenderContext) {
+        gl::UseProgram(self.shader_program);
+        gl::BindVertexArray(self.vao);
+        gl::DrawArrays(gl::TRIANGLES, 0, 3);
+    }
+
+    unsafe fn teardown(&mut self) {
+        delete_vertex_array(self.vao);
+        gl::DeleteProgram(self.shader_program);
+    }
+}
+
+/// two triangles with their own vertex array objects and shader programs (orange and yellow)
+#[derive(Default)]
+pub struct TwoTrianglesDemo {
+    shader_programs: [GLuint; 2],
+    vaos: [GLuint; 2],
+}
+
+impl Demo for TwoTrianglesDemo {
+    fn name(&self) -> &'static str {
+        "two-triangles"
+    }
+
+    unsafe fn setup(&mut self) {
+        self.shader_programs = create_two_shader_programs();
+        self.vaos = create_two_vertex_array_objects_two_triangles();
+    }
+
+    unsafe fn render(&mut self, _context: &RenderContext) {
+        for (shader_program, vao) in self.shader_programs.iter().zip(self.vaos.iter()) {
+            gl::UseProgram(*shader_program);
+            gl::BindVertexArray(*vao);
+            gl::DrawArrays(gl::TRIANGLES, 0, 3);
+        }
+    }
+
+    unsafe fn teardown(&mut self) {
+        for (shader_program, vao) in self.shader_programs.iter().zip(self.vaos.iter()) {
+            delete_vertex_array(*vao);
+            gl::DeleteProgram(*shader_program);
+        }
+    }
 }
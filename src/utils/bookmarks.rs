@@ -0,0 +1,292 @@
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use cgmath::prelude::*;
+use cgmath::{Point3, Quaternion};
+
+use utils::camera::Camera;
+
+pub const BOOKMARK_SLOTS: usize = 10;
+const BOOKMARKS_FILE_NAME: &str = "camera_bookmarks.txt";
+
+#[derive(Debug)]
+pub enum BookmarkError {
+    Io(String),
+    Parse { line: usize, message: String },
+    /// the file couldn't be loaded completely, saving would lose what wasn't
+    ReadOnly(PathBuf),
+}
+
+impl fmt::Display for BookmarkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BookmarkError::Io(message) => write!(f, "{}", message),
+            BookmarkError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+            BookmarkError::ReadOnly(path) => write!(
+                f,
+                "not saving over {}, fix or remove the bookmarks that failed to load first",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl Error for BookmarkError {}
+
+/// Saved camera poses, one per number key, persisted to a small text file.
+///
+/// Each line of the file holds a slot followed by the position, the orientation
+/// quaternion (s x y z) and the fov:
+///
+/// ```text
+/// 1  0.0 0.0 3.0  1.0 0.0 0.0 0.0  45.0
+/// ```
+///
+/// Bookmarks loaded from a file with lines that couldn't be read are kept, but never saved,
+/// so the bookmarks on those lines aren't lost.
+pub struct Bookmarks {
+    path: PathBuf,
+    slots: [Option<Camera>; BOOKMARK_SLOTS],
+    read_only: bool,
+}
+
+impl Bookmarks {
+    /// the bookmarks file next to the executable, falling back to the working directory
+    pub fn default_path() -> PathBuf {
+        env::current_exe()
+            .map(|exe| exe.with_file_name(BOOKMARKS_FILE_NAME))
+            .unwrap_or_else(|_| PathBuf::from(BOOKMARKS_FILE_NAME))
+    }
+
+    /// empty bookmarks that will be saved to `path`
+    pub fn new(path: &Path) -> Self {
+        Bookmarks {
+            path: path.to_path_buf(),
+            slots: [None; BOOKMARK_SLOTS],
+            read_only: false,
+        }
+    }
+
+    /// Load bookmarks from `path`, a missing file gives empty bookmarks.
+    ///
+    /// Lines that can't be read are skipped and returned as errors, and the bookmarks become
+    /// read-only. A file that can't be read at all gives empty, read-only bookmarks.
+    pub fn load(path: &Path) -> (Bookmarks, Vec<BookmarkError>) {
+        let mut bookmarks = Bookmarks::new(path);
+        match read_file(path) {
+            Ok(Some(contents)) => {
+                let errors = bookmarks.parse(&contents);
+                bookmarks.read_only = !errors.is_empty();
+                (bookmarks, errors)
+            }
+            Ok(None) => (bookmarks, Vec::new()),
+            Err(e) => {
+                bookmarks.read_only = true;
+                (bookmarks, vec![e])
+            }
+        }
+    }
+
+    /// fill in the bookmarks of every line that can be read, returns the errors of the others
+    fn parse(&mut self, contents: &str) -> Vec<BookmarkError> {
+        let mut errors = Vec::new();
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match parse_bookmark(line) {
+                Ok((slot, camera)) => self.slots[slot] = Some(camera),
+                Err(message) => errors.push(BookmarkError::Parse {
+                    line: index + 1,
+                    message,
+                }),
+            }
+        }
+        errors
+    }
+
+    /// whether `save` refuses to write because the file didn't load completely
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    pub fn get(&self, slot: usize) -> Option<Camera> {
+        self.slots.get(slot).and_then(|camera| *camera)
+    }
+
+    /// store `camera` in `slot` and write all bookmarks back to disk, read-only bookmarks
+    /// keep it until the program exits
+    pub fn set(&mut self, slot: usize, camera: Camera) -> Result<(), BookmarkError> {
+        self.slots[slot] = Some(camera);
+        self.save()
+    }
+
+    pub fn save(&self) -> Result<(), BookmarkError> {
+        if self.read_only {
+            return Err(BookmarkError::ReadOnly(self.path.clone()));
+        }
+        let mut contents = String::from("# slot  position  orientation (s x y z)  fov\n");
+        for (slot, camera) in self.slots.iter().enumerate() {
+            if let Some(camera) = camera {
+                let (position, orientation) = (camera.position, camera.orientation);
+                contents.push_str(&format!(
+                    "{} {} {} {} {} {} {} {} {}\n",
+                    slot,
+                    position.x,
+                    position.y,
+                    position.z,
+                    orientation.s,
+                    orientation.v.x,
+                    orientation.v.y,
+                    orientation.v.z,
+                    camera.fov
+                ));
+            }
+        }
+
+        File::create(&self.path)
+            .and_then(|mut file| file.write_all(contents.as_bytes()))
+            .map_err(|e| BookmarkError::Io(format!("Failed to write {}: {}", self.path.display(), e)))
+    }
+}
+
+/// contents of a file, `None` if there is none
+fn read_file(path: &Path) -> Result<Option<String>, BookmarkError> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(BookmarkError::Io(format!("Failed to open {}: {}", path.display(), e))),
+    };
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)
+        .map_err(|e| BookmarkError::Io(format!("Failed to read {}: {}", path.display(), e)))?;
+    Ok(Some(contents))
+}
+
+fn parse_bookmark(line: &str) -> Result<(usize, Camera), String> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    if tokens.len() != 9 {
+        return Err(format!("expected 9 values, found {}", tokens.len()));
+    }
+
+    let slot = tokens[0]
+        .parse::<usize>()
+        .ok()
+        .filter(|slot| *slot < BOOKMARK_SLOTS)
+        .ok_or_else(|| format!("'{}' is not a slot between 0 and {}", tokens[0], BOOKMARK_SLOTS - 1))?;
+    let mut values = [0.0f32; 8];
+    for (value, token) in values.iter_mut().zip(tokens[1..].iter()) {
+        *value = match token.parse::<f32>() {
+            Ok(number) if number.is_finite() => number,
+            _ => return Err(format!("'{}' is not a number", token)),
+        };
+    }
+
+    let orientation = Quaternion::new(values[3], values[4], values[5], values[6]);
+    if orientation.magnitude2() == 0.0 {
+        return Err("orientation must not be zero".to_string());
+    }
+
+    // the projection needs a field of view strictly between 0 and 180 degrees
+    let fov = values[7];
+    if fov <= 0.0 || fov >= 180.0 {
+        return Err(format!("fov {} is not between 0 and 180 degrees", tokens[8]));
+    }
+
+    let mut camera = Camera::new(Point3::new(values[0], values[1], values[2]), orientation.normalize());
+    camera.fov = fov;
+    Ok((slot, camera))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// a file in the temporary directory, removed again when the test is done
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str, contents: Option<&str>) -> TempFile {
+            let path = env::temp_dir().join(format!("learn_opengl_{}_{}", name, std::process::id()));
+            if let Some(contents) = contents {
+                fs::write(&path, contents).unwrap();
+            }
+            TempFile(path)
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn missing_file_gives_writable_empty_bookmarks() {
+        let file = TempFile::new("bookmarks_missing", None);
+        let (mut bookmarks, errors) = Bookmarks::load(&file.0);
+        assert!(errors.is_empty());
+        assert!(!bookmarks.is_read_only());
+        assert_eq!(bookmarks.get(1), None);
+
+        let camera = Camera::from_yaw_pitch(Point3::new(1.0, 2.0, 3.0), -45.0, 10.0);
+        bookmarks.set(1, camera).unwrap();
+        let (reloaded, errors) = Bookmarks::load(&file.0);
+        assert!(errors.is_empty());
+        let saved = reloaded.get(1).unwrap();
+        assert_eq!(saved.position, camera.position);
+        assert!((saved.orientation - camera.orientation).magnitude() < 1e-5);
+    }
+
+    #[test]
+    fn bad_lines_keep_the_good_ones_and_the_file() {
+        let contents = "# slot  position  orientation (s x y z)  fov\n\
+                        1 0 0 3 1 0 0 0 45\n\
+                        2 0 0 three 1 0 0 0 45\n\
+                        3 1 1 1 1 0 0 0 60\n";
+        let file = TempFile::new("bookmarks_bad_line", Some(contents));
+        let (mut bookmarks, errors) = Bookmarks::load(&file.0);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].to_string(), "line 3: 'three' is not a number");
+        assert!(bookmarks.get(1).is_some());
+        assert!(bookmarks.get(2).is_none());
+        assert_eq!(bookmarks.get(3).unwrap().fov, 60.0);
+
+        // saving would drop line 3, so it's refused and the file stays as it was
+        assert!(bookmarks.is_read_only());
+        let camera = Camera::from_yaw_pitch(Point3::new(0.0, 0.0, 0.0), 0.0, 0.0);
+        match bookmarks.set(4, camera) {
+            Err(BookmarkError::ReadOnly(_)) => (),
+            other => panic!("expected a read-only error, got {:?}", other),
+        }
+        assert!(bookmarks.get(4).is_some());
+        assert_eq!(fs::read_to_string(&file.0).unwrap(), contents);
+    }
+
+    #[test]
+    fn fov_out_of_range_is_an_error() {
+        let contents = "1 0 0 3 1 0 0 0 0\n\
+                        2 0 0 3 1 0 0 0 -30\n\
+                        3 0 0 3 1 0 0 0 180\n\
+                        4 0 0 3 1 0 0 0 179.5\n";
+        let file = TempFile::new("bookmarks_fov", Some(contents));
+        let (bookmarks, errors) = Bookmarks::load(&file.0);
+        let errors: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+        assert_eq!(
+            errors,
+            vec![
+                "line 1: fov 0 is not between 0 and 180 degrees",
+                "line 2: fov -30 is not between 0 and 180 degrees",
+                "line 3: fov 180 is not between 0 and 180 degrees",
+            ]
+        );
+        assert!(bookmarks.get(1).is_none());
+        assert_eq!(bookmarks.get(4).unwrap().fov, 179.5);
+    }
+}
@@ -0,0 +1,183 @@
+use cgmath::prelude::*;
+use cgmath::{Matrix4, Point3, Vector3};
+
+/// axis aligned bounding box
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Point3<f32>,
+    pub max: Point3<f32>,
+}
+
+impl Aabb {
+    pub fn new(min: Point3<f32>, max: Point3<f32>) -> Self {
+        Aabb { min, max }
+    }
+
+    /// smallest box containing all `points`, `None` if there are none
+    pub fn from_points<I>(points: I) -> Option<Aabb>
+    where
+        I: IntoIterator<Item = Point3<f32>>,
+    {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        let mut aabb = Aabb::new(first, first);
+        for point in points {
+            aabb.min = Point3::new(
+                aabb.min.x.min(point.x),
+                aabb.min.y.min(point.y),
+                aabb.min.z.min(point.z),
+            );
+            aabb.max = Point3::new(
+                aabb.max.x.max(point.x),
+                aabb.max.y.max(point.y),
+                aabb.max.z.max(point.z),
+            );
+        }
+        Some(aabb)
+    }
+
+    pub fn center(&self) -> Point3<f32> {
+        self.min.midpoint(self.max)
+    }
+
+    pub fn half_extents(&self) -> Vector3<f32> {
+        (self.max - self.min) * 0.5
+    }
+
+    /// corner `i` has the max x if bit 0 of `i` is set, the max y for bit 1 and the max z for bit 2
+    pub fn corners(&self) -> [Point3<f32>; 8] {
+        let (min, max) = (self.min, self.max);
+        [
+            Point3::new(min.x, min.y, min.z),
+            Point3::new(max.x, min.y, min.z),
+            Point3::new(min.x, max.y, min.z),
+            Point3::new(max.x, max.y, min.z),
+            Point3::new(min.x, min.y, max.z),
+            Point3::new(max.x, min.y, max.z),
+            Point3::new(min.x, max.y, max.z),
+            Point3::new(max.x, max.y, max.z),
+        ]
+    }
+
+    /// box in world space enclosing this box transformed by `model`
+    pub fn transform(&self, model: &Matrix4<f32>) -> Aabb {
+        Aabb::from_points(self.corners().iter().map(|corner| model.transform_point(*corner))).unwrap()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingSphere {
+    pub center: Point3<f32>,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    pub fn new(center: Point3<f32>, radius: f32) -> Self {
+        BoundingSphere { center, radius }
+    }
+
+    /// sphere around the center of the box of `points` reaching the farthest of them, `None` if there are none
+    pub fn from_points<I>(points: I) -> Option<BoundingSphere>
+    where
+        I: IntoIterator<Item = Point3<f32>> + Clone,
+    {
+        let center = Aabb::from_points(points.clone())?.center();
+        let radius = points
+            .into_iter()
+            .map(|point| point.distance2(center))
+            .fold(0.0, f32::max)
+            .sqrt();
+        Some(BoundingSphere::new(center, radius))
+    }
+
+    /// sphere around the center of `aabb` enclosing it
+    pub fn from_aabb(aabb: &Aabb) -> Self {
+        BoundingSphere::new(aabb.center(), aabb.half_extents().magnitude())
+    }
+
+    /// sphere in world space enclosing this sphere transformed by `model`,
+    /// non-uniform scales grow the radius by the largest axis scale
+    pub fn transform(&self, model: &Matrix4<f32>) -> BoundingSphere {
+        let scale = model
+            .x
+            .truncate()
+            .magnitude()
+            .max(model.y.truncate().magnitude())
+            .max(model.z.truncate().magnitude());
+        BoundingSphere::new(model.transform_point(self.center), self.radius * scale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{vec3, Deg};
+
+    fn unit_box() -> Aabb {
+        Aabb::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0))
+    }
+
+    /// a scale that differs per axis, a rotation around a tilted axis and a translation
+    fn skewed_model() -> Matrix4<f32> {
+        Matrix4::from_translation(vec3(3.0, -2.0, 5.0))
+            * Matrix4::from_axis_angle(vec3(1.0, 2.0, 0.5).normalize(), Deg(37.0))
+            * Matrix4::from_nonuniform_scale(2.0, 0.5, 1.5)
+    }
+
+    fn contains(aabb: &Aabb, point: Point3<f32>) -> bool {
+        let epsilon = 1e-5;
+        point.x >= aabb.min.x - epsilon
+            && point.y >= aabb.min.y - epsilon
+            && point.z >= aabb.min.z - epsilon
+            && point.x <= aabb.max.x + epsilon
+            && point.y <= aabb.max.y + epsilon
+            && point.z <= aabb.max.z + epsilon
+    }
+
+    #[test]
+    fn from_points() {
+        assert_eq!(Aabb::from_points(Vec::new()), None);
+        let points = vec![Point3::new(1.0, -2.0, 0.5), Point3::new(-1.0, 4.0, 0.0), Point3::new(0.0, 0.0, 3.0)];
+        let aabb = Aabb::from_points(points.clone()).unwrap();
+        assert_eq!(aabb, Aabb::new(Point3::new(-1.0, -2.0, 0.0), Point3::new(1.0, 4.0, 3.0)));
+        let sphere = BoundingSphere::from_points(points.clone()).unwrap();
+        assert_eq!(sphere.center, aabb.center());
+        for point in points {
+            assert!(point.distance(sphere.center) <= sphere.radius + 1e-5);
+        }
+    }
+
+    #[test]
+    fn aabb_transform_encloses_rotated_box() {
+        let model = Matrix4::from_angle_y(Deg(45.0));
+        let aabb = unit_box().transform(&model);
+        let extent = 2.0f32.sqrt();
+        assert!((aabb.max.x - extent).abs() < 1e-5 && (aabb.min.x + extent).abs() < 1e-5);
+        assert!((aabb.max.z - extent).abs() < 1e-5 && (aabb.min.z + extent).abs() < 1e-5);
+        assert!((aabb.max.y - 1.0).abs() < 1e-5 && (aabb.min.y + 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn aabb_transform_encloses_scaled_box() {
+        let model = skewed_model();
+        let aabb = unit_box().transform(&model);
+        for corner in unit_box().corners().iter() {
+            assert!(contains(&aabb, model.transform_point(*corner)), "{:?}", corner);
+        }
+        // and is no bigger than it has to be: every face touches a corner
+        let corners: Vec<Point3<f32>> = unit_box().corners().iter().map(|corner| model.transform_point(*corner)).collect();
+        assert_eq!(Aabb::from_points(corners), Some(aabb));
+    }
+
+    #[test]
+    fn sphere_transform_encloses_scaled_box() {
+        let sphere = BoundingSphere::from_aabb(&unit_box());
+        for model in &[Matrix4::from_angle_z(Deg(30.0)), skewed_model(), Matrix4::from_nonuniform_scale(0.1, 4.0, 1.0)] {
+            let transformed = sphere.transform(model);
+            for corner in unit_box().corners().iter() {
+                let corner = model.transform_point(*corner);
+                assert!(corner.distance(transformed.center) <= transformed.radius + 1e-4, "{:?}", corner);
+            }
+        }
+    }
+}
@@ -0,0 +1,241 @@
+use std::time::Duration;
+
+use cgmath::prelude::*;
+use cgmath::{perspective, Deg, Matrix4, Point3, Quaternion, Vector3};
+
+use utils::bounds::BoundingSphere;
+use utils::interpolation::Interpolate;
+
+// directions in camera space, the camera looks down the negative z axis
+const LOCAL_FRONT: Vector3<f32> = Vector3 {
+    x: 0.0,
+    y: 0.0,
+    z: -1.0,
+};
+const LOCAL_UP: Vector3<f32> = Vector3 {
+    x: 0.0,
+    y: 1.0,
+    z: 0.0,
+};
+const LOCAL_RIGHT: Vector3<f32> = Vector3 {
+    x: 1.0,
+    y: 0.0,
+    z: 0.0,
+};
+
+pub const DEFAULT_FOV: f32 = 45.0;
+
+/// orientation from yaw and pitch in degrees, using the same convention as `Input`
+/// (a yaw of -90 looks down the negative z axis)
+pub fn orientation_from_yaw_pitch(yaw: f32, pitch: f32) -> Quaternion<f32> {
+    Quaternion::from_angle_y(Deg(-(yaw + 90.0))) * Quaternion::from_angle_x(Deg(pitch))
+}
+
+/// yaw and pitch in degrees of an orientation, any roll is lost
+pub fn yaw_pitch_from_orientation(orientation: Quaternion<f32>) -> (f32, f32) {
+    let front = (orientation * LOCAL_FRONT).normalize();
+    let pitch = front.y.clamp(-1.0, 1.0).asin().to_degrees();
+    let yaw = front.z.atan2(front.x).to_degrees();
+    (yaw, pitch)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    pub position: Point3<f32>,
+    pub orientation: Quaternion<f32>,
+    /// vertical field of view in degrees
+    pub fov: f32,
+}
+
+impl Camera {
+    pub fn new(position: Point3<f32>, orientation: Quaternion<f32>) -> Self {
+        Camera {
+            position,
+            orientation,
+            fov: DEFAULT_FOV,
+        }
+    }
+
+    pub fn from_yaw_pitch(position: Point3<f32>, yaw: f32, pitch: f32) -> Self {
+        Camera::new(position, orientation_from_yaw_pitch(yaw, pitch))
+    }
+
+    pub fn yaw_pitch(&self) -> (f32, f32) {
+        yaw_pitch_from_orientation(self.orientation)
+    }
+
+    pub fn set_yaw_pitch(&mut self, yaw: f32, pitch: f32) {
+        self.orientation = orientation_from_yaw_pitch(yaw, pitch);
+    }
+
+    pub fn front(&self) -> Vector3<f32> {
+        (self.orientation * LOCAL_FRONT).normalize()
+    }
+
+    pub fn up(&self) -> Vector3<f32> {
+        (self.orientation * LOCAL_UP).normalize()
+    }
+
+    pub fn right(&self) -> Vector3<f32> {
+        (self.orientation * LOCAL_RIGHT).normalize()
+    }
+
+    /// rotate around the camera's own axes, angles in degrees.
+    /// Unlike yaw/pitch there are no limits, so the camera can roll and loop freely.
+    pub fn rotate_local(&mut self, yaw: f32, pitch: f32, roll: f32) {
+        let rotation = Quaternion::from_angle_y(Deg(-yaw))
+            * Quaternion::from_angle_x(Deg(pitch))
+            * Quaternion::from_angle_z(Deg(-roll));
+        self.orientation = (self.orientation * rotation).normalize();
+    }
+
+    pub fn view_matrix(&self) -> Matrix4<f32> {
+        Matrix4::look_at(self.position, self.position + self.front(), self.up())
+    }
+
+    pub fn projection_matrix(&self, aspect_ratio: f32) -> Matrix4<f32> {
+        perspective(Deg(self.fov), aspect_ratio, 0.1, 100.0)
+    }
+
+    /// height of a sphere on screen as a fraction of the screen's, infinite with the camera inside it
+    pub fn projected_size(&self, sphere: &BoundingSphere) -> f32 {
+        let distance = self.position.distance(sphere.center);
+        if distance <= sphere.radius {
+            return f32::INFINITY;
+        }
+        sphere.radius / (distance * (Deg(self.fov) / 2.0).tan())
+    }
+
+    /// pose between `self` and `other`, lerping the position and fov and slerping the orientation
+    pub fn interpolate(&self, other: &Camera, amount: f32) -> Camera {
+        Camera {
+            position: self.position + (other.position - self.position) * amount,
+            orientation: slerp_shortest(self.orientation, other.orientation, amount),
+            fov: self.fov + (other.fov - self.fov) * amount,
+        }
+    }
+}
+
+impl Interpolate for Camera {
+    fn interpolate(&self, other: &Camera, amount: f32) -> Camera {
+        Camera::interpolate(self, other, amount)
+    }
+}
+
+/// slerp along the shorter arc between two orientations
+pub fn slerp_shortest(from: Quaternion<f32>, to: Quaternion<f32>, amount: f32) -> Quaternion<f32> {
+    // q and -q are the same rotation, cgmath's slerp would take the long way round for one of them
+    let to = if from.dot(to) < 0.0 { -to } else { to };
+    from.slerp(to, amount)
+}
+
+/// Smooth transition from one camera pose to another, advanced by fixed update steps.
+pub struct CameraTransition {
+    from: Camera,
+    to: Camera,
+    duration: f32,
+    elapsed: f32,
+}
+
+impl CameraTransition {
+    pub fn new(from: Camera, to: Camera, duration: Duration) -> Self {
+        CameraTransition {
+            from,
+            to,
+            duration: duration.as_secs_f32(),
+            elapsed: 0.0,
+        }
+    }
+
+    pub fn finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+
+    pub fn update(&mut self, step: Duration) {
+        self.elapsed = (self.elapsed + step.as_secs_f32()).min(self.duration);
+    }
+
+    /// the pose at this point of the transition, exactly the target pose once it's finished
+    pub fn camera(&self) -> Camera {
+        // slerp doesn't land exactly on its end
+        if self.finished() {
+            return self.to;
+        }
+        let t = self.elapsed / self.duration;
+        // ease in and out so the camera doesn't jerk at either end
+        self.from.interpolate(&self.to, t * t * (3.0 - 2.0 * t))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// degrees between two angles, the short way around
+    fn angle_between(a: f32, b: f32) -> f32 {
+        let difference = (a - b).rem_euclid(360.0);
+        difference.min(360.0 - difference)
+    }
+
+    #[test]
+    fn yaw_pitch_round_trip() {
+        for &yaw in &[-180.0, -135.0, -90.0, -30.0, 0.0, 45.0, 90.0, 179.0] {
+            for &pitch in &[-89.0, -88.5, -60.0, 0.0, 30.0, 88.5, 89.0] {
+                let orientation = orientation_from_yaw_pitch(yaw, pitch);
+                let (round_yaw, round_pitch) = yaw_pitch_from_orientation(orientation);
+                assert!(angle_between(round_yaw, yaw) < 1e-2, "yaw {} pitch {}: {}", yaw, pitch, round_yaw);
+                assert!((round_pitch - pitch).abs() < 1e-3, "yaw {} pitch {}: {}", yaw, pitch, round_pitch);
+
+                // no roll, the right vector stays level
+                let camera = Camera::from_yaw_pitch(Point3::new(0.0, 0.0, 0.0), yaw, pitch);
+                assert!(camera.right().y.abs() < 1e-6);
+                assert!(camera.up().y >= 0.0);
+            }
+        }
+        let camera = Camera::from_yaw_pitch(Point3::new(0.0, 0.0, 0.0), -90.0, 0.0);
+        assert!((camera.front() - LOCAL_FRONT).magnitude() < 1e-6);
+    }
+
+    #[test]
+    fn slerp_takes_the_short_arc() {
+        let from = Quaternion::from_angle_y(Deg(10.0));
+        // the same rotation as 100 degrees, but on the other side of the 4d sphere
+        let to = -Quaternion::from_angle_y(Deg(100.0));
+        assert!(from.dot(to) < 0.0);
+        for &(amount, angle) in &[(0.0, 10.0), (0.5, 55.0), (1.0, 100.0)] {
+            let rotated = slerp_shortest(from, to, amount) * LOCAL_FRONT;
+            let expected = Quaternion::from_angle_y(Deg(angle)) * LOCAL_FRONT;
+            assert!((rotated - expected).magnitude() < 1e-5, "{}: {:?} != {:?}", amount, rotated, expected);
+        }
+        // the plain slerp goes the long way round
+        let long = from.slerp(to, 0.5) * LOCAL_FRONT;
+        assert!((long - Quaternion::from_angle_y(Deg(55.0)) * LOCAL_FRONT).magnitude() > 1.0);
+    }
+
+    #[test]
+    fn transition_ends_on_the_target() {
+        let from = Camera::from_yaw_pitch(Point3::new(0.0, 1.0, 3.0), -90.0, 0.0);
+        let mut to = Camera::from_yaw_pitch(Point3::new(-2.0, 0.5, 7.0), 170.0, -30.0);
+        to.fov = 60.0;
+        let mut transition = CameraTransition::new(from, to, Duration::from_millis(500));
+        let start = transition.camera();
+        assert_eq!(start.position, from.position);
+        assert!((start.orientation - from.orientation).magnitude() < 1e-6);
+        let mut previous = 0.0;
+        while !transition.finished() {
+            transition.update(Duration::from_millis(16));
+            // the position moves towards the target all the way
+            let travelled = transition.camera().position.distance(from.position);
+            assert!(travelled >= previous);
+            previous = travelled;
+        }
+        assert_eq!(transition.camera(), to);
+        // overshooting the duration stays on the target
+        transition.update(Duration::from_millis(16));
+        assert_eq!(transition.camera(), to);
+
+        let instant = CameraTransition::new(from, to, Duration::from_millis(0));
+        assert!(instant.finished());
+        assert_eq!(instant.camera(), to);
+    }
+}
@@ -0,0 +1,398 @@
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::time::Duration;
+
+use cgmath::prelude::*;
+use cgmath::{Point3, Vector3};
+
+/// orientation of a keyframe, either given directly or as a point to look at
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Orientation {
+    YawPitch(f32, f32),
+    LookAt(Point3<f32>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keyframe {
+    pub time: f32,
+    pub position: Point3<f32>,
+    pub orientation: Orientation,
+}
+
+/// camera pose produced by sampling a path
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraPose {
+    pub position: Point3<f32>,
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+#[derive(Debug)]
+pub enum CameraPathError {
+    Io(String),
+    Parse { line: usize, message: String },
+    TooFewKeyframes,
+    /// index of a keyframe with a time, coordinate or angle that is NaN or infinite
+    NotFinite(usize),
+}
+
+impl fmt::Display for CameraPathError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CameraPathError::Io(message) => write!(f, "{}", message),
+            CameraPathError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+            CameraPathError::TooFewKeyframes => write!(f, "a camera path needs at least two keyframes"),
+            CameraPathError::NotFinite(index) => write!(f, "keyframe {} has a value that isn't a finite number", index),
+        }
+    }
+}
+
+impl Error for CameraPathError {}
+
+/// A camera path made of keyframes interpolated with a Catmull-Rom spline.
+///
+/// Path files hold one keyframe per line, blank lines and lines starting with `#` are ignored:
+///
+/// ```text
+/// # time  position       orientation
+/// 0.0     0.0 0.0 3.0    yaw -90.0 0.0
+/// 4.0     3.0 1.0 -2.0   look 0.0 0.0 -5.0
+/// ```
+pub struct CameraPath {
+    keyframes: Vec<Keyframe>,
+    // yaw/pitch of every keyframe, with look-at targets resolved and yaw unwrapped
+    angles: Vec<(f32, f32)>,
+}
+
+impl CameraPath {
+    pub fn new(mut keyframes: Vec<Keyframe>) -> Result<CameraPath, CameraPathError> {
+        if keyframes.len() < 2 {
+            return Err(CameraPathError::TooFewKeyframes);
+        }
+        if let Some(index) = keyframes.iter().position(|keyframe| !is_finite(keyframe)) {
+            return Err(CameraPathError::NotFinite(index));
+        }
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+        let mut angles: Vec<(f32, f32)> = Vec::with_capacity(keyframes.len());
+        for keyframe in keyframes.iter() {
+            let (mut yaw, pitch) = match keyframe.orientation {
+                Orientation::YawPitch(yaw, pitch) => (yaw, pitch),
+                Orientation::LookAt(target) => yaw_pitch_towards(keyframe.position, target),
+            };
+            // take the shortest way around from the previous keyframe
+            if let Some(&(previous_yaw, _)) = angles.last() {
+                while yaw - previous_yaw > 180.0 {
+                    yaw -= 360.0;
+                }
+                while yaw - previous_yaw < -180.0 {
+                    yaw += 360.0;
+                }
+            }
+            angles.push((yaw, pitch));
+        }
+
+        Ok(CameraPath { keyframes, angles })
+    }
+
+    pub fn load(path: &Path) -> Result<CameraPath, CameraPathError> {
+        let mut file = File::open(path)
+            .map_err(|e| CameraPathError::Io(format!("Failed to open {}: {}", path.display(), e)))?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)
+            .map_err(|e| CameraPathError::Io(format!("Failed to read {}: {}", path.display(), e)))?;
+        CameraPath::parse(&contents)
+    }
+
+    pub fn parse(contents: &str) -> Result<CameraPath, CameraPathError> {
+        let mut keyframes = Vec::new();
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let keyframe = parse_keyframe(line).map_err(|message| CameraPathError::Parse {
+                line: index + 1,
+                message,
+            })?;
+            keyframes.push(keyframe);
+        }
+        CameraPath::new(keyframes)
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.keyframes[self.keyframes.len() - 1].time - self.keyframes[0].time
+    }
+
+    /// sample the path at `time` seconds from its start, times outside the path are clamped
+    pub fn sample(&self, time: f32) -> CameraPose {
+        let start = self.keyframes[0].time;
+        let time = start + time.clamp(0.0, self.duration());
+
+        // find the segment [i, i + 1] containing time
+        let last = self.keyframes.len() - 1;
+        let mut i = 0;
+        while i < last - 1 && self.keyframes[i + 1].time <= time {
+            i += 1;
+        }
+        let (t0, t1) = (self.keyframes[i].time, self.keyframes[i + 1].time);
+        let u = if t1 > t0 { (time - t0) / (t1 - t0) } else { 1.0 };
+
+        // neighbours of the segment, repeating the end keyframes
+        let indices = [if i == 0 { 0 } else { i - 1 }, i, i + 1, (i + 2).min(last)];
+
+        let positions: Vec<Vector3<f32>> = indices
+            .iter()
+            .map(|&k| self.keyframes[k].position.to_vec())
+            .collect();
+        let yaws: Vec<f32> = indices.iter().map(|&k| self.angles[k].0).collect();
+        let pitches: Vec<f32> = indices.iter().map(|&k| self.angles[k].1).collect();
+
+        CameraPose {
+            position: Point3::from_vec(catmull_rom(
+                positions[0],
+                positions[1],
+                positions[2],
+                positions[3],
+                u,
+            )),
+            yaw: catmull_rom(yaws[0], yaws[1], yaws[2], yaws[3], u),
+            pitch: catmull_rom(pitches[0], pitches[1], pitches[2], pitches[3], u),
+        }
+    }
+}
+
+/// Plays a camera path back, advanced by fixed update steps so playback is reproducible.
+///
+/// The speed along the path is eased in at the start and eased out at the end.
+pub struct CameraPathPlayer {
+    path: CameraPath,
+    elapsed: f32,
+}
+
+impl CameraPathPlayer {
+    pub fn new(path: CameraPath) -> Self {
+        CameraPathPlayer { path, elapsed: 0.0 }
+    }
+
+    pub fn restart(&mut self) {
+        self.elapsed = 0.0;
+    }
+
+    pub fn finished(&self) -> bool {
+        self.elapsed >= self.path.duration()
+    }
+
+    /// advance playback by one update step
+    pub fn update(&mut self, step: Duration) {
+        self.elapsed = (self.elapsed + step.as_secs_f32()).min(self.path.duration());
+    }
+
+    pub fn pose(&self) -> CameraPose {
+        let duration = self.path.duration();
+        if duration <= 0.0 {
+            return self.path.sample(0.0);
+        }
+        self.path.sample(ease_in_out(self.elapsed / duration) * duration)
+    }
+}
+
+fn parse_keyframe(line: &str) -> Result<Keyframe, String> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    let number = |index: usize| -> Result<f32, String> {
+        let token = tokens
+            .get(index)
+            .ok_or_else(|| format!("expected a number at column {}", index + 1))?;
+        match token.parse::<f32>() {
+            Ok(value) if value.is_finite() => Ok(value),
+            _ => Err(format!("'{}' is not a number", token)),
+        }
+    };
+
+    let time = number(0)?;
+    let position = Point3::new(number(1)?, number(2)?, number(3)?);
+    let (orientation, expected_len) = match tokens.get(4) {
+        Some(&"yaw") => (Orientation::YawPitch(number(5)?, number(6)?), 7),
+        Some(&"look") => (
+            Orientation::LookAt(Point3::new(number(5)?, number(6)?, number(7)?)),
+            8,
+        ),
+        Some(other) => return Err(format!("unknown orientation '{}', expected 'yaw' or 'look'", other)),
+        None => return Err("missing orientation, expected 'yaw' or 'look'".to_string()),
+    };
+    if tokens.len() > expected_len {
+        return Err(format!("unexpected '{}' after keyframe", tokens[expected_len]));
+    }
+
+    Ok(Keyframe {
+        time,
+        position,
+        orientation,
+    })
+}
+
+/// whether every number of a keyframe is finite, the parser only produces such keyframes
+fn is_finite(keyframe: &Keyframe) -> bool {
+    let position = keyframe.position;
+    let numbers = match keyframe.orientation {
+        Orientation::YawPitch(yaw, pitch) => vec![yaw, pitch],
+        Orientation::LookAt(target) => vec![target.x, target.y, target.z],
+    };
+    [keyframe.time, position.x, position.y, position.z]
+        .iter()
+        .chain(&numbers)
+        .all(|number| number.is_finite())
+}
+
+/// yaw and pitch in degrees of the direction from `position` to `target`
+pub fn yaw_pitch_towards(position: Point3<f32>, target: Point3<f32>) -> (f32, f32) {
+    let direction = target - position;
+    if direction.magnitude2() == 0.0 {
+        return (-90.0, 0.0);
+    }
+    let direction = direction.normalize();
+    let pitch = direction.y.clamp(-1.0, 1.0).asin().to_degrees();
+    let yaw = direction.z.atan2(direction.x).to_degrees();
+    (yaw, pitch)
+}
+
+fn catmull_rom<T>(p0: T, p1: T, p2: T, p3: T, t: f32) -> T
+where
+    T: Copy + ::std::ops::Add<Output = T> + ::std::ops::Sub<Output = T> + ::std::ops::Mul<f32, Output = T>,
+{
+    let t2 = t * t;
+    let t3 = t2 * t;
+    (p1 * 2.0 + (p2 - p0) * t + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2 + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3) * 0.5
+}
+
+fn ease_in_out(t: f32) -> f32 {
+    let t = t.clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyframe(time: f32, yaw: f32) -> Keyframe {
+        Keyframe {
+            time,
+            position: Point3::new(0.0, 0.0, 0.0),
+            orientation: Orientation::YawPitch(yaw, 0.0),
+        }
+    }
+
+    #[test]
+    fn sorts_keyframes_by_time() {
+        let path = CameraPath::new(vec![keyframe(2.0, 0.0), keyframe(0.0, 0.0), keyframe(1.0, 0.0)]).unwrap();
+        let times: Vec<f32> = path.keyframes().iter().map(|keyframe| keyframe.time).collect();
+        assert_eq!(times, vec![0.0, 1.0, 2.0]);
+    }
+
+    #[test]
+    fn rejects_non_finite_keyframes() {
+        for &(time, yaw) in &[(f32::NAN, 0.0), (f32::INFINITY, 0.0), (1.0, f32::NAN)] {
+            match CameraPath::new(vec![keyframe(0.0, 0.0), keyframe(time, yaw)]) {
+                Err(CameraPathError::NotFinite(1)) => (),
+                other => panic!("expected keyframe 1 to be rejected, got {:?}", other.map(|path| path.duration())),
+            }
+        }
+    }
+
+    /// keyframes at uneven times, starting after 0, one of them looking at a point and the yaw
+    /// wrapping around between the last two
+    fn path() -> CameraPath {
+        CameraPath::parse(
+            "1.0  0.0 0.0 3.0    yaw -90.0 0.0\n\
+             2.5  2.0 1.0 0.0    yaw 0.0 20.0\n\
+             3.0  2.0 2.0 -2.0   look 0.0 0.0 -5.0\n\
+             6.0  -1.0 0.5 -4.0  yaw 170.0 -10.0\n\
+             7.0  -3.0 0.0 -4.0  yaw -170.0 0.0\n",
+        ).unwrap()
+    }
+
+    /// degrees between two angles, the short way around
+    fn angle_between(a: f32, b: f32) -> f32 {
+        let difference = (a - b).rem_euclid(360.0);
+        difference.min(360.0 - difference)
+    }
+
+    fn assert_pose(pose: CameraPose, keyframe: &Keyframe) {
+        let (yaw, pitch) = match keyframe.orientation {
+            Orientation::YawPitch(yaw, pitch) => (yaw, pitch),
+            Orientation::LookAt(target) => yaw_pitch_towards(keyframe.position, target),
+        };
+        assert!((pose.position - keyframe.position).magnitude() < 1e-5, "{:?} != {:?}", pose, keyframe);
+        // yaws are unwrapped along the path
+        assert!(angle_between(pose.yaw, yaw) < 1e-4, "{:?} != {:?}", pose, keyframe);
+        assert!((pose.pitch - pitch).abs() < 1e-4, "{:?} != {:?}", pose, keyframe);
+    }
+
+    #[test]
+    fn passes_through_keyframes() {
+        let path = path();
+        assert_eq!(path.duration(), 6.0);
+        for keyframe in path.keyframes() {
+            assert_pose(path.sample(keyframe.time - 1.0), keyframe);
+        }
+        // between the last two keyframes the yaw takes the short way over 180 degrees
+        let middle = path.sample(5.5);
+        assert!(angle_between(middle.yaw, 180.0) < 10.0, "{:?}", middle);
+    }
+
+    #[test]
+    fn sampling_clamps_to_the_ends() {
+        let path = path();
+        let keyframes = path.keyframes();
+        for &time in &[-10.0, -0.001, 0.0] {
+            assert_pose(path.sample(time), &keyframes[0]);
+        }
+        for &time in &[6.0, 6.001, 100.0] {
+            assert_pose(path.sample(time), &keyframes[keyframes.len() - 1]);
+        }
+    }
+
+    #[test]
+    fn easing_is_monotone() {
+        assert_eq!(ease_in_out(0.0), 0.0);
+        assert_eq!(ease_in_out(0.5), 0.5);
+        assert_eq!(ease_in_out(1.0), 1.0);
+        assert_eq!(ease_in_out(-1.0), 0.0);
+        assert_eq!(ease_in_out(2.0), 1.0);
+        let mut previous = 0.0;
+        for step in 1..=100 {
+            let eased = ease_in_out(step as f32 / 100.0);
+            assert!(eased >= previous, "{} after {} at step {}", eased, previous, step);
+            previous = eased;
+        }
+        // slow at both ends
+        assert!(ease_in_out(0.1) < 0.1);
+        assert!(ease_in_out(0.9) > 0.9);
+    }
+
+    #[test]
+    fn player_ends_on_the_last_keyframe() {
+        let mut player = CameraPathPlayer::new(path());
+        assert_pose(player.pose(), &path().keyframes()[0]);
+        let step = Duration::from_millis(16);
+        let mut steps = 0;
+        while !player.finished() {
+            player.update(step);
+            steps += 1;
+        }
+        // 6 seconds, give or take rounding in the step sum
+        assert!((375..=376).contains(&steps), "{} steps", steps);
+        let keyframes = path().keyframes().to_vec();
+        assert_pose(player.pose(), &keyframes[keyframes.len() - 1]);
+        player.restart();
+        assert!(!player.finished());
+        assert_pose(player.pose(), &keyframes[0]);
+    }
+}
@@ -0,0 +1,144 @@
+use std::time::Duration;
+
+pub const MIN_TIME_SCALE: f32 = 0.1;
+pub const MAX_TIME_SCALE: f32 = 4.0;
+// time scales stepped through by `faster` and `slower`
+const TIME_SCALE_STEPS: [f32; 7] = [0.1, 0.25, 0.5, 1.0, 2.0, 3.0, 4.0];
+
+// never try to catch up on more than this much time, after a stall the simulation just slows down
+const MAX_LAG: Duration = Duration::from_millis(250);
+// most updates run for a single frame
+const MAX_UPDATES_PER_FRAME: u32 = 5;
+
+/// Simulation time, kept apart from real time so the simulation can be paused,
+/// stepped one update at a time or run slower or faster than real time.
+///
+/// Lag is clamped and the number of updates per frame capped, so a stalled frame can't
+/// make the game loop spend ever longer catching up. Time dropped that way is counted.
+pub struct SimulationClock {
+    step: Duration,
+    time_scale: f32,
+    paused: bool,
+    pending_steps: u32,
+    lag: Duration,
+    elapsed: Duration,
+    updates_this_frame: u32,
+    dropped_time: Duration,
+}
+
+impl SimulationClock {
+    /// clock running fixed updates of `step` simulation time each
+    pub fn new(step: Duration) -> Self {
+        SimulationClock {
+            step,
+            time_scale: 1.0,
+            paused: false,
+            pending_steps: 0,
+            lag: Duration::new(0, 0),
+            elapsed: Duration::new(0, 0),
+            updates_this_frame: 0,
+            dropped_time: Duration::new(0, 0),
+        }
+    }
+
+    /// let `real_time` pass, scaled by the time scale, nothing passes while paused.
+    /// Call once per frame.
+    pub fn advance(&mut self, real_time: Duration) {
+        self.updates_this_frame = 0;
+        if !self.paused {
+            self.lag += real_time.mul_f32(self.time_scale);
+        }
+        if self.lag > MAX_LAG {
+            self.dropped_time += self.lag - MAX_LAG;
+            self.lag = MAX_LAG;
+        }
+    }
+
+    /// true if an update is due, consuming its time. Call until it returns false.
+    pub fn tick(&mut self) -> bool {
+        let due = if self.paused {
+            self.pending_steps > 0
+        } else {
+            self.lag >= self.step
+        };
+        if !due {
+            return false;
+        }
+        if self.updates_this_frame == MAX_UPDATES_PER_FRAME {
+            if !self.paused {
+                // give up on the time we couldn't catch up on, keeping the partial update for interpolation
+                let partial = Duration::from_nanos((self.lag.as_nanos() % self.step.as_nanos()) as u64);
+                self.dropped_time += self.lag - partial;
+                self.lag = partial;
+            }
+            return false;
+        }
+
+        self.updates_this_frame += 1;
+        if self.paused {
+            self.pending_steps -= 1;
+        } else {
+            self.lag -= self.step;
+        }
+        self.elapsed += self.step;
+        true
+    }
+
+    /// how far the simulation is into the next update, for render interpolation
+    pub fn alpha(&self) -> f32 {
+        self.lag.as_secs_f32() / self.step.as_secs_f32()
+    }
+
+    /// simulation time passed in seconds
+    pub fn time(&self) -> f32 {
+        self.elapsed.as_secs_f32()
+    }
+
+    /// time that passed without being simulated because the loop fell behind
+    pub fn dropped_time(&self) -> Duration {
+        self.dropped_time
+    }
+
+    /// number of updates skipped because the loop fell behind
+    pub fn dropped_updates(&self) -> u64 {
+        (self.dropped_time.as_nanos() / self.step.as_nanos()) as u64
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        self.pending_steps = 0;
+    }
+
+    /// run a single update while paused
+    pub fn request_step(&mut self) {
+        if self.paused {
+            self.pending_steps += 1;
+        }
+    }
+
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale
+    }
+
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        self.time_scale = time_scale.clamp(MIN_TIME_SCALE, MAX_TIME_SCALE);
+    }
+
+    /// switch to the next larger preset time scale
+    pub fn faster(&mut self) {
+        if let Some(&time_scale) = TIME_SCALE_STEPS.iter().find(|&&step| step > self.time_scale) {
+            self.time_scale = time_scale;
+        }
+    }
+
+    /// switch to the next smaller preset time scale
+    pub fn slower(&mut self) {
+        if let Some(&time_scale) = TIME_SCALE_STEPS.iter().rev().find(|&&step| step < self.time_scale) {
+            self.time_scale = time_scale;
+        }
+    }
+}
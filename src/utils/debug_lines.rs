@@ -0,0 +1,134 @@
+use std::ffi::CString;
+use std::mem;
+use std::path::Path;
+
+use gl;
+use gl::types::{GLint, GLsizei, GLuint};
+
+use cgmath::prelude::*;
+use cgmath::{Matrix4, Point3, Vector4};
+
+use utils::bounds::Aabb;
+use utils::mesh::{VertexAttribute, VertexLayout};
+use utils::shader::Shader;
+use utils::stream_buffer::StreamBuffer;
+
+// room for lines in flight, lines are streamed a quarter of it at a time
+const STREAM_CAPACITY: usize = 1 << 20;
+const DRAW_BATCH_BYTES: usize = STREAM_CAPACITY / 4;
+
+// the corners of `Aabb::corners` joined by the edges of the box, corners differ in one bit per edge
+const BOX_EDGES: [(usize, usize); 12] = [
+    (0, 1), (2, 3), (4, 5), (6, 7),
+    (0, 2), (1, 3), (4, 6), (5, 7),
+    (0, 4), (1, 5), (2, 6), (3, 7),
+];
+
+/// Lines collected during a frame and drawn at once, for visualizing bounds and other debug data.
+///
+/// `draw` leaves the shader program, vertex array and array buffer bound as they were, so it
+/// can be called in the middle of rendering something else.
+pub struct DebugLines {
+    vao: GLuint,
+    stream: StreamBuffer,
+    shader: Shader,
+    layout: VertexLayout,
+    /// position and rgba color of every vertex, two vertices per line
+    vertices: Vec<f32>,
+}
+
+impl DebugLines {
+    pub unsafe fn new() -> DebugLines {
+        let layout = VertexLayout::new(vec![
+            VertexAttribute::float(0, 3), // position
+            VertexAttribute::float(1, 4), // color
+        ]).unwrap();
+        let mut vao = 0;
+        gl::GenVertexArrays(1, &mut vao);
+        let stream = StreamBuffer::new(gl::ARRAY_BUFFER, STREAM_CAPACITY);
+        gl::BindVertexArray(vao);
+        stream.bind();
+        layout.apply();
+        gl::BindVertexArray(0);
+        gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+
+        DebugLines {
+            vao,
+            stream,
+            shader: Shader::new(Path::new("src/shaders/lines.vs"), Path::new("src/shaders/lines.fs")),
+            layout,
+            vertices: Vec::new(),
+        }
+    }
+
+    /// lines added since the last `draw`
+    pub fn len(&self) -> usize {
+        self.vertices.len() / (self.layout.stride() / mem::size_of::<f32>()) / 2
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+
+    pub fn add_line(&mut self, from: Point3<f32>, to: Point3<f32>, color: Vector4<f32>) {
+        for point in &[from, to] {
+            self.vertices
+                .extend_from_slice(&[point.x, point.y, point.z, color.x, color.y, color.z, color.w]);
+        }
+    }
+
+    /// the edges of `aabb`, which is in world space
+    pub fn add_aabb(&mut self, aabb: &Aabb, color: Vector4<f32>) {
+        self.add_box(aabb, &Matrix4::identity(), color);
+    }
+
+    /// the edges of `aabb` transformed by `model`, which rotates them along with the object
+    pub fn add_box(&mut self, aabb: &Aabb, model: &Matrix4<f32>, color: Vector4<f32>) {
+        let corners = aabb.corners();
+        for &(from, to) in BOX_EDGES.iter() {
+            self.add_line(
+                model.transform_point(corners[from]),
+                model.transform_point(corners[to]),
+                color,
+            );
+        }
+    }
+
+    /// draw the lines added since the last call with depth testing, and forget them
+    pub unsafe fn draw(&mut self, view: &Matrix4<f32>, projection: &Matrix4<f32>) {
+        if self.vertices.is_empty() {
+            return;
+        }
+        let (mut program, mut vao, mut array_buffer): (GLint, GLint, GLint) = (0, 0, 0);
+        gl::GetIntegerv(gl::CURRENT_PROGRAM, &mut program);
+        gl::GetIntegerv(gl::VERTEX_ARRAY_BINDING, &mut vao);
+        gl::GetIntegerv(gl::ARRAY_BUFFER_BINDING, &mut array_buffer);
+
+        self.shader.use_program();
+        self.shader.set_matrix4(&CString::new("view").unwrap(), view);
+        self.shader.set_matrix4(&CString::new("projection").unwrap(), projection);
+
+        gl::BindVertexArray(self.vao);
+        // whole lines at offsets a multiple of the stride, so they can be drawn from their first vertex
+        let stride = self.layout.stride();
+        let line_floats = 2 * stride / mem::size_of::<f32>();
+        let batch_floats = DRAW_BATCH_BYTES / mem::size_of::<f32>() / line_floats * line_floats;
+        for batch in self.vertices.chunks(batch_floats) {
+            let offset = self.stream.write(batch, stride);
+            let vertex_count = self.layout.vertex_count(mem::size_of_val(batch)).unwrap();
+            gl::DrawArrays(gl::LINES, (offset / stride) as GLint, vertex_count as GLsizei);
+            self.stream.fence();
+        }
+        self.vertices.clear();
+
+        gl::UseProgram(program as GLuint);
+        gl::BindVertexArray(vao as GLuint);
+        gl::BindBuffer(gl::ARRAY_BUFFER, array_buffer as GLuint);
+    }
+
+    pub unsafe fn delete(self) {
+        gl::DeleteVertexArrays(1, &self.vao);
+        self.stream.delete();
+        self.shader.delete();
+    }
+}
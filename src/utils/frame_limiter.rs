@@ -0,0 +1,32 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Sleeps at the end of each frame so the game loop doesn't busy-spin faster than
+/// a target frame rate, e.g. when vsync is off.
+pub struct FrameLimiter {
+    frame_duration: Option<Duration>,
+    frame_start: Instant,
+}
+
+impl FrameLimiter {
+    /// limiter for at most `max_fps` frames per second, `None` doesn't limit
+    pub fn new(max_fps: Option<u32>) -> Self {
+        FrameLimiter {
+            frame_duration: max_fps
+                .filter(|fps| *fps > 0)
+                .map(|fps| Duration::from_secs(1) / fps),
+            frame_start: Instant::now(),
+        }
+    }
+
+    /// call once per frame after presenting it, sleeps for whatever is left of the frame
+    pub fn wait(&mut self) {
+        if let Some(frame_duration) = self.frame_duration {
+            let elapsed = self.frame_start.elapsed();
+            if elapsed < frame_duration {
+                thread::sleep(frame_duration - elapsed);
+            }
+        }
+        self.frame_start = Instant::now();
+    }
+}
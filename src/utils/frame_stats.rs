@@ -0,0 +1,167 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+// frames kept for the rolling statistics, enough for a meaningful 0.1% low
+pub const FRAME_HISTORY: usize = 1000;
+
+/// how long the parts of a single frame took
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FrameTiming {
+    /// whole frame, from the start of one frame to the start of the next
+    pub frame: Duration,
+    /// frame time spent on the CPU, everything but waiting for the swap and the frame limiter
+    pub cpu: Duration,
+    /// fixed updates of the camera and the simulation
+    pub update: Duration,
+    /// submitting draw calls
+    pub render: Duration,
+    /// presenting the frame, including any wait for vsync
+    pub swap: Duration,
+}
+
+/// Statistics over the frames in the history, times in milliseconds.
+///
+/// The lows are the frame rates the slowest 1% and 0.1% of the frames stayed under.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FrameSummary {
+    pub fps: f32,
+    pub min_ms: f32,
+    pub avg_ms: f32,
+    pub max_ms: f32,
+    pub low_1_percent_fps: f32,
+    pub low_0_1_percent_fps: f32,
+    pub avg_cpu_ms: f32,
+    pub avg_update_ms: f32,
+    pub avg_render_ms: f32,
+    pub avg_swap_ms: f32,
+}
+
+/// Timings of the last `FRAME_HISTORY` frames in a ring buffer.
+pub struct FrameStats {
+    timings: Vec<FrameTiming>,
+    // where the next timing goes once the buffer is full
+    next: usize,
+    frame_count: u64,
+}
+
+impl Default for FrameStats {
+    fn default() -> Self {
+        FrameStats::new()
+    }
+}
+
+impl FrameStats {
+    pub fn new() -> Self {
+        FrameStats {
+            timings: Vec::with_capacity(FRAME_HISTORY),
+            next: 0,
+            frame_count: 0,
+        }
+    }
+
+    pub fn record(&mut self, timing: FrameTiming) {
+        if self.timings.len() < FRAME_HISTORY {
+            self.timings.push(timing);
+        } else {
+            self.timings[self.next] = timing;
+        }
+        self.next = (self.next + 1) % FRAME_HISTORY;
+        self.frame_count += 1;
+    }
+
+    /// number of frames recorded so far, including those no longer in the history
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// statistics over the history, `None` before the first frame
+    pub fn summary(&self) -> Option<FrameSummary> {
+        if self.timings.is_empty() {
+            return None;
+        }
+        let count = self.timings.len() as f32;
+        let average = |part: fn(&FrameTiming) -> Duration| -> f32 {
+            self.timings.iter().map(|timing| milliseconds(part(timing))).sum::<f32>() / count
+        };
+
+        let mut frame_ms: Vec<f32> = self.timings.iter().map(|timing| milliseconds(timing.frame)).collect();
+        frame_ms.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let avg_ms = frame_ms.iter().sum::<f32>() / count;
+        // frame time the slowest `fraction` of the frames reached, at least the slowest frame
+        let low = |fraction: f32| -> f32 {
+            let slow_frames = ((count * fraction).ceil() as usize).max(1);
+            fps_from_ms(frame_ms[frame_ms.len() - slow_frames])
+        };
+
+        Some(FrameSummary {
+            fps: fps_from_ms(avg_ms),
+            min_ms: frame_ms[0],
+            avg_ms,
+            max_ms: frame_ms[frame_ms.len() - 1],
+            low_1_percent_fps: low(0.01),
+            low_0_1_percent_fps: low(0.001),
+            avg_cpu_ms: average(|timing| timing.cpu),
+            avg_update_ms: average(|timing| timing.update),
+            avg_render_ms: average(|timing| timing.render),
+            avg_swap_ms: average(|timing| timing.swap),
+        })
+    }
+}
+
+impl FrameSummary {
+    /// short form for the window title
+    pub fn title(&self) -> String {
+        format!(
+            "{:.0} fps ({:.1}/{:.1}/{:.1} ms, 1% low {:.0}, 0.1% low {:.0})",
+            self.fps, self.min_ms, self.avg_ms, self.max_ms, self.low_1_percent_fps, self.low_0_1_percent_fps
+        )
+    }
+}
+
+fn milliseconds(duration: Duration) -> f32 {
+    duration.as_secs_f32() * 1000.0
+}
+
+fn fps_from_ms(ms: f32) -> f32 {
+    if ms > 0.0 {
+        1000.0 / ms
+    } else {
+        0.0
+    }
+}
+
+/// Writes one CSV row per frame, times in milliseconds.
+pub struct FrameCsv {
+    writer: BufWriter<File>,
+    frame: u64,
+}
+
+impl FrameCsv {
+    /// create (or truncate) the file at `path` and write the header
+    pub fn create(path: &Path) -> io::Result<FrameCsv> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "frame,frame_ms,cpu_ms,update_ms,render_ms,swap_ms")?;
+        Ok(FrameCsv { writer, frame: 0 })
+    }
+
+    pub fn write(&mut self, timing: &FrameTiming) -> io::Result<()> {
+        writeln!(
+            self.writer,
+            "{},{:.3},{:.3},{:.3},{:.3},{:.3}",
+            self.frame,
+            milliseconds(timing.frame),
+            milliseconds(timing.cpu),
+            milliseconds(timing.update),
+            milliseconds(timing.render),
+            milliseconds(timing.swap)
+        )?;
+        self.frame += 1;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
@@ -0,0 +1,203 @@
+use cgmath::prelude::*;
+use cgmath::{Matrix4, Point3, Vector3, Vector4};
+
+use utils::bounds::{Aabb, BoundingSphere};
+
+/// plane `normal . p + distance = 0`, the normal points into the frustum
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Plane {
+    pub normal: Vector3<f32>,
+    pub distance: f32,
+}
+
+impl Plane {
+    fn from_coefficients(coefficients: Vector4<f32>) -> Self {
+        let length = coefficients.truncate().magnitude();
+        Plane {
+            normal: coefficients.truncate() / length,
+            distance: coefficients.w / length,
+        }
+    }
+
+    /// signed distance of `point` from the plane, positive on the inner side
+    pub fn signed_distance(&self, point: Point3<f32>) -> f32 {
+        self.normal.dot(point.to_vec()) + self.distance
+    }
+}
+
+/// The six planes of a view frustum, in the order left, right, bottom, top, near, far.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frustum {
+    pub planes: [Plane; 6],
+}
+
+impl Frustum {
+    /// extract the planes from a combined `projection * view` matrix,
+    /// the planes are then in world space
+    pub fn from_matrix(view_projection: &Matrix4<f32>) -> Self {
+        let m = view_projection;
+        let row = |i: usize| Vector4::new(m.x[i], m.y[i], m.z[i], m.w[i]);
+        let (row_0, row_1, row_2, row_3) = (row(0), row(1), row(2), row(3));
+
+        Frustum {
+            planes: [
+                Plane::from_coefficients(row_3 + row_0),
+                Plane::from_coefficients(row_3 - row_0),
+                Plane::from_coefficients(row_3 + row_1),
+                Plane::from_coefficients(row_3 - row_1),
+                Plane::from_coefficients(row_3 + row_2),
+                Plane::from_coefficients(row_3 - row_2),
+            ],
+        }
+    }
+
+    pub fn contains_point(&self, point: Point3<f32>) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.signed_distance(point) >= 0.0)
+    }
+
+    /// false only if the sphere is completely outside the frustum
+    pub fn intersects_sphere(&self, sphere: &BoundingSphere) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.signed_distance(sphere.center) >= -sphere.radius)
+    }
+
+    /// false only if the box is completely outside the frustum
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            // the corner furthest along the plane normal
+            let positive = Point3::new(
+                if plane.normal.x >= 0.0 { aabb.max.x } else { aabb.min.x },
+                if plane.normal.y >= 0.0 { aabb.max.y } else { aabb.min.y },
+                if plane.normal.z >= 0.0 { aabb.max.z } else { aabb.min.z },
+            );
+            plane.signed_distance(positive) >= 0.0
+        })
+    }
+}
+
+/// number of objects submitted and skipped during a frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CullStats {
+    pub drawn: usize,
+    pub culled: usize,
+}
+
+impl CullStats {
+    pub fn record(&mut self, visible: bool) {
+        if visible {
+            self.drawn += 1;
+        } else {
+            self.culled += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::vec3;
+    use utils::camera::Camera;
+
+    const LEFT: usize = 0;
+    const RIGHT: usize = 1;
+    const BOTTOM: usize = 2;
+    const TOP: usize = 3;
+    const FAR: usize = 5;
+
+    /// camera at (1, 2, 3) looking down the negative z axis with a 45 degree fov, near 0.1, far 100
+    fn camera() -> Camera {
+        Camera::from_yaw_pitch(Point3::new(1.0, 2.0, 3.0), -90.0, 0.0)
+    }
+
+    fn frustum() -> Frustum {
+        let camera = camera();
+        Frustum::from_matrix(&(camera.projection_matrix(1.0) * camera.view_matrix()))
+    }
+
+    /// point `distance` in front of the camera, moved sideways by `x` and up by `y`
+    fn ahead(x: f32, y: f32, distance: f32) -> Point3<f32> {
+        camera().position + vec3(x, y, -distance)
+    }
+
+    fn cube(center: Point3<f32>, half_size: f32) -> Aabb {
+        let half = vec3(half_size, half_size, half_size);
+        Aabb::new(center - half, center + half)
+    }
+
+    /// sideways distance from the view axis to the side planes at `distance`
+    fn half_width(distance: f32) -> f32 {
+        distance * (22.5f32).to_radians().tan()
+    }
+
+    #[test]
+    fn planes_point_inwards() {
+        let frustum = frustum();
+        let inside = ahead(0.0, 0.0, 10.0);
+        for plane in frustum.planes.iter() {
+            assert!((plane.normal.magnitude() - 1.0).abs() < 1e-5);
+            assert!(plane.signed_distance(inside) > 0.0);
+        }
+        assert!(frustum.contains_point(inside));
+        assert!(!frustum.contains_point(ahead(0.0, 0.0, -1.0)));
+    }
+
+    #[test]
+    fn keeps_objects_in_front() {
+        let frustum = frustum();
+        let center = ahead(0.0, 0.0, 10.0);
+        assert!(frustum.intersects_sphere(&BoundingSphere::new(center, 1.0)));
+        assert!(frustum.intersects_aabb(&cube(center, 1.0)));
+        // bigger than the whole frustum
+        assert!(frustum.intersects_sphere(&BoundingSphere::new(center, 500.0)));
+        assert!(frustum.intersects_aabb(&cube(center, 500.0)));
+    }
+
+    #[test]
+    fn culls_objects_behind() {
+        let frustum = frustum();
+        let center = ahead(0.0, 0.0, -10.0);
+        assert!(!frustum.intersects_sphere(&BoundingSphere::new(center, 1.0)));
+        assert!(!frustum.intersects_aabb(&cube(center, 1.0)));
+    }
+
+    #[test]
+    fn keeps_objects_straddling_a_plane() {
+        let frustum = frustum();
+        let edge = half_width(10.0);
+        let straddling = [
+            ahead(0.0, 0.0, 0.0),     // near plane
+            ahead(0.0, 0.0, 100.0),   // far plane
+            ahead(-edge, 0.0, 10.0),  // left
+            ahead(edge, 0.0, 10.0),   // right
+            ahead(0.0, -edge, 10.0),  // bottom
+            ahead(0.0, edge, 10.0),   // top
+        ];
+        for &center in straddling.iter() {
+            assert!(frustum.intersects_sphere(&BoundingSphere::new(center, 0.5)), "{:?}", center);
+            assert!(frustum.intersects_aabb(&cube(center, 0.5)), "{:?}", center);
+        }
+    }
+
+    #[test]
+    fn culls_objects_outside_each_side() {
+        let frustum = frustum();
+        let outside = half_width(10.0) + 2.0;
+        let cases = [
+            (LEFT, ahead(-outside, 0.0, 10.0)),
+            (RIGHT, ahead(outside, 0.0, 10.0)),
+            (BOTTOM, ahead(0.0, -outside, 10.0)),
+            (TOP, ahead(0.0, outside, 10.0)),
+            (FAR, ahead(0.0, 0.0, 102.0)),
+        ];
+        for &(plane, center) in cases.iter() {
+            let sphere = BoundingSphere::new(center, 1.0);
+            assert!(!frustum.intersects_sphere(&sphere), "{:?}", center);
+            assert!(!frustum.intersects_aabb(&cube(center, 1.0)), "{:?}", center);
+            // culled by the plane on that side
+            assert!(frustum.planes[plane].signed_distance(center) < -sphere.radius, "{:?}", center);
+        }
+    }
+}
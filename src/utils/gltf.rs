@@ -0,0 +1,1260 @@
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use cgmath::prelude::*;
+use cgmath::{vec2, vec3, vec4, Matrix4, Point3, Quaternion, Vector3, Vector4};
+
+use json;
+use json::JsonValue;
+
+use utils::mesh_data::MeshData;
+
+// first bytes of a binary glTF file, "glTF" in little endian
+const GLB_MAGIC: u32 = 0x4654_6C67;
+const GLB_CHUNK_JSON: u32 = 0x4E4F_534A;
+const GLB_CHUNK_BIN: u32 = 0x004E_4942;
+
+// accessor component types
+const BYTE: u32 = 5120;
+const UNSIGNED_BYTE: u32 = 5121;
+const SHORT: u32 = 5122;
+const UNSIGNED_SHORT: u32 = 5123;
+const UNSIGNED_INT: u32 = 5125;
+const FLOAT: u32 = 5126;
+
+// sampler wrap mode used when none is given
+const REPEAT: u32 = 10497;
+
+#[derive(Debug)]
+pub enum GltfError {
+    Io(String),
+    /// malformed JSON or GLB container
+    Format(String),
+    /// `path` points at the offending object, like `accessors[2].sparse`
+    Invalid { path: String, message: String },
+}
+
+impl fmt::Display for GltfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GltfError::Io(message) | GltfError::Format(message) => write!(f, "{}", message),
+            GltfError::Invalid { path, message } => write!(f, "{}: {}", path, message),
+        }
+    }
+}
+
+impl Error for GltfError {}
+
+fn invalid(path: &str, message: String) -> GltfError {
+    GltfError::Invalid {
+        path: path.to_string(),
+        message,
+    }
+}
+
+/// texture used by a material, `tex_coord` is the index of the TEXCOORD_n set
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextureRef {
+    /// index into `GltfScene::textures`
+    pub texture: usize,
+    pub tex_coord: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlphaMode {
+    Opaque,
+    /// fully transparent below `PbrMaterial::alpha_cutoff`, opaque otherwise
+    Mask,
+    Blend,
+}
+
+/// metallic-roughness material, defaults as given by the glTF spec
+#[derive(Debug, Clone, PartialEq)]
+pub struct PbrMaterial {
+    pub name: Option<String>,
+    /// linear rgba, multiplied with the base color texture
+    pub base_color_factor: Vector4<f32>,
+    pub base_color_texture: Option<TextureRef>,
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    /// roughness in the green channel, metalness in the blue channel
+    pub metallic_roughness_texture: Option<TextureRef>,
+    pub normal_texture: Option<TextureRef>,
+    pub normal_scale: f32,
+    pub occlusion_texture: Option<TextureRef>,
+    pub occlusion_strength: f32,
+    pub emissive_texture: Option<TextureRef>,
+    pub emissive_factor: Vector3<f32>,
+    pub alpha_mode: AlphaMode,
+    pub alpha_cutoff: f32,
+    pub double_sided: bool,
+}
+
+impl Default for PbrMaterial {
+    fn default() -> Self {
+        PbrMaterial {
+            name: None,
+            base_color_factor: vec4(1.0, 1.0, 1.0, 1.0),
+            base_color_texture: None,
+            metallic_factor: 1.0,
+            roughness_factor: 1.0,
+            metallic_roughness_texture: None,
+            normal_texture: None,
+            normal_scale: 1.0,
+            occlusion_texture: None,
+            occlusion_strength: 1.0,
+            emissive_texture: None,
+            emissive_factor: vec3(0.0, 0.0, 0.0),
+            alpha_mode: AlphaMode::Opaque,
+            alpha_cutoff: 0.5,
+            double_sided: false,
+        }
+    }
+}
+
+/// where the pixels of an image come from
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImageSource {
+    /// image file, relative to the glTF file unless loaded with `load_gltf`
+    File(PathBuf),
+    /// encoded image from a data URI or a buffer view
+    Embedded { mime_type: Option<String>, data: Vec<u8> },
+}
+
+/// filters and wrap modes as GL enums, filters are left to the renderer when `None`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sampler {
+    pub mag_filter: Option<u32>,
+    pub min_filter: Option<u32>,
+    pub wrap_s: u32,
+    pub wrap_t: u32,
+}
+
+impl Default for Sampler {
+    fn default() -> Self {
+        Sampler {
+            mag_filter: None,
+            min_filter: None,
+            wrap_s: REPEAT,
+            wrap_t: REPEAT,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfTexture {
+    /// index into `GltfScene::images`
+    pub image: Option<usize>,
+    pub sampler: Sampler,
+}
+
+/// part of a mesh drawn with a single material
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfPrimitive {
+    /// index into `GltfScene::materials`, `None` uses the default material
+    pub material: Option<usize>,
+    pub data: MeshData,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfMesh {
+    pub name: Option<String>,
+    pub primitives: Vec<GltfPrimitive>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfNode {
+    pub name: Option<String>,
+    /// transform relative to the parent node
+    pub local: Matrix4<f32>,
+    /// indices into `GltfScene::nodes`
+    pub children: Vec<usize>,
+    /// index into `GltfScene::meshes`
+    pub mesh: Option<usize>,
+}
+
+/// a mesh placed in the world by a node
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeshInstance {
+    pub mesh: usize,
+    pub node: usize,
+    pub transform: Matrix4<f32>,
+}
+
+/// The default scene of a glTF file with everything it references.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfScene {
+    pub nodes: Vec<GltfNode>,
+    /// nodes of the scene without a parent
+    pub roots: Vec<usize>,
+    pub meshes: Vec<GltfMesh>,
+    pub materials: Vec<PbrMaterial>,
+    pub textures: Vec<GltfTexture>,
+    pub images: Vec<ImageSource>,
+}
+
+impl GltfScene {
+    /// every mesh reachable from the roots with the world transform of its node
+    pub fn instances(&self) -> Vec<MeshInstance> {
+        let mut instances = Vec::new();
+        let mut stack: Vec<(usize, Matrix4<f32>)> = self
+            .roots
+            .iter()
+            .rev()
+            .map(|&root| (root, Matrix4::identity()))
+            .collect();
+        while let Some((index, parent)) = stack.pop() {
+            let node = &self.nodes[index];
+            let transform = parent * node.local;
+            if let Some(mesh) = node.mesh {
+                instances.push(MeshInstance {
+                    mesh,
+                    node: index,
+                    transform,
+                });
+            }
+            stack.extend(node.children.iter().rev().map(|&child| (child, transform)));
+        }
+        instances
+    }
+
+    pub fn material(&self, index: Option<usize>) -> PbrMaterial {
+        index.map(|index| self.materials[index].clone()).unwrap_or_default()
+    }
+}
+
+/// Load a `.gltf` or `.glb` file.
+///
+/// External buffers are read relative to the file, image paths are resolved relative to it.
+pub fn load_gltf(path: &Path) -> Result<GltfScene, GltfError> {
+    let contents = read_file(path)?;
+    let directory = path.parent().unwrap_or_else(|| Path::new("")).to_path_buf();
+    let mut load_buffer = |uri: &str| read_file(&directory.join(uri));
+    let mut scene = if contents.starts_with(b"glTF") {
+        let (json, bin) = parse_glb(&contents)?;
+        parse_gltf(json, bin, &mut load_buffer)?
+    } else {
+        let json = String::from_utf8(contents)
+            .map_err(|_| GltfError::Format(format!("{} is neither UTF-8 JSON nor GLB", path.display())))?;
+        parse_gltf(&json, None, &mut load_buffer)?
+    };
+    for image in scene.images.iter_mut() {
+        if let ImageSource::File(ref mut file) = *image {
+            *file = directory.join(&*file);
+        }
+    }
+    Ok(scene)
+}
+
+fn read_file(path: &Path) -> Result<Vec<u8>, GltfError> {
+    let mut file = File::open(path).map_err(|e| GltfError::Io(format!("Failed to open {}: {}", path.display(), e)))?;
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents)
+        .map_err(|e| GltfError::Io(format!("Failed to read {}: {}", path.display(), e)))?;
+    Ok(contents)
+}
+
+/// split a GLB container into its JSON chunk and optional binary chunk
+pub fn parse_glb(contents: &[u8]) -> Result<(&str, Option<&[u8]>), GltfError> {
+    let format_error = |message: &str| GltfError::Format(format!("invalid GLB: {}", message));
+    if contents.len() < 12 || read_u32(contents, 0) != GLB_MAGIC {
+        return Err(format_error("missing glTF header"));
+    }
+    let version = read_u32(contents, 4);
+    if version != 2 {
+        return Err(GltfError::Format(format!("GLB version {} is not supported, expected 2", version)));
+    }
+    let length = read_u32(contents, 8) as usize;
+    if length > contents.len() {
+        return Err(format_error("file is shorter than its header says"));
+    }
+
+    let mut json = None;
+    let mut bin = None;
+    let mut offset = 12;
+    while offset < length {
+        if offset + 8 > length {
+            return Err(format_error("truncated chunk header"));
+        }
+        let chunk_length = read_u32(contents, offset) as usize;
+        let chunk_type = read_u32(contents, offset + 4);
+        let start = offset + 8;
+        if start + chunk_length > length {
+            return Err(format_error("chunk runs past the end of the file"));
+        }
+        let data = &contents[start..start + chunk_length];
+        match chunk_type {
+            GLB_CHUNK_JSON if json.is_none() => {
+                json = Some(std::str::from_utf8(data).map_err(|_| format_error("JSON chunk is not UTF-8"))?)
+            }
+            GLB_CHUNK_BIN if bin.is_none() => bin = Some(data),
+            // unknown chunks are skipped, as the spec asks
+            _ => (),
+        }
+        // chunks are padded to 4 bytes
+        offset = start + chunk_length.div_ceil(4) * 4;
+    }
+    let json = json.ok_or_else(|| format_error("no JSON chunk"))?;
+    Ok((json, bin))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from(bytes[offset])
+        | u32::from(bytes[offset + 1]) << 8
+        | u32::from(bytes[offset + 2]) << 16
+        | u32::from(bytes[offset + 3]) << 24
+}
+
+/// Parse the JSON of a glTF file.
+///
+/// `bin` is the binary chunk of a GLB file, used by the buffer without a URI. `load_buffer`
+/// is called with the (decoded) URI of every external buffer. Primitives are turned into
+/// triangle lists with `POSITION`, `NORMAL`, `TANGENT`, `TEXCOORD_0` and `COLOR_0`, texture
+/// coords are flipped to have v pointing up like the rest of the renderer expects.
+pub fn parse_gltf(
+    json: &str,
+    bin: Option<&[u8]>,
+    load_buffer: &mut dyn FnMut(&str) -> Result<Vec<u8>, GltfError>,
+) -> Result<GltfScene, GltfError> {
+    let root = json::parse(json).map_err(|e| GltfError::Format(format!("invalid JSON: {}", e)))?;
+
+    let version = root["asset"]["version"].as_str().unwrap_or("");
+    if !version.starts_with("2.") {
+        return Err(invalid("asset.version", format!("'{}' is not supported, expected 2.x", version)));
+    }
+
+    let buffers = parse_buffers(&root, bin, load_buffer)?;
+    let document = Document { root: &root, buffers };
+
+    let images = document.parse_images()?;
+    let textures = document.parse_textures(images.len())?;
+    let materials = document.parse_materials(textures.len())?;
+    let meshes = document.parse_meshes(materials.len())?;
+    let nodes = document.parse_nodes(meshes.len())?;
+    let roots = document.scene_roots(&nodes)?;
+    Ok(GltfScene {
+        nodes,
+        roots,
+        meshes,
+        materials,
+        textures,
+        images,
+    })
+}
+
+fn parse_buffers(
+    root: &JsonValue,
+    bin: Option<&[u8]>,
+    load_buffer: &mut dyn FnMut(&str) -> Result<Vec<u8>, GltfError>,
+) -> Result<Vec<Vec<u8>>, GltfError> {
+    let mut buffers = Vec::new();
+    for (i, buffer) in root["buffers"].members().enumerate() {
+        let path = format!("buffers[{}]", i);
+        let byte_length = required_usize(buffer, "byteLength", &path)?;
+        let data = match buffer["uri"].as_str() {
+            Some(uri) if uri.starts_with("data:") => decode_data_uri(uri).map_err(|message| invalid(&path, message))?.1,
+            Some(uri) => load_buffer(&percent_decode(uri))?,
+            None => match bin {
+                Some(bin) if i == 0 => bin.to_vec(),
+                _ => return Err(invalid(&path, "has no URI and there is no GLB binary chunk".to_string())),
+            },
+        };
+        if data.len() < byte_length {
+            return Err(invalid(
+                &path,
+                format!("holds {} bytes, byteLength is {}", data.len(), byte_length),
+            ));
+        }
+        buffers.push(data);
+    }
+    Ok(buffers)
+}
+
+/// mime type and bytes of a base64 `data:` URI
+fn decode_data_uri(uri: &str) -> Result<(Option<String>, Vec<u8>), String> {
+    let mut parts = uri["data:".len()..].splitn(2, ',');
+    let header = parts.next().unwrap_or("");
+    let data = parts.next().ok_or_else(|| "data URI without ','".to_string())?;
+    if !header.ends_with(";base64") {
+        return Err("only base64 data URIs are supported".to_string());
+    }
+    let mime_type = &header[..header.len() - ";base64".len()];
+    let mime_type = if mime_type.is_empty() {
+        None
+    } else {
+        Some(mime_type.to_string())
+    };
+    Ok((mime_type, decode_base64(data)?))
+}
+
+fn decode_base64(data: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::with_capacity(data.len() * 3 / 4);
+    let mut bits: u32 = 0;
+    let mut bit_count = 0;
+    for c in data.trim_end_matches('=').chars() {
+        let value = match c {
+            'A'..='Z' => c as u32 - 'A' as u32,
+            'a'..='z' => c as u32 - 'a' as u32 + 26,
+            '0'..='9' => c as u32 - '0' as u32 + 52,
+            '+' => 62,
+            '/' => 63,
+            _ => return Err(format!("'{}' is not a base64 character", c)),
+        };
+        bits = bits << 6 | value;
+        bit_count += 6;
+        if bit_count >= 8 {
+            bit_count -= 8;
+            bytes.push((bits >> bit_count) as u8);
+            bits &= (1 << bit_count) - 1;
+        }
+    }
+    Ok(bytes)
+}
+
+/// undo the %XX escapes of a relative URI
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = if bytes[i] == b'%' && i + 2 < bytes.len() {
+            std::str::from_utf8(&bytes[i + 1..i + 3])
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        } else {
+            None
+        };
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+// json helpers, `path` names the object for error messages
+
+fn optional_usize(object: &JsonValue, key: &str, path: &str) -> Result<Option<usize>, GltfError> {
+    let value = &object[key];
+    if value.is_null() {
+        return Ok(None);
+    }
+    value
+        .as_usize()
+        .map(Some)
+        .ok_or_else(|| invalid(path, format!("{} must be a non-negative integer", key)))
+}
+
+fn required_usize(object: &JsonValue, key: &str, path: &str) -> Result<usize, GltfError> {
+    optional_usize(object, key, path)?.ok_or_else(|| invalid(path, format!("{} is missing", key)))
+}
+
+/// index `key` of `object` that has to point into a list of `count` items
+fn optional_index(object: &JsonValue, key: &str, count: usize, path: &str) -> Result<Option<usize>, GltfError> {
+    match optional_usize(object, key, path)? {
+        Some(index) if index >= count => Err(invalid(
+            path,
+            format!("{} {} is out of range, there are {}", key, index, count),
+        )),
+        index => Ok(index),
+    }
+}
+
+fn optional_f32(object: &JsonValue, key: &str, path: &str) -> Result<Option<f32>, GltfError> {
+    let value = &object[key];
+    if value.is_null() {
+        return Ok(None);
+    }
+    value
+        .as_f32()
+        .map(Some)
+        .ok_or_else(|| invalid(path, format!("{} must be a number", key)))
+}
+
+/// array of exactly `len` numbers
+fn optional_floats(object: &JsonValue, key: &str, len: usize, path: &str) -> Result<Option<Vec<f32>>, GltfError> {
+    let value = &object[key];
+    if value.is_null() {
+        return Ok(None);
+    }
+    let numbers: Option<Vec<f32>> = value.members().map(|number| number.as_f32()).collect();
+    match numbers {
+        Some(ref numbers) if value.is_array() && numbers.len() == len => Ok(Some(numbers.clone())),
+        _ => Err(invalid(path, format!("{} must be an array of {} numbers", key, len))),
+    }
+}
+
+fn optional_string(object: &JsonValue, key: &str) -> Option<String> {
+    object[key].as_str().map(|name| name.to_string())
+}
+
+/// the parsed JSON together with the loaded buffers
+struct Document<'a> {
+    root: &'a JsonValue,
+    buffers: Vec<Vec<u8>>,
+}
+
+impl<'a> Document<'a> {
+    fn parse_images(&self) -> Result<Vec<ImageSource>, GltfError> {
+        let mut images = Vec::new();
+        for (i, image) in self.root["images"].members().enumerate() {
+            let path = format!("images[{}]", i);
+            let source = match image["uri"].as_str() {
+                Some(uri) if uri.starts_with("data:") => {
+                    let (mime_type, data) = decode_data_uri(uri).map_err(|message| invalid(&path, message))?;
+                    ImageSource::Embedded { mime_type, data }
+                }
+                Some(uri) => ImageSource::File(PathBuf::from(percent_decode(uri))),
+                None => {
+                    let view = required_usize(image, "bufferView", &path)?;
+                    let (data, _) = self.buffer_view(view)?;
+                    ImageSource::Embedded {
+                        mime_type: optional_string(image, "mimeType"),
+                        data: data.to_vec(),
+                    }
+                }
+            };
+            images.push(source);
+        }
+        Ok(images)
+    }
+
+    fn parse_textures(&self, image_count: usize) -> Result<Vec<GltfTexture>, GltfError> {
+        let mut samplers = Vec::new();
+        for (i, sampler) in self.root["samplers"].members().enumerate() {
+            let path = format!("samplers[{}]", i);
+            let gl_enum = |key| optional_usize(sampler, key, &path).map(|value| value.map(|value| value as u32));
+            samplers.push(Sampler {
+                mag_filter: gl_enum("magFilter")?,
+                min_filter: gl_enum("minFilter")?,
+                wrap_s: gl_enum("wrapS")?.unwrap_or(REPEAT),
+                wrap_t: gl_enum("wrapT")?.unwrap_or(REPEAT),
+            });
+        }
+
+        let mut textures = Vec::new();
+        for (i, texture) in self.root["textures"].members().enumerate() {
+            let path = format!("textures[{}]", i);
+            let sampler = optional_index(texture, "sampler", samplers.len(), &path)?;
+            textures.push(GltfTexture {
+                image: optional_index(texture, "source", image_count, &path)?,
+                sampler: sampler.map(|sampler| samplers[sampler]).unwrap_or_default(),
+            });
+        }
+        Ok(textures)
+    }
+
+    fn parse_materials(&self, texture_count: usize) -> Result<Vec<PbrMaterial>, GltfError> {
+        let mut materials = Vec::new();
+        for (i, material) in self.root["materials"].members().enumerate() {
+            let path = format!("materials[{}]", i);
+            let texture_ref = |object: &JsonValue, key: &str| -> Result<Option<TextureRef>, GltfError> {
+                let info = &object[key];
+                if info.is_null() {
+                    return Ok(None);
+                }
+                let info_path = format!("{}.{}", path, key);
+                let texture = required_usize(info, "index", &info_path)?;
+                if texture >= texture_count {
+                    return Err(invalid(&info_path, format!("texture {} is out of range", texture)));
+                }
+                Ok(Some(TextureRef {
+                    texture,
+                    tex_coord: optional_usize(info, "texCoord", &info_path)?.unwrap_or(0) as u32,
+                }))
+            };
+
+            let mut parsed = PbrMaterial {
+                name: optional_string(material, "name"),
+                ..PbrMaterial::default()
+            };
+            let pbr = &material["pbrMetallicRoughness"];
+            if let Some(factor) = optional_floats(pbr, "baseColorFactor", 4, &path)? {
+                parsed.base_color_factor = vec4(factor[0], factor[1], factor[2], factor[3]);
+            }
+            parsed.base_color_texture = texture_ref(pbr, "baseColorTexture")?;
+            parsed.metallic_factor = optional_f32(pbr, "metallicFactor", &path)?.unwrap_or(1.0);
+            parsed.roughness_factor = optional_f32(pbr, "roughnessFactor", &path)?.unwrap_or(1.0);
+            parsed.metallic_roughness_texture = texture_ref(pbr, "metallicRoughnessTexture")?;
+
+            parsed.normal_texture = texture_ref(material, "normalTexture")?;
+            parsed.normal_scale = optional_f32(&material["normalTexture"], "scale", &path)?.unwrap_or(1.0);
+            parsed.occlusion_texture = texture_ref(material, "occlusionTexture")?;
+            parsed.occlusion_strength =
+                optional_f32(&material["occlusionTexture"], "strength", &path)?.unwrap_or(1.0);
+            parsed.emissive_texture = texture_ref(material, "emissiveTexture")?;
+            if let Some(factor) = optional_floats(material, "emissiveFactor", 3, &path)? {
+                parsed.emissive_factor = vec3(factor[0], factor[1], factor[2]);
+            }
+
+            parsed.alpha_mode = match material["alphaMode"].as_str() {
+                None | Some("OPAQUE") => AlphaMode::Opaque,
+                Some("MASK") => AlphaMode::Mask,
+                Some("BLEND") => AlphaMode::Blend,
+                Some(mode) => return Err(invalid(&path, format!("unknown alphaMode '{}'", mode))),
+            };
+            parsed.alpha_cutoff = optional_f32(material, "alphaCutoff", &path)?.unwrap_or(0.5);
+            parsed.double_sided = material["doubleSided"].as_bool().unwrap_or(false);
+            materials.push(parsed);
+        }
+        Ok(materials)
+    }
+
+    fn parse_meshes(&self, material_count: usize) -> Result<Vec<GltfMesh>, GltfError> {
+        let mut meshes = Vec::new();
+        for (i, mesh) in self.root["meshes"].members().enumerate() {
+            let mut primitives = Vec::new();
+            for (j, primitive) in mesh["primitives"].members().enumerate() {
+                let path = format!("meshes[{}].primitives[{}]", i, j);
+                primitives.push(GltfPrimitive {
+                    material: optional_index(primitive, "material", material_count, &path)?,
+                    data: self.primitive_data(primitive, &path)?,
+                });
+            }
+            meshes.push(GltfMesh {
+                name: optional_string(mesh, "name"),
+                primitives,
+            });
+        }
+        Ok(meshes)
+    }
+
+    fn primitive_data(&self, primitive: &JsonValue, path: &str) -> Result<MeshData, GltfError> {
+        let attributes = &primitive["attributes"];
+        let attribute = |name: &str, allowed_components: &[usize]| -> Result<Option<Accessor>, GltfError> {
+            match optional_usize(attributes, name, path)? {
+                Some(index) => {
+                    let accessor = self.read_accessor(index)?;
+                    if !allowed_components.contains(&accessor.components) {
+                        return Err(invalid(
+                            path,
+                            format!("{} has {} components per vertex", name, accessor.components),
+                        ));
+                    }
+                    Ok(Some(accessor))
+                }
+                None => Ok(None),
+            }
+        };
+
+        let mut data = MeshData::default();
+        let positions = attribute("POSITION", &[3])?.ok_or_else(|| invalid(path, "POSITION is missing".to_string()))?;
+        data.positions = positions
+            .elements()
+            .map(|p| Point3::new(p[0] as f32, p[1] as f32, p[2] as f32))
+            .collect();
+        if let Some(normals) = attribute("NORMAL", &[3])? {
+            data.normals = normals
+                .elements()
+                .map(|n| vec3(n[0] as f32, n[1] as f32, n[2] as f32))
+                .collect();
+        }
+        if let Some(tangents) = attribute("TANGENT", &[4])? {
+            data.tangents = tangents
+                .elements()
+                .map(|t| vec4(t[0] as f32, t[1] as f32, t[2] as f32, t[3] as f32))
+                .collect();
+        }
+        if let Some(tex_coords) = attribute("TEXCOORD_0", &[2])? {
+            // glTF has the origin at the top left of the image
+            data.tex_coords = tex_coords
+                .elements()
+                .map(|t| vec2(t[0] as f32, 1.0 - t[1] as f32))
+                .collect();
+        }
+        if let Some(colors) = attribute("COLOR_0", &[3, 4])? {
+            data.colors = colors
+                .elements()
+                .map(|c| vec4(c[0] as f32, c[1] as f32, c[2] as f32, *c.get(3).unwrap_or(&1.0) as f32))
+                .collect();
+        }
+
+        let vertices: Vec<u32> = match optional_usize(primitive, "indices", path)? {
+            Some(index) => {
+                let indices = self.read_accessor(index)?;
+                if indices.components != 1 || indices.component_type == FLOAT {
+                    return Err(invalid(path, "indices must be unsigned integer scalars".to_string()));
+                }
+                indices.values.iter().map(|&index| index as u32).collect()
+            }
+            None => (0..data.vertex_count() as u32).collect(),
+        };
+        data.indices = match optional_usize(primitive, "mode", path)?.unwrap_or(4) {
+            4 => vertices,
+            5 => triangle_strip(&vertices),
+            6 => triangle_fan(&vertices),
+            mode => return Err(invalid(path, format!("mode {} is not a triangle mode", mode))),
+        };
+        data.validate().map_err(|e| invalid(path, e.to_string()))?;
+        Ok(data)
+    }
+
+    /// bytes of a buffer view and its stride, if it has one
+    fn buffer_view(&self, index: usize) -> Result<(&[u8], Option<usize>), GltfError> {
+        let path = format!("bufferViews[{}]", index);
+        let view = &self.root["bufferViews"][index];
+        if view.is_null() {
+            return Err(invalid(&path, "doesn't exist".to_string()));
+        }
+        let buffer = required_usize(view, "buffer", &path)?;
+        let buffer = self
+            .buffers
+            .get(buffer)
+            .ok_or_else(|| invalid(&path, format!("buffer {} doesn't exist", buffer)))?;
+        let offset = optional_usize(view, "byteOffset", &path)?.unwrap_or(0);
+        let length = required_usize(view, "byteLength", &path)?;
+        if offset.checked_add(length).is_none_or(|end| end > buffer.len()) {
+            return Err(invalid(&path, "runs past the end of its buffer".to_string()));
+        }
+        let stride = optional_usize(view, "byteStride", &path)?;
+        Ok((&buffer[offset..offset + length], stride))
+    }
+
+    /// read every element of an accessor, sparse values applied and normalized integers mapped to floats
+    fn read_accessor(&self, index: usize) -> Result<Accessor, GltfError> {
+        let path = format!("accessors[{}]", index);
+        let accessor = &self.root["accessors"][index];
+        if accessor.is_null() {
+            return Err(invalid(&path, "doesn't exist".to_string()));
+        }
+        let component_type = required_usize(accessor, "componentType", &path)? as u32;
+        let component_bytes = component_size(component_type)
+            .ok_or_else(|| invalid(&path, format!("unknown componentType {}", component_type)))?;
+        let components = match accessor["type"].as_str().unwrap_or("") {
+            "SCALAR" => 1,
+            "VEC2" => 2,
+            "VEC3" => 3,
+            "VEC4" => 4,
+            "MAT2" => 4,
+            "MAT3" => 9,
+            "MAT4" => 16,
+            other => return Err(invalid(&path, format!("unknown type '{}'", other))),
+        };
+        let normalized = accessor["normalized"].as_bool().unwrap_or(false);
+        let count = required_usize(accessor, "count", &path)?;
+        let element_size = components * component_bytes;
+
+        // counts come from the file, check them against the bytes they're read from before allocating for them
+        let view = match optional_usize(accessor, "bufferView", &path)? {
+            Some(view) => {
+                let (bytes, stride) = self.buffer_view(view)?;
+                let offset = optional_usize(accessor, "byteOffset", &path)?.unwrap_or(0);
+                let stride = stride.unwrap_or(element_size);
+                let end = match count.checked_sub(1) {
+                    Some(last) => last
+                        .checked_mul(stride)
+                        .and_then(|start| start.checked_add(offset))
+                        .and_then(|start| start.checked_add(element_size)),
+                    None => Some(0),
+                };
+                if end.is_none_or(|end| end > bytes.len()) {
+                    return Err(invalid(&path, format!("{} elements don't fit into bufferView {}", count, view)));
+                }
+                Some((bytes, offset, stride))
+            }
+            None => {
+                // all zeros, which a real file holds as much data for in sparse values or other accessors
+                let buffer_bytes: usize = self.buffers.iter().map(|buffer| buffer.len()).sum();
+                if count.checked_mul(element_size).is_none_or(|size| size > buffer_bytes) {
+                    return Err(invalid(
+                        &path,
+                        format!("{} elements without a bufferView are more than the buffers hold", count),
+                    ));
+                }
+                None
+            }
+        };
+
+        // accessors without a buffer view are all zeros, unless sparse says otherwise
+        let mut values = vec![0.0; count * components];
+        if let Some((bytes, offset, stride)) = view {
+            for element in 0..count {
+                for component in 0..components {
+                    values[element * components + component] = read_component(
+                        bytes,
+                        offset + element * stride + component * component_bytes,
+                        component_type,
+                        normalized,
+                    );
+                }
+            }
+        }
+
+        let sparse = &accessor["sparse"];
+        if !sparse.is_null() {
+            let sparse_path = format!("{}.sparse", path);
+            let sparse_count = required_usize(sparse, "count", &sparse_path)?;
+            let indices_path = format!("{}.indices", sparse_path);
+            let indices = &sparse["indices"];
+            let (index_bytes, _) = self.buffer_view(required_usize(indices, "bufferView", &indices_path)?)?;
+            let index_offset = optional_usize(indices, "byteOffset", &indices_path)?.unwrap_or(0);
+            let index_type = required_usize(indices, "componentType", &indices_path)? as u32;
+            let index_size = match index_type {
+                UNSIGNED_BYTE | UNSIGNED_SHORT | UNSIGNED_INT => component_size(index_type).unwrap(),
+                _ => return Err(invalid(&indices_path, format!("componentType {} is not unsigned", index_type))),
+            };
+            let values_path = format!("{}.values", sparse_path);
+            let sparse_values = &sparse["values"];
+            let (value_bytes, _) = self.buffer_view(required_usize(sparse_values, "bufferView", &values_path)?)?;
+            let value_offset = optional_usize(sparse_values, "byteOffset", &values_path)?.unwrap_or(0);
+            let fits = |offset: usize, size: usize, bytes: &[u8]| {
+                sparse_count
+                    .checked_mul(size)
+                    .and_then(|size| size.checked_add(offset))
+                    .is_some_and(|end| end <= bytes.len())
+            };
+            if !fits(index_offset, index_size, index_bytes) {
+                return Err(invalid(&indices_path, format!("{} indices don't fit", sparse_count)));
+            }
+            if !fits(value_offset, element_size, value_bytes) {
+                return Err(invalid(&values_path, format!("{} values don't fit", sparse_count)));
+            }
+            for i in 0..sparse_count {
+                let element = read_component(index_bytes, index_offset + i * index_size, index_type, false) as usize;
+                if element >= count {
+                    return Err(invalid(
+                        &indices_path,
+                        format!("index {} is out of range, the accessor has {} elements", element, count),
+                    ));
+                }
+                for component in 0..components {
+                    values[element * components + component] = read_component(
+                        value_bytes,
+                        value_offset + i * element_size + component * component_bytes,
+                        component_type,
+                        normalized,
+                    );
+                }
+            }
+        }
+
+        Ok(Accessor {
+            component_type,
+            components,
+            values,
+        })
+    }
+
+    fn parse_nodes(&self, mesh_count: usize) -> Result<Vec<GltfNode>, GltfError> {
+        let node_count = self.root["nodes"].len();
+        let mut nodes = Vec::new();
+        for (i, node) in self.root["nodes"].members().enumerate() {
+            let path = format!("nodes[{}]", i);
+            let local = match optional_floats(node, "matrix", 16, &path)? {
+                Some(m) => Matrix4::new(
+                    m[0], m[1], m[2], m[3], m[4], m[5], m[6], m[7], m[8], m[9], m[10], m[11], m[12], m[13], m[14], m[15],
+                ),
+                None => {
+                    let t = optional_floats(node, "translation", 3, &path)?.unwrap_or_else(|| vec![0.0; 3]);
+                    let r = optional_floats(node, "rotation", 4, &path)?.unwrap_or_else(|| vec![0.0, 0.0, 0.0, 1.0]);
+                    let s = optional_floats(node, "scale", 3, &path)?.unwrap_or_else(|| vec![1.0; 3]);
+                    // rotations are stored as x, y, z, w
+                    Matrix4::from_translation(vec3(t[0], t[1], t[2]))
+                        * Matrix4::from(Quaternion::new(r[3], r[0], r[1], r[2]).normalize())
+                        * Matrix4::from_nonuniform_scale(s[0], s[1], s[2])
+                }
+            };
+            let mut children = Vec::new();
+            for child in node["children"].members() {
+                match child.as_usize() {
+                    Some(child) if child < node_count => children.push(child),
+                    _ => return Err(invalid(&path, format!("child {} is not a node", child))),
+                }
+            }
+            nodes.push(GltfNode {
+                name: optional_string(node, "name"),
+                local,
+                children,
+                mesh: optional_index(node, "mesh", mesh_count, &path)?,
+            });
+        }
+
+        // the hierarchy has to be a forest, every node with at most one parent
+        let mut parent: Vec<Option<usize>> = vec![None; nodes.len()];
+        for (i, node) in nodes.iter().enumerate() {
+            for &child in &node.children {
+                if child == i || parent[child].is_some() {
+                    return Err(invalid(
+                        &format!("nodes[{}]", child),
+                        "has more than one parent or is its own child".to_string(),
+                    ));
+                }
+                parent[child] = Some(i);
+            }
+        }
+        for start in 0..nodes.len() {
+            let mut ancestor = parent[start];
+            let mut depth = 0;
+            while let Some(node) = ancestor {
+                depth += 1;
+                if node == start || depth > nodes.len() {
+                    return Err(invalid(&format!("nodes[{}]", start), "is part of a cycle".to_string()));
+                }
+                ancestor = parent[node];
+            }
+        }
+        Ok(nodes)
+    }
+
+    /// root nodes of the default scene, or of the first scene, or every node without a parent
+    fn scene_roots(&self, nodes: &[GltfNode]) -> Result<Vec<usize>, GltfError> {
+        let scene_count = self.root["scenes"].len();
+        let scene = match optional_index(self.root, "scene", scene_count, "scene")? {
+            Some(scene) => Some(scene),
+            None if scene_count > 0 => Some(0),
+            None => None,
+        };
+        match scene {
+            Some(scene) => {
+                let path = format!("scenes[{}]", scene);
+                let mut roots = Vec::new();
+                for root in self.root["scenes"][scene]["nodes"].members() {
+                    match root.as_usize() {
+                        Some(root) if root < nodes.len() => roots.push(root),
+                        _ => return Err(invalid(&path, format!("{} is not a node", root))),
+                    }
+                }
+                Ok(roots)
+            }
+            None => {
+                let children: Vec<usize> = nodes.iter().flat_map(|node| node.children.iter().cloned()).collect();
+                Ok((0..nodes.len()).filter(|node| !children.contains(node)).collect())
+            }
+        }
+    }
+}
+
+/// accessor elements flattened into `values`, `components` values per element
+struct Accessor {
+    component_type: u32,
+    components: usize,
+    values: Vec<f64>,
+}
+
+impl Accessor {
+    fn elements(&self) -> std::slice::Chunks<'_, f64> {
+        self.values.chunks(self.components)
+    }
+}
+
+fn component_size(component_type: u32) -> Option<usize> {
+    match component_type {
+        BYTE | UNSIGNED_BYTE => Some(1),
+        SHORT | UNSIGNED_SHORT => Some(2),
+        UNSIGNED_INT | FLOAT => Some(4),
+        _ => None,
+    }
+}
+
+/// a single little endian component, normalized integers are mapped to [0, 1] or [-1, 1]
+fn read_component(bytes: &[u8], offset: usize, component_type: u32, normalized: bool) -> f64 {
+    let read_u16 = || u16::from(bytes[offset]) | u16::from(bytes[offset + 1]) << 8;
+    match (component_type, normalized) {
+        (BYTE, false) => f64::from(bytes[offset] as i8),
+        (BYTE, true) => (f64::from(bytes[offset] as i8) / 127.0).max(-1.0),
+        (UNSIGNED_BYTE, false) => f64::from(bytes[offset]),
+        (UNSIGNED_BYTE, true) => f64::from(bytes[offset]) / 255.0,
+        (SHORT, false) => f64::from(read_u16() as i16),
+        (SHORT, true) => (f64::from(read_u16() as i16) / 32767.0).max(-1.0),
+        (UNSIGNED_SHORT, false) => f64::from(read_u16()),
+        (UNSIGNED_SHORT, true) => f64::from(read_u16()) / 65535.0,
+        (UNSIGNED_INT, _) => f64::from(read_u32(bytes, offset)),
+        _ => f64::from(f32::from_bits(read_u32(bytes, offset))),
+    }
+}
+
+/// triangle list from a strip, every other triangle flipped to keep the winding
+fn triangle_strip(strip: &[u32]) -> Vec<u32> {
+    let mut triangles = Vec::new();
+    for i in 2..strip.len() {
+        if i.is_multiple_of(2) {
+            triangles.extend_from_slice(&[strip[i - 2], strip[i - 1], strip[i]]);
+        } else {
+            triangles.extend_from_slice(&[strip[i - 1], strip[i - 2], strip[i]]);
+        }
+    }
+    triangles
+}
+
+fn triangle_fan(fan: &[u32]) -> Vec<u32> {
+    let mut triangles = Vec::new();
+    for i in 2..fan.len() {
+        triangles.extend_from_slice(&[fan[0], fan[i - 1], fan[i]]);
+    }
+    triangles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a triangle with its positions in bufferView 0 and sparse data for them in the other two
+    const TRIANGLE: &str = r#"{
+        "asset": { "version": "2.0" },
+        "buffers": [ { BUFFER "byteLength": 52 } ],
+        "bufferViews": [
+            { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+            { "buffer": 0, "byteOffset": 36, "byteLength": 4 },
+            { "buffer": 0, "byteOffset": 40, "byteLength": 12 }
+        ],
+        "accessors": [ { ACCESSOR "componentType": 5126, "count": 3, "type": "VEC3" } ],
+        "meshes": [ { "primitives": [ { "attributes": { "POSITION": 0 } } ] } ],
+        "nodes": NODES
+    }"#;
+
+    const POSITIONS: &str = r#""bufferView": 0,"#;
+    const ONE_NODE: &str = r#"[ { "mesh": 0 } ]"#;
+
+    /// positions (0, 0, 0), (1, 0, 0), (0, 1, 0), then sparse index 2 and the value (0, 2, 0) for it
+    fn buffer() -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for &float in &[0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+            bytes.extend_from_slice(&float.to_le_bytes());
+        }
+        bytes.extend_from_slice(&[2, 0, 0, 0]);
+        for &float in &[0.0f32, 2.0, 0.0] {
+            bytes.extend_from_slice(&float.to_le_bytes());
+        }
+        bytes
+    }
+
+    fn triangle(buffer: &str, accessor: &str, nodes: &str) -> String {
+        TRIANGLE
+            .replace("BUFFER", buffer)
+            .replace("ACCESSOR", accessor)
+            .replace("NODES", nodes)
+    }
+
+    fn data_uri(bytes: &[u8]) -> String {
+        format!(r#""uri": "data:application/octet-stream;base64,{}","#, encode_base64(bytes))
+    }
+
+    fn encode_base64(bytes: &[u8]) -> String {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut encoded = String::new();
+        for chunk in bytes.chunks(3) {
+            let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, &byte)| bits | u32::from(byte) << (16 - 8 * i));
+            for i in 0..4 {
+                if i <= chunk.len() {
+                    encoded.push(ALPHABET[(bits >> (18 - 6 * i) & 63) as usize] as char);
+                } else {
+                    encoded.push('=');
+                }
+            }
+        }
+        encoded
+    }
+
+    /// GLB container with the chunks padded as the spec asks
+    fn glb(chunks: &[(u32, &[u8])]) -> Vec<u8> {
+        let mut body = Vec::new();
+        for &(chunk_type, data) in chunks {
+            let padding = if chunk_type == GLB_CHUNK_JSON { b' ' } else { 0 };
+            let mut data = data.to_vec();
+            while !data.len().is_multiple_of(4) {
+                data.push(padding);
+            }
+            body.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            body.extend_from_slice(&chunk_type.to_le_bytes());
+            body.extend_from_slice(&data);
+        }
+        let mut contents = Vec::new();
+        contents.extend_from_slice(&GLB_MAGIC.to_le_bytes());
+        contents.extend_from_slice(&2u32.to_le_bytes());
+        contents.extend_from_slice(&(12 + body.len() as u32).to_le_bytes());
+        contents.extend_from_slice(&body);
+        contents
+    }
+
+    fn parse(json: &str, bin: Option<&[u8]>) -> Result<GltfScene, GltfError> {
+        parse_gltf(json, bin, &mut |uri| Err(GltfError::Io(format!("no file {}", uri))))
+    }
+
+    fn positions(scene: &GltfScene) -> Vec<Point3<f32>> {
+        scene.meshes[0].primitives[0].data.positions.clone()
+    }
+
+    fn assert_invalid(result: Result<GltfScene, GltfError>, expected_path: &str) {
+        match result {
+            Err(GltfError::Invalid { ref path, .. }) if path == expected_path => (),
+            other => panic!("expected {} to be invalid, got {:?}", expected_path, other),
+        }
+    }
+
+    #[test]
+    fn base64() {
+        assert_eq!(decode_base64("TWFu").unwrap(), b"Man");
+        assert_eq!(decode_base64("TWE=").unwrap(), b"Ma");
+        assert_eq!(decode_base64("TQ==").unwrap(), b"M");
+        assert_eq!(decode_base64("").unwrap(), b"");
+        assert!(decode_base64("TW!u").is_err());
+        let bytes: Vec<u8> = (0..=255).collect();
+        assert_eq!(decode_base64(&encode_base64(&bytes)).unwrap(), bytes);
+    }
+
+    #[test]
+    fn data_uris() {
+        let (mime_type, data) = decode_data_uri("data:image/png;base64,TWFu").unwrap();
+        assert_eq!(mime_type.as_deref(), Some("image/png"));
+        assert_eq!(data, b"Man");
+        assert_eq!(decode_data_uri("data:;base64,TQ==").unwrap(), (None, b"M".to_vec()));
+        assert!(decode_data_uri("data:text/plain,Man").is_err());
+        assert!(decode_data_uri("data:text/plain;base64").is_err());
+    }
+
+    #[test]
+    fn buffer_from_data_uri() {
+        let scene = parse(&triangle(&data_uri(&buffer()), POSITIONS, ONE_NODE), None).unwrap();
+        assert_eq!(
+            positions(&scene),
+            vec![Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0), Point3::new(0.0, 1.0, 0.0)]
+        );
+        assert_eq!(scene.meshes[0].primitives[0].data.indices, vec![0, 1, 2]);
+        assert_eq!(scene.roots, vec![0]);
+
+        let short = data_uri(&buffer()[..40]);
+        assert_invalid(parse(&triangle(&short, POSITIONS, ONE_NODE), None), "buffers[0]");
+    }
+
+    #[test]
+    fn glb_chunks() {
+        let json = triangle("", POSITIONS, ONE_NODE);
+        let bin = buffer();
+        // a chunk of an unknown type before the binary one is skipped
+        let contents = glb(&[(GLB_CHUNK_JSON, json.as_bytes()), (0x1234_5678, b"ext"), (GLB_CHUNK_BIN, &bin)]);
+        let (parsed_json, parsed_bin) = parse_glb(&contents).unwrap();
+        assert_eq!(parsed_json.trim_end(), json);
+        assert_eq!(parsed_bin, Some(&bin[..]));
+        let scene = parse(parsed_json, parsed_bin).unwrap();
+        assert_eq!(positions(&scene)[1], Point3::new(1.0, 0.0, 0.0));
+
+        // the buffer without a URI needs the binary chunk
+        let contents = glb(&[(GLB_CHUNK_JSON, json.as_bytes())]);
+        let (json_only, no_bin) = parse_glb(&contents).unwrap();
+        assert_eq!(no_bin, None);
+        assert_invalid(parse(json_only, no_bin), "buffers[0]");
+    }
+
+    #[test]
+    fn malformed_glb() {
+        let contents = glb(&[(GLB_CHUNK_JSON, b"{}")]);
+        let format_error = |contents: &[u8]| match parse_glb(contents) {
+            Err(GltfError::Format(_)) => (),
+            other => panic!("expected a format error, got {:?}", other),
+        };
+
+        format_error(&contents[..8]);
+        let mut magic = contents.clone();
+        magic[0] = b'x';
+        format_error(&magic);
+        let mut version = contents.clone();
+        version[4] = 1;
+        format_error(&version);
+        // the header claims more than there is
+        format_error(&contents[..contents.len() - 4]);
+        let mut chunk_length = contents.clone();
+        chunk_length[12] = 8;
+        format_error(&chunk_length);
+        format_error(&glb(&[(GLB_CHUNK_BIN, b"data")]));
+    }
+
+    #[test]
+    fn sparse_accessor() {
+        let sparse = r#""bufferView": 0, "sparse": {
+            "count": 1,
+            "indices": { "bufferView": 1, "componentType": 5121 },
+            "values": { "bufferView": 2 }
+        },"#;
+        let scene = parse(&triangle(&data_uri(&buffer()), sparse, ONE_NODE), None).unwrap();
+        assert_eq!(
+            positions(&scene),
+            vec![Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0), Point3::new(0.0, 2.0, 0.0)]
+        );
+
+        // without a buffer view the other elements are zeros
+        let zeros = sparse.replacen(r#""bufferView": 0, "#, "", 1);
+        let scene = parse(&triangle(&data_uri(&buffer()), &zeros, ONE_NODE), None).unwrap();
+        assert_eq!(
+            positions(&scene),
+            vec![Point3::new(0.0, 0.0, 0.0), Point3::new(0.0, 0.0, 0.0), Point3::new(0.0, 2.0, 0.0)]
+        );
+
+        let mut out_of_range = buffer();
+        out_of_range[36] = 3;
+        let result = parse(&triangle(&data_uri(&out_of_range), sparse, ONE_NODE), None);
+        assert_invalid(result, "accessors[0].sparse.indices");
+
+        let too_many = sparse.replace(r#""count": 1"#, r#""count": 2"#);
+        let result = parse(&triangle(&data_uri(&buffer()), &too_many, ONE_NODE), None);
+        assert_invalid(result, "accessors[0].sparse.values");
+
+        let signed = sparse.replace("5121", "5120");
+        let result = parse(&triangle(&data_uri(&buffer()), &signed, ONE_NODE), None);
+        assert_invalid(result, "accessors[0].sparse.indices");
+    }
+
+    #[test]
+    fn impossible_counts_and_offsets() {
+        let buffer = data_uri(&buffer());
+        // would take more memory than there is, or overflow computing where the last element is
+        for count in &["4294967296", "18446744073709551615"] {
+            let json = triangle(&buffer, POSITIONS, ONE_NODE)
+                .replace(r#""count": 3"#, &format!(r#""count": {}"#, count));
+            assert_invalid(parse(&json, None), "accessors[0]");
+            let without_view = json.replace(POSITIONS, "");
+            assert_invalid(parse(&without_view, None), "accessors[0]");
+        }
+
+        let json = triangle(&buffer, POSITIONS, ONE_NODE);
+        let view_offset = json.replace(
+            r#""byteOffset": 0, "byteLength": 36"#,
+            r#""byteOffset": 18446744073709551615, "byteLength": 36"#,
+        );
+        assert_invalid(parse(&view_offset, None), "bufferViews[0]");
+        let accessor_offset = json.replace(POSITIONS, r#""bufferView": 0, "byteOffset": 18446744073709551615,"#);
+        assert_invalid(parse(&accessor_offset, None), "accessors[0]");
+        let stride = json.replace(r#""byteLength": 36 }"#, r#""byteLength": 36, "byteStride": 9223372036854775807 }"#);
+        assert_invalid(parse(&stride, None), "accessors[0]");
+
+        let sparse = r#""bufferView": 0, "sparse": {
+            "count": 9223372036854775807,
+            "indices": { "bufferView": 1, "byteOffset": 1, "componentType": 5123 },
+            "values": { "bufferView": 2 }
+        },"#;
+        assert_invalid(parse(&triangle(&buffer, sparse, ONE_NODE), None), "accessors[0].sparse.indices");
+    }
+
+    #[test]
+    fn node_hierarchy() {
+        let nodes = r#"[ { "children": [1] }, { "mesh": 0, "translation": [1, 2, 3] } ]"#;
+        let scene = parse(&triangle(&data_uri(&buffer()), POSITIONS, nodes), None).unwrap();
+        // without scenes every node without a parent is a root
+        assert_eq!(scene.roots, vec![0]);
+        let instances = scene.instances();
+        assert_eq!(instances.len(), 1);
+        assert_eq!(instances[0].node, 1);
+        assert_eq!(instances[0].transform, Matrix4::from_translation(vec3(1.0, 2.0, 3.0)));
+    }
+
+    #[test]
+    fn node_cycles_are_rejected() {
+        let buffer = data_uri(&buffer());
+        let cycle = r#"[ { "mesh": 0, "children": [1] }, { "children": [2] }, { "children": [0] } ]"#;
+        assert_invalid(parse(&triangle(&buffer, POSITIONS, cycle), None), "nodes[0]");
+        let own_child = r#"[ { "mesh": 0, "children": [0] } ]"#;
+        assert_invalid(parse(&triangle(&buffer, POSITIONS, own_child), None), "nodes[0]");
+        let two_parents = r#"[ { "children": [2] }, { "children": [2] }, { "mesh": 0 } ]"#;
+        assert_invalid(parse(&triangle(&buffer, POSITIONS, two_parents), None), "nodes[2]");
+        let missing_child = r#"[ { "mesh": 0, "children": [5] } ]"#;
+        assert_invalid(parse(&triangle(&buffer, POSITIONS, missing_child), None), "nodes[0]");
+    }
+}
@@ -1,15 +1,34 @@
+use glutin::VirtualKeyCode;
+
 const MIN_PITCH: f32 = -89.0;
 const MAX_PITCH: f32 = 89.0;
 
+pub const MOUSE_SENSITIVITY: f32 = 0.1;
+
 pub struct Input {
     up: bool,
     down: bool,
     left: bool,
     right: bool,
     close: bool,
+    roll_left: bool,
+    roll_right: bool,
+    free_look: bool,
+    reset_view: bool,
+    mouse_delta: (f32, f32),
+    save_bookmark: Option<usize>,
+    recall_bookmark: Option<usize>,
+    paused: bool,
+    step: bool,
+    time_scale_change: i32,
     yaw: f32,
     pitch: f32,
-    fov: f32,
+}
+
+impl Default for Input {
+    fn default() -> Self {
+        Input::new()
+    }
 }
 
 impl Input {
@@ -20,9 +39,18 @@ impl Input {
             left: false,
             right: false,
             close: false,
+            roll_left: false,
+            roll_right: false,
+            free_look: false,
+            reset_view: false,
+            mouse_delta: (0.0, 0.0),
+            save_bookmark: None,
+            recall_bookmark: None,
+            paused: false,
+            step: false,
+            time_scale_change: 0,
             yaw: -90.0,
             pitch: 0.0,
-            fov: 45.0,
         }
     }
 
@@ -66,6 +94,100 @@ impl Input {
         self.close = pressed;
     }
 
+    pub fn roll_left(&self) -> bool {
+        self.roll_left
+    }
+
+    pub fn set_roll_left(&mut self, pressed: bool) {
+        self.roll_left = pressed;
+    }
+
+    pub fn roll_right(&self) -> bool {
+        self.roll_right
+    }
+
+    pub fn set_roll_right(&mut self, pressed: bool) {
+        self.roll_right = pressed;
+    }
+
+    /// free look rotates the camera around its own axes instead of using yaw/pitch
+    pub fn free_look(&self) -> bool {
+        self.free_look
+    }
+
+    pub fn set_free_look(&mut self, enabled: bool) {
+        self.free_look = enabled;
+    }
+
+    pub fn reset_view(&self) -> bool {
+        self.reset_view
+    }
+
+    pub fn set_reset_view(&mut self, pressed: bool) {
+        self.reset_view = pressed;
+    }
+
+    pub fn add_mouse_delta(&mut self, x: f32, y: f32) {
+        self.mouse_delta.0 += x;
+        self.mouse_delta.1 += y;
+    }
+
+    /// mouse movement accumulated since the last call
+    pub fn take_mouse_delta(&mut self) -> (f32, f32) {
+        let delta = self.mouse_delta;
+        self.mouse_delta = (0.0, 0.0);
+        delta
+    }
+
+    pub fn set_save_bookmark(&mut self, slot: usize) {
+        self.save_bookmark = Some(slot);
+    }
+
+    /// bookmark slot requested to be saved since the last call
+    pub fn take_save_bookmark(&mut self) -> Option<usize> {
+        self.save_bookmark.take()
+    }
+
+    pub fn set_recall_bookmark(&mut self, slot: usize) {
+        self.recall_bookmark = Some(slot);
+    }
+
+    /// bookmark slot requested to be recalled since the last call
+    pub fn take_recall_bookmark(&mut self) -> Option<usize> {
+        self.recall_bookmark.take()
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub fn set_step(&mut self) {
+        self.step = true;
+    }
+
+    /// whether a single simulation step was requested since the last call
+    pub fn take_step(&mut self) -> bool {
+        let step = self.step;
+        self.step = false;
+        step
+    }
+
+    /// request a faster (positive) or slower (negative) simulation
+    pub fn change_time_scale(&mut self, steps: i32) {
+        self.time_scale_change += steps;
+    }
+
+    /// time scale changes requested since the last call
+    pub fn take_time_scale_change(&mut self) -> i32 {
+        let change = self.time_scale_change;
+        self.time_scale_change = 0;
+        change
+    }
+
     pub fn yaw(&self) -> f32 {
         self.yaw
     }
@@ -88,12 +210,79 @@ impl Input {
         }
     }
 
-    pub fn fov(&self) -> f32 {
-        self.fov
+    /// apply a key press, `ctrl` turns number keys from recalling into saving bookmarks
+    pub fn press_key(&mut self, key: VirtualKeyCode, ctrl: bool) {
+        match key {
+            VirtualKeyCode::W => self.set_up(true),
+            VirtualKeyCode::S => self.set_down(true),
+            VirtualKeyCode::A => self.set_left(true),
+            VirtualKeyCode::D => self.set_right(true),
+            VirtualKeyCode::Q => self.set_roll_left(true),
+            VirtualKeyCode::E => self.set_roll_right(true),
+            VirtualKeyCode::F => {
+                let free_look = self.free_look();
+                self.set_free_look(!free_look);
+            }
+            VirtualKeyCode::Home => self.set_reset_view(true),
+            VirtualKeyCode::P => {
+                let paused = self.paused();
+                self.set_paused(!paused);
+            }
+            VirtualKeyCode::N => self.set_step(),
+            VirtualKeyCode::Equals | VirtualKeyCode::Add => self.change_time_scale(1),
+            VirtualKeyCode::Minus | VirtualKeyCode::Subtract => self.change_time_scale(-1),
+            VirtualKeyCode::Escape => self.set_close(true),
+            _ => {
+                if let Some(slot) = bookmark_slot(key) {
+                    if ctrl {
+                        self.set_save_bookmark(slot);
+                    } else {
+                        self.set_recall_bookmark(slot);
+                    }
+                }
+            }
+        }
     }
 
-    pub fn set_fov(&mut self, value: f32) {
-        self.fov = value;
+    pub fn release_key(&mut self, key: VirtualKeyCode) {
+        match key {
+            VirtualKeyCode::W => self.set_up(false),
+            VirtualKeyCode::S => self.set_down(false),
+            VirtualKeyCode::A => self.set_left(false),
+            VirtualKeyCode::D => self.set_right(false),
+            VirtualKeyCode::Q => self.set_roll_left(false),
+            VirtualKeyCode::E => self.set_roll_right(false),
+            _ => (),
+        }
     }
 
-}
\ No newline at end of file
+    /// apply raw mouse motion, queued for free look or turned into yaw/pitch otherwise
+    pub fn move_mouse(&mut self, x_delta: f32, y_delta: f32) {
+        if self.free_look() {
+            self.add_mouse_delta(x_delta, y_delta);
+        } else {
+            let current_yaw = self.yaw();
+            self.set_yaw(current_yaw + x_delta * MOUSE_SENSITIVITY);
+
+            let current_pitch = self.pitch();
+            self.set_pitch(current_pitch + (-y_delta) * MOUSE_SENSITIVITY);
+        }
+    }
+}
+
+/// bookmark slot of a number key
+pub fn bookmark_slot(key: VirtualKeyCode) -> Option<usize> {
+    match key {
+        VirtualKeyCode::Key0 => Some(0),
+        VirtualKeyCode::Key1 => Some(1),
+        VirtualKeyCode::Key2 => Some(2),
+        VirtualKeyCode::Key3 => Some(3),
+        VirtualKeyCode::Key4 => Some(4),
+        VirtualKeyCode::Key5 => Some(5),
+        VirtualKeyCode::Key6 => Some(6),
+        VirtualKeyCode::Key7 => Some(7),
+        VirtualKeyCode::Key8 => Some(8),
+        VirtualKeyCode::Key9 => Some(9),
+        _ => None,
+    }
+}
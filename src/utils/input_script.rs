@@ -0,0 +1,183 @@
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use glutin::VirtualKeyCode;
+
+use utils::input::Input;
+
+/// one scripted input event
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScriptedInput {
+    Press { key: VirtualKeyCode, ctrl: bool },
+    Release(VirtualKeyCode),
+    MouseMotion(f32, f32),
+    Close,
+}
+
+impl ScriptedInput {
+    pub fn apply(&self, input: &mut Input) {
+        match *self {
+            ScriptedInput::Press { key, ctrl } => input.press_key(key, ctrl),
+            ScriptedInput::Release(key) => input.release_key(key),
+            ScriptedInput::MouseMotion(x_delta, y_delta) => input.move_mouse(x_delta, y_delta),
+            ScriptedInput::Close => input.set_close(true),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum InputScriptError {
+    Io(String),
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for InputScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InputScriptError::Io(message) => write!(f, "{}", message),
+            InputScriptError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for InputScriptError {}
+
+/// Input events to feed into a headless `Simulation`, each at a given tick.
+///
+/// Script files hold one event per line, blank lines and lines starting with `#` are ignored:
+///
+/// ```text
+/// # tick  event
+/// 0       press W
+/// 30      release W
+/// 30      mouse 200.0 -50.0
+/// 60      press ctrl 1
+/// 90      close
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InputScript {
+    // sorted by tick, events of the same tick keep their order
+    events: Vec<(u64, ScriptedInput)>,
+}
+
+impl InputScript {
+    pub fn new(mut events: Vec<(u64, ScriptedInput)>) -> Self {
+        events.sort_by_key(|&(tick, _)| tick);
+        InputScript { events }
+    }
+
+    pub fn load(path: &Path) -> Result<InputScript, InputScriptError> {
+        let mut file = File::open(path)
+            .map_err(|e| InputScriptError::Io(format!("Failed to open {}: {}", path.display(), e)))?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)
+            .map_err(|e| InputScriptError::Io(format!("Failed to read {}: {}", path.display(), e)))?;
+        InputScript::parse(&contents)
+    }
+
+    pub fn parse(contents: &str) -> Result<InputScript, InputScriptError> {
+        let mut events = Vec::new();
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let event = parse_event(line).map_err(|message| InputScriptError::Parse {
+                line: index + 1,
+                message,
+            })?;
+            events.push(event);
+        }
+        Ok(InputScript::new(events))
+    }
+
+    pub fn events(&self) -> &[(u64, ScriptedInput)] {
+        &self.events
+    }
+
+    /// events to apply before running `tick`
+    pub fn events_at(&self, tick: u64) -> impl Iterator<Item = &ScriptedInput> {
+        self.events
+            .iter()
+            .filter(move |&&(event_tick, _)| event_tick == tick)
+            .map(|(_, event)| event)
+    }
+}
+
+fn parse_event(line: &str) -> Result<(u64, ScriptedInput), String> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    let tick = tokens[0]
+        .parse::<u64>()
+        .map_err(|_| format!("'{}' is not a tick number", tokens[0]))?;
+    let key = |index: usize| -> Result<VirtualKeyCode, String> {
+        let token = tokens
+            .get(index)
+            .ok_or_else(|| format!("expected a key at column {}", index + 1))?;
+        key_from_name(token).ok_or_else(|| format!("unknown key '{}'", token))
+    };
+    let number = |index: usize| -> Result<f32, String> {
+        let token = tokens
+            .get(index)
+            .ok_or_else(|| format!("expected a number at column {}", index + 1))?;
+        match token.parse::<f32>() {
+            Ok(value) if value.is_finite() => Ok(value),
+            _ => Err(format!("'{}' is not a number", token)),
+        }
+    };
+
+    let (event, expected_len) = match tokens.get(1) {
+        Some(&"press") if tokens.get(2) == Some(&"ctrl") => (ScriptedInput::Press { key: key(3)?, ctrl: true }, 4),
+        Some(&"press") => (ScriptedInput::Press { key: key(2)?, ctrl: false }, 3),
+        Some(&"release") => (ScriptedInput::Release(key(2)?), 3),
+        Some(&"mouse") => (ScriptedInput::MouseMotion(number(2)?, number(3)?), 4),
+        Some(&"close") => (ScriptedInput::Close, 2),
+        Some(other) => {
+            return Err(format!(
+                "unknown event '{}', expected 'press', 'release', 'mouse' or 'close'",
+                other
+            ))
+        }
+        None => return Err("missing event, expected 'press', 'release', 'mouse' or 'close'".to_string()),
+    };
+    if tokens.len() > expected_len {
+        return Err(format!("unexpected '{}' after event", tokens[expected_len]));
+    }
+    Ok((tick, event))
+}
+
+/// key with the given name, for the keys the application reacts to
+pub fn key_from_name(name: &str) -> Option<VirtualKeyCode> {
+    let key = match name.to_lowercase().as_str() {
+        "w" => VirtualKeyCode::W,
+        "a" => VirtualKeyCode::A,
+        "s" => VirtualKeyCode::S,
+        "d" => VirtualKeyCode::D,
+        "q" => VirtualKeyCode::Q,
+        "e" => VirtualKeyCode::E,
+        "f" => VirtualKeyCode::F,
+        "n" => VirtualKeyCode::N,
+        "p" => VirtualKeyCode::P,
+        "home" => VirtualKeyCode::Home,
+        "escape" => VirtualKeyCode::Escape,
+        "tab" => VirtualKeyCode::Tab,
+        "pageup" => VirtualKeyCode::PageUp,
+        "pagedown" => VirtualKeyCode::PageDown,
+        "equals" => VirtualKeyCode::Equals,
+        "minus" => VirtualKeyCode::Minus,
+        "0" => VirtualKeyCode::Key0,
+        "1" => VirtualKeyCode::Key1,
+        "2" => VirtualKeyCode::Key2,
+        "3" => VirtualKeyCode::Key3,
+        "4" => VirtualKeyCode::Key4,
+        "5" => VirtualKeyCode::Key5,
+        "6" => VirtualKeyCode::Key6,
+        "7" => VirtualKeyCode::Key7,
+        "8" => VirtualKeyCode::Key8,
+        "9" => VirtualKeyCode::Key9,
+        _ => return None,
+    };
+    Some(key)
+}
@@ -0,0 +1,140 @@
+use std::mem;
+use std::os::raw::c_void;
+use std::ptr;
+
+use gl;
+use gl::types::{GLsizei, GLsizeiptr, GLuint};
+
+use cgmath::{Matrix4, Vector4};
+
+use utils::mesh::Mesh;
+
+/// first of the four locations the model matrix takes, one per column, after the `Semantic` ones
+pub const INSTANCE_MODEL_LOCATION: GLuint = 5;
+pub const INSTANCE_COLOR_LOCATION: GLuint = 9;
+
+/// floats per instance without and with a color
+const MODEL_FLOATS: usize = 16;
+const COLOR_FLOATS: usize = 4;
+
+/// Per-instance model matrices, and optionally colors, for drawing a mesh many times in one call.
+///
+/// `attach` adds the buffer to a mesh's vertex array as attributes that advance once per instance:
+/// `layout (location = 5) in mat4 aModel` and, with colors, `layout (location = 9) in vec4 aColor`.
+/// The buffer is refilled every frame with `upload`.
+pub struct InstanceBuffer {
+    vbo: GLuint,
+    colors: bool,
+    /// instances uploaded last
+    count: usize,
+    /// instances the buffer has room for
+    capacity: usize,
+}
+
+impl InstanceBuffer {
+    pub unsafe fn new(colors: bool) -> InstanceBuffer {
+        let mut vbo = 0;
+        gl::GenBuffers(1, &mut vbo);
+        InstanceBuffer {
+            vbo,
+            colors,
+            count: 0,
+            capacity: 0,
+        }
+    }
+
+    pub fn has_colors(&self) -> bool {
+        self.colors
+    }
+
+    /// number of instances uploaded last
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    fn stride(&self) -> usize {
+        (MODEL_FLOATS + if self.colors { COLOR_FLOATS } else { 0 }) * mem::size_of::<f32>()
+    }
+
+    /// source the instance attributes of `mesh` from this buffer
+    pub unsafe fn attach(&self, mesh: &Mesh) {
+        mesh.bind();
+        gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+        let stride = self.stride() as GLsizei;
+        // a mat4 attribute is four vec4 attributes in a row
+        for column in 0..4 {
+            let location = INSTANCE_MODEL_LOCATION + column;
+            gl::VertexAttribPointer(
+                location,
+                4,
+                gl::FLOAT,
+                gl::FALSE,
+                stride,
+                (column as usize * 4 * mem::size_of::<f32>()) as *const c_void,
+            );
+            gl::EnableVertexAttribArray(location);
+            gl::VertexAttribDivisor(location, 1);
+        }
+        if self.colors {
+            gl::VertexAttribPointer(
+                INSTANCE_COLOR_LOCATION,
+                4,
+                gl::FLOAT,
+                gl::FALSE,
+                stride,
+                (MODEL_FLOATS * mem::size_of::<f32>()) as *const c_void,
+            );
+            gl::EnableVertexAttribArray(INSTANCE_COLOR_LOCATION);
+            gl::VertexAttribDivisor(INSTANCE_COLOR_LOCATION, 1);
+        }
+        gl::BindVertexArray(0);
+        gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+    }
+
+    /// Replace the instances with `models`, and `colors` if the buffer has them.
+    ///
+    /// Panics if `colors` doesn't have one color per model or is given to a buffer without colors.
+    pub unsafe fn upload(&mut self, models: &[Matrix4<f32>], colors: Option<&[Vector4<f32>]>) {
+        assert_eq!(
+            colors.is_some(),
+            self.colors,
+            "colors have to be given exactly when the instance buffer has them"
+        );
+        if let Some(colors) = colors {
+            assert_eq!(colors.len(), models.len(), "one color per instance");
+        }
+        let mut data: Vec<f32> = Vec::with_capacity(models.len() * self.stride() / mem::size_of::<f32>());
+        for (i, model) in models.iter().enumerate() {
+            let columns: &[[f32; 4]; 4] = model.as_ref();
+            for column in columns {
+                data.extend_from_slice(column);
+            }
+            if let Some(colors) = colors {
+                let color = colors[i];
+                data.extend_from_slice(&[color.x, color.y, color.z, color.w]);
+            }
+        }
+
+        gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+        let size = (data.len() * mem::size_of::<f32>()) as GLsizeiptr;
+        if models.len() > self.capacity {
+            gl::BufferData(gl::ARRAY_BUFFER, size, data.as_ptr() as *const c_void, gl::DYNAMIC_DRAW);
+            self.capacity = models.len();
+        } else {
+            // let the driver hand out fresh storage instead of waiting for last frame's draws
+            let capacity_size = (self.capacity * self.stride()) as GLsizeiptr;
+            gl::BufferData(gl::ARRAY_BUFFER, capacity_size, ptr::null(), gl::DYNAMIC_DRAW);
+            gl::BufferSubData(gl::ARRAY_BUFFER, 0, size, data.as_ptr() as *const c_void);
+        }
+        gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        self.count = models.len();
+    }
+
+    pub unsafe fn delete(self) {
+        gl::DeleteBuffers(1, &self.vbo);
+    }
+}
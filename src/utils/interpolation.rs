@@ -0,0 +1,57 @@
+/// state that can be blended between two updates
+pub trait Interpolate {
+    /// state `amount` of the way from `self` to `other`, `amount` is in [0, 1]
+    fn interpolate(&self, other: &Self, amount: f32) -> Self;
+}
+
+impl<T: Interpolate + Clone> Interpolate for Vec<T> {
+    /// interpolates element-wise, elements without a counterpart are taken from `other`
+    fn interpolate(&self, other: &Vec<T>, amount: f32) -> Vec<T> {
+        other
+            .iter()
+            .enumerate()
+            .map(|(i, current)| match self.get(i) {
+                Some(previous) => previous.interpolate(current, amount),
+                None => current.clone(),
+            }).collect()
+    }
+}
+
+/// The state before and after the last fixed update, so rendering can interpolate
+/// between them instead of showing whatever the last update left behind.
+#[derive(Default)]
+pub struct Snapshot<T> {
+    previous: T,
+    current: T,
+}
+
+impl<T: Interpolate + Clone> Snapshot<T> {
+    pub fn new(state: T) -> Self {
+        Snapshot {
+            previous: state.clone(),
+            current: state,
+        }
+    }
+
+    pub fn previous(&self) -> &T {
+        &self.previous
+    }
+
+    pub fn current(&self) -> &T {
+        &self.current
+    }
+
+    pub fn current_mut(&mut self) -> &mut T {
+        &mut self.current
+    }
+
+    /// call at the start of every update, the current state becomes the previous one
+    pub fn advance(&mut self) {
+        self.previous = self.current.clone();
+    }
+
+    /// the state `alpha` of the way from the previous to the current update
+    pub fn interpolated(&self, alpha: f32) -> T {
+        self.previous.interpolate(&self.current, alpha)
+    }
+}
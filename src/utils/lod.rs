@@ -0,0 +1,607 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use cgmath::prelude::*;
+use cgmath::{Point3, Vector3};
+
+use utils::mesh::MeshError;
+use utils::mesh_data::MeshData;
+use utils::mesh_optimize::{optimize_vertex_cache, optimize_vertex_fetch};
+
+/// triangles of each level of detail after the full one, relative to the full one
+pub const LOD_RATIOS: [f32; 3] = [0.5, 0.25, 0.125];
+
+/// projected size, as a fraction of the screen height, up to which full detail is drawn
+pub const FULL_DETAIL_SIZE: f32 = 0.5;
+
+// borders weigh this much more than the faces around them, so collapses keep their outline
+const BORDER_WEIGHT: f64 = 10.0;
+
+/// Simplify a mesh to about `target_triangles` by collapsing edges with the least quadric error.
+///
+/// Vertices only ever move onto a neighbour, so every vertex keeps its own attributes. Vertices
+/// on a border move along it. Vertices split in two by a UV seam or any other split move along
+/// the seam, both halves together onto the two halves of the same neighbour, so the seam stays
+/// closed. Other splits, corners and vertices with non manifold edges stay where they are.
+/// Collapses that would fold triangles over or join two surfaces are skipped, so the target
+/// isn't reached if nothing else can go.
+pub fn simplify(data: &MeshData, target_triangles: usize) -> Result<MeshData, MeshError> {
+    data.validate()?;
+    let mut simplifier = Simplifier::new(data);
+    simplifier.run(target_triangles);
+
+    let mut result = data.clone();
+    result.indices = simplifier
+        .triangles
+        .iter()
+        .zip(&simplifier.alive)
+        .filter(|&(_, &alive)| alive)
+        .flat_map(|(triangle, _)| triangle.iter().cloned())
+        .collect();
+    optimize_vertex_cache(&mut result);
+    optimize_vertex_fetch(&mut result);
+    Ok(result)
+}
+
+/// The mesh and the simplifications of it at `ratios` of its triangles, each simplified from
+/// the one before. Stops early once simplifying doesn't remove any more triangles.
+pub fn lod_chain(data: &MeshData, ratios: &[f32]) -> Result<Vec<MeshData>, MeshError> {
+    let mut chain = vec![data.clone()];
+    for &ratio in ratios {
+        let target = (data.triangle_count() as f32 * ratio).round() as usize;
+        let lod = simplify(chain.last().unwrap(), target)?;
+        if lod.triangle_count() >= chain.last().unwrap().triangle_count() {
+            break;
+        }
+        chain.push(lod);
+    }
+    Ok(chain)
+}
+
+/// Level of detail to draw at a projected size, for a chain with `triangle_ratios` of the
+/// triangles of its first mesh.
+///
+/// The triangles on screen should stay about as dense as the full mesh at `FULL_DETAIL_SIZE`,
+/// so a level is good enough while the size is below that scaled by the square root of its ratio.
+pub fn select_lod(projected_size: f32, triangle_ratios: &[f32]) -> usize {
+    triangle_ratios
+        .iter()
+        .rposition(|&ratio| projected_size <= FULL_DETAIL_SIZE * ratio.sqrt())
+        .unwrap_or(0)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum VertexKind {
+    Interior,
+    /// on exactly one open edge loop, can only move along it
+    Border,
+    /// one of two vertices at a position split by a seam through it, both move along the seam
+    Seam,
+    /// on any other split, a non manifold edge or a corner of borders
+    Locked,
+}
+
+/// Symmetric 4x4 matrix summing squared distances to planes: a², ab, ac, ad, b², bc, bd, c², cd, d².
+#[derive(Debug, Clone, Copy, Default)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    fn plane(normal: Vector3<f64>, distance: f64, weight: f64) -> Quadric {
+        let (a, b, c, d) = (normal.x, normal.y, normal.z, distance);
+        Quadric([
+            a * a * weight,
+            a * b * weight,
+            a * c * weight,
+            a * d * weight,
+            b * b * weight,
+            b * c * weight,
+            b * d * weight,
+            c * c * weight,
+            c * d * weight,
+            d * d * weight,
+        ])
+    }
+
+    fn add(&mut self, other: &Quadric) {
+        for (value, other) in self.0.iter_mut().zip(other.0.iter()) {
+            *value += other;
+        }
+    }
+
+    fn error(&self, point: Point3<f32>) -> f64 {
+        let q = &self.0;
+        let (x, y, z) = (f64::from(point.x), f64::from(point.y), f64::from(point.z));
+        let error = q[0] * x * x + 2.0 * q[1] * x * y + 2.0 * q[2] * x * z + 2.0 * q[3] * x
+            + q[4] * y * y + 2.0 * q[5] * y * z + 2.0 * q[6] * y
+            + q[7] * z * z + 2.0 * q[8] * z
+            + q[9];
+        error.max(0.0)
+    }
+}
+
+/// moving vertex `from` onto `to` for `cost`, stale once either vertex changed since
+#[derive(Debug, Clone, Copy)]
+struct Collapse {
+    cost: f64,
+    from: u32,
+    to: u32,
+    versions: (u32, u32),
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Collapse) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Collapse {}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Collapse) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Collapse {
+    /// cheapest first out of a `BinaryHeap`
+    fn cmp(&self, other: &Collapse) -> Ordering {
+        other.cost.partial_cmp(&self.cost).unwrap_or(Ordering::Equal)
+    }
+}
+
+struct Simplifier<'a> {
+    positions: &'a [Point3<f32>],
+    triangles: Vec<[u32; 3]>,
+    alive: Vec<bool>,
+    alive_count: usize,
+    /// alive triangles around each vertex
+    vertex_triangles: Vec<Vec<usize>>,
+    kinds: Vec<VertexKind>,
+    /// quadric of each vertex, vertices at the same position share theirs
+    quadric_of: Vec<usize>,
+    /// the vertices at each position
+    wedges: Vec<Vec<u32>>,
+    quadrics: Vec<Quadric>,
+    versions: Vec<u32>,
+    removed: Vec<bool>,
+    heap: BinaryHeap<Collapse>,
+}
+
+impl<'a> Simplifier<'a> {
+    fn new(data: &'a MeshData) -> Simplifier<'a> {
+        let vertex_count = data.vertex_count();
+        let triangles: Vec<[u32; 3]> = data
+            .indices
+            .chunks(3)
+            .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+            .collect();
+        // degenerate triangles only get in the way
+        let alive: Vec<bool> = triangles
+            .iter()
+            .map(|t| t[0] != t[1] && t[1] != t[2] && t[2] != t[0])
+            .collect();
+        let mut vertex_triangles: Vec<Vec<usize>> = vec![Vec::new(); vertex_count];
+        for (index, triangle) in triangles.iter().enumerate().filter(|&(index, _)| alive[index]) {
+            for &vertex in triangle {
+                vertex_triangles[vertex as usize].push(index);
+            }
+        }
+
+        // vertices split by their attributes share a position
+        let mut position_ids: HashMap<[u32; 3], usize> = HashMap::new();
+        let quadric_of: Vec<usize> = data
+            .positions
+            .iter()
+            .map(|p| {
+                let key = [(p.x + 0.0).to_bits(), (p.y + 0.0).to_bits(), (p.z + 0.0).to_bits()];
+                let next = position_ids.len();
+                *position_ids.entry(key).or_insert(next)
+            }).collect();
+        let mut wedges = vec![Vec::new(); position_ids.len()];
+        for (vertex, &position) in quadric_of.iter().enumerate() {
+            wedges[position].push(vertex as u32);
+        }
+        // triangles on each edge between positions, to find borders across seams,
+        // and between vertices, an edge with one there and two between positions is on a seam
+        let mut edge_triangles: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut vertex_edge_triangles: HashMap<(u32, u32), usize> = HashMap::new();
+        for (triangle, _) in triangles.iter().zip(&alive).filter(|&(_, &alive)| alive) {
+            for k in 0..3 {
+                let (a, b) = (triangle[k], triangle[(k + 1) % 3]);
+                *edge_triangles.entry(position_edge(&quadric_of, a, b)).or_insert(0) += 1;
+                *vertex_edge_triangles.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+            }
+        }
+
+        let kinds = (0..vertex_count)
+            .map(|vertex| {
+                if vertex_triangles[vertex].is_empty() {
+                    return VertexKind::Locked;
+                }
+                let (mut borders, mut seams) = (0, 0);
+                for neighbour in neighbours(&triangles, &vertex_triangles[vertex], vertex as u32) {
+                    match edge_triangles[&position_edge(&quadric_of, vertex as u32, neighbour)] {
+                        1 => borders += 1,
+                        2 => {
+                            let edge = (neighbour.min(vertex as u32), neighbour.max(vertex as u32));
+                            if vertex_edge_triangles[&edge] == 1 {
+                                seams += 1;
+                            }
+                        }
+                        _ => return VertexKind::Locked,
+                    }
+                }
+                match (wedges[quadric_of[vertex]].len(), borders, seams) {
+                    (1, 0, 0) => VertexKind::Interior,
+                    (1, 2, 0) => VertexKind::Border,
+                    (2, 0, 2) => VertexKind::Seam,
+                    _ => VertexKind::Locked,
+                }
+            }).collect();
+
+        let mut quadrics = vec![Quadric::default(); position_ids.len()];
+        for (triangle, _) in triangles.iter().zip(&alive).filter(|&(_, &alive)| alive) {
+            let corners: Vec<Vector3<f64>> = triangle
+                .iter()
+                .map(|&vertex| data.positions[vertex as usize].to_vec().cast::<f64>().unwrap())
+                .collect();
+            let normal = (corners[1] - corners[0]).cross(corners[2] - corners[0]);
+            let double_area = normal.magnitude();
+            if double_area == 0.0 {
+                continue;
+            }
+            let normal = normal / double_area;
+            let face = Quadric::plane(normal, -normal.dot(corners[0]), double_area * 0.5);
+            for k in 0..3 {
+                quadrics[quadric_of[triangle[k] as usize]].add(&face);
+
+                // a plane through the border edge, perpendicular to the face, keeps the outline in place
+                let (a, b) = (triangle[k], triangle[(k + 1) % 3]);
+                if edge_triangles[&position_edge(&quadric_of, a, b)] == 1 {
+                    let edge = corners[(k + 1) % 3] - corners[k];
+                    let border_normal = edge.cross(normal);
+                    if border_normal.magnitude2() > 0.0 {
+                        let border_normal = border_normal.normalize();
+                        let border = Quadric::plane(
+                            border_normal,
+                            -border_normal.dot(corners[k]),
+                            BORDER_WEIGHT * edge.magnitude2(),
+                        );
+                        quadrics[quadric_of[a as usize]].add(&border);
+                        quadrics[quadric_of[b as usize]].add(&border);
+                    }
+                }
+            }
+        }
+
+        let alive_count = alive.iter().filter(|&&alive| alive).count();
+        Simplifier {
+            positions: &data.positions,
+            triangles,
+            alive,
+            alive_count,
+            vertex_triangles,
+            kinds,
+            quadric_of,
+            wedges,
+            quadrics,
+            versions: vec![0; vertex_count],
+            removed: vec![false; vertex_count],
+            heap: BinaryHeap::new(),
+        }
+    }
+
+    fn run(&mut self, target_triangles: usize) {
+        for vertex in 0..self.positions.len() as u32 {
+            self.push_collapses(vertex);
+        }
+        while self.alive_count > target_triangles {
+            let collapse = match self.heap.pop() {
+                Some(collapse) => collapse,
+                None => break,
+            };
+            let (from, to) = (collapse.from as usize, collapse.to as usize);
+            if self.removed[from] || self.removed[to] || collapse.versions != (self.versions[from], self.versions[to]) {
+                continue;
+            }
+            if let Some(twin) = self.collapse_twin(collapse.from, collapse.to) {
+                self.collapse(collapse.from, collapse.to, twin);
+            }
+        }
+    }
+
+    fn neighbours(&self, vertex: u32) -> Vec<u32> {
+        neighbours(&self.triangles, &self.vertex_triangles[vertex as usize], vertex)
+    }
+
+    /// alive triangles with both vertices
+    fn shared_triangles(&self, a: u32, b: u32) -> usize {
+        self.vertex_triangles[a as usize]
+            .iter()
+            .filter(|&&triangle| self.triangles[triangle].contains(&b))
+            .count()
+    }
+
+    fn push_collapses(&mut self, from: u32) {
+        if self.kinds[from as usize] == VertexKind::Locked {
+            return;
+        }
+        for to in self.neighbours(from) {
+            let mut quadric = self.quadrics[self.quadric_of[from as usize]];
+            quadric.add(&self.quadrics[self.quadric_of[to as usize]]);
+            self.heap.push(Collapse {
+                cost: quadric.error(self.positions[to as usize]),
+                from,
+                to,
+                versions: (self.versions[from as usize], self.versions[to as usize]),
+            });
+        }
+    }
+
+    /// the other vertex at the position of a seam vertex
+    fn twin(&self, vertex: u32) -> u32 {
+        let wedges = &self.wedges[self.quadric_of[vertex as usize]];
+        if wedges[0] == vertex {
+            wedges[1]
+        } else {
+            wedges[0]
+        }
+    }
+
+    /// Whether `from` can move onto `to`, with the collapse of the other side of a seam that has
+    /// to go with it. `None` if it can't, `Some(None)` without a seam.
+    fn collapse_twin(&self, from: u32, to: u32) -> Option<Option<(u32, u32)>> {
+        let shared = self.shared_triangles(from, to);
+        match self.kinds[from as usize] {
+            VertexKind::Locked => None,
+            VertexKind::Border if self.kinds[to as usize] != VertexKind::Border || shared != 1 => None,
+            // only along the seam, where the vertices on the other side have an edge too
+            VertexKind::Seam => {
+                if self.kinds[to as usize] != VertexKind::Seam || shared != 1 {
+                    return None;
+                }
+                let twin = (self.twin(from), self.twin(to));
+                let along_seam = self.shared_triangles(twin.0, twin.1) == 1;
+                if along_seam && self.can_collapse(from, to) && self.can_collapse(twin.0, twin.1) {
+                    Some(Some(twin))
+                } else {
+                    None
+                }
+            }
+            _ if self.can_collapse(from, to) => Some(None),
+            _ => None,
+        }
+    }
+
+    /// whether moving `from` onto `to` keeps the surface manifold and facing the same way
+    fn can_collapse(&self, from: u32, to: u32) -> bool {
+        let shared = self.shared_triangles(from, to);
+        // the vertices may only have the neighbours of their shared triangles in common,
+        // others would end up with an edge used by more than two triangles
+        let to_neighbours = self.neighbours(to);
+        let common = self
+            .neighbours(from)
+            .iter()
+            .filter(|neighbour| to_neighbours.contains(neighbour))
+            .count();
+        if shared == 0 || common != shared {
+            return false;
+        }
+        // no triangle may turn over
+        let target = self.positions[to as usize];
+        self.vertex_triangles[from as usize].iter().all(|&triangle| {
+            let corners = self.triangles[triangle];
+            if corners.contains(&to) {
+                return true;
+            }
+            let point = |vertex: u32| self.positions[vertex as usize];
+            let moved = |vertex: u32| if vertex == from { target } else { point(vertex) };
+            let before = (point(corners[1]) - point(corners[0])).cross(point(corners[2]) - point(corners[0]));
+            let after = (moved(corners[1]) - moved(corners[0])).cross(moved(corners[2]) - moved(corners[0]));
+            before.dot(after) > 0.0
+        })
+    }
+
+    fn collapse(&mut self, from: u32, to: u32, twin: Option<(u32, u32)>) {
+        self.move_vertex(from, to);
+        if let Some((twin_from, twin_to)) = twin {
+            self.move_vertex(twin_from, twin_to);
+        }
+        let quadric = self.quadrics[self.quadric_of[from as usize]];
+        self.quadrics[self.quadric_of[to as usize]].add(&quadric);
+
+        // every collapse onto the position or away from it costs something else now
+        for to in self.wedges[self.quadric_of[to as usize]].clone() {
+            self.versions[to as usize] += 1;
+            self.push_collapses(to);
+            for neighbour in self.neighbours(to) {
+                self.push_collapses_onto(neighbour, to);
+            }
+        }
+    }
+
+    /// move the triangles of `from` onto `to`, dropping the ones they share
+    fn move_vertex(&mut self, from: u32, to: u32) {
+        for triangle in std::mem::take(&mut self.vertex_triangles[from as usize]) {
+            if self.triangles[triangle].contains(&to) {
+                self.alive[triangle] = false;
+                self.alive_count -= 1;
+                for &vertex in &self.triangles[triangle] {
+                    self.vertex_triangles[vertex as usize].retain(|&other| other != triangle);
+                }
+            } else {
+                for vertex in self.triangles[triangle].iter_mut() {
+                    if *vertex == from {
+                        *vertex = to;
+                    }
+                }
+                self.vertex_triangles[to as usize].push(triangle);
+            }
+        }
+        self.removed[from as usize] = true;
+    }
+
+    fn push_collapses_onto(&mut self, from: u32, to: u32) {
+        if self.kinds[from as usize] == VertexKind::Locked {
+            return;
+        }
+        let mut quadric = self.quadrics[self.quadric_of[from as usize]];
+        quadric.add(&self.quadrics[self.quadric_of[to as usize]]);
+        self.heap.push(Collapse {
+            cost: quadric.error(self.positions[to as usize]),
+            from,
+            to,
+            versions: (self.versions[from as usize], self.versions[to as usize]),
+        });
+    }
+}
+
+/// the other vertices of `triangles` around `vertex`, each once
+fn neighbours(triangles: &[[u32; 3]], around: &[usize], vertex: u32) -> Vec<u32> {
+    let mut neighbours = Vec::new();
+    for &triangle in around {
+        for &other in &triangles[triangle] {
+            if other != vertex && !neighbours.contains(&other) {
+                neighbours.push(other);
+            }
+        }
+    }
+    neighbours
+}
+
+/// an edge between the positions of two vertices, in either direction
+fn position_edge(position_of: &[usize], a: u32, b: u32) -> (usize, usize) {
+    let (a, b) = (position_of[a as usize], position_of[b as usize]);
+    (a.min(b), a.max(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::vec2;
+    use utils::shapes;
+
+    /// grid of 16 by 16 cells with hills, so collapses have different costs
+    fn hills() -> MeshData {
+        let mut data = shapes::plane(4.0, 4.0, 16, 16);
+        for p in data.positions.iter_mut() {
+            p.y = 0.2 * (p.x * 1.3).sin() * (p.z * 0.9).cos();
+        }
+        data
+    }
+
+    /// flat grid of 16 by 16 cells with the vertices down its middle split by a UV seam
+    fn seamed_plane() -> MeshData {
+        let mut data = shapes::plane(4.0, 4.0, 16, 16);
+        let middle: Vec<u32> = (0..data.vertex_count() as u32)
+            .filter(|&vertex| data.positions[vertex as usize].x == 0.0)
+            .collect();
+        let mut copies = HashMap::new();
+        for &vertex in &middle {
+            let copy = data.duplicate_vertex(vertex as usize);
+            data.tex_coords[copy as usize] += vec2(1.0, 0.0);
+            copies.insert(vertex, copy);
+        }
+        let positions = data.positions.clone();
+        for triangle in data.indices.chunks_mut(3) {
+            let right = triangle.iter().any(|&vertex| positions[vertex as usize].x > 0.0);
+            for vertex in triangle.iter_mut().filter(|_| right) {
+                if let Some(&copy) = copies.get(vertex) {
+                    *vertex = copy;
+                }
+            }
+        }
+        data
+    }
+
+    fn area(data: &MeshData) -> f32 {
+        data.indices
+            .chunks(3)
+            .map(|t| {
+                let p = |k: usize| data.positions[t[k] as usize];
+                (p(1) - p(0)).cross(p(2) - p(0)).magnitude() / 2.0
+            }).sum()
+    }
+
+    /// edges between positions with only one triangle
+    fn open_edges(data: &MeshData) -> Vec<(Point3<f32>, Point3<f32>)> {
+        let key = |p: Point3<f32>| [p.x.to_bits(), p.y.to_bits(), p.z.to_bits()];
+        let mut edges: HashMap<_, (usize, Point3<f32>, Point3<f32>)> = HashMap::new();
+        for t in data.indices.chunks(3) {
+            for k in 0..3 {
+                let (a, b) = (data.positions[t[k] as usize], data.positions[t[(k + 1) % 3] as usize]);
+                let edge = if key(a) < key(b) { (key(a), key(b)) } else { (key(b), key(a)) };
+                edges.entry(edge).or_insert((0, a, b)).0 += 1;
+            }
+        }
+        edges.values().filter(|edge| edge.0 == 1).map(|&(_, a, b)| (a, b)).collect()
+    }
+
+    #[test]
+    fn simplify_reaches_the_target() {
+        let data = hills();
+        assert_eq!(data.triangle_count(), 512);
+        for &target in &[256, 128, 64, 32] {
+            let lod = simplify(&data, target).unwrap();
+            assert!(lod.validate().is_ok());
+            let count = lod.triangle_count();
+            assert!(count <= target && count + 2 >= target, "{} for {}", count, target);
+        }
+    }
+
+    #[test]
+    fn simplify_keeps_the_border() {
+        let data = shapes::plane(4.0, 4.0, 16, 16);
+        let lod = simplify(&data, 16).unwrap();
+        assert!(lod.triangle_count() <= 16);
+        assert!((area(&lod) - 16.0).abs() < 1e-4);
+        for (a, b) in open_edges(&lod) {
+            let on_border = |p: Point3<f32>| p.x.abs() == 2.0 || p.z.abs() == 2.0;
+            assert!(on_border(a) && on_border(b) && (a.x == b.x || a.z == b.z), "{:?} {:?}", a, b);
+        }
+        for &corner in &[Point3::new(-2.0, 0.0, -2.0), Point3::new(2.0, 0.0, -2.0), Point3::new(2.0, 0.0, 2.0)] {
+            assert!(lod.indices.iter().any(|&vertex| lod.positions[vertex as usize] == corner));
+        }
+    }
+
+    #[test]
+    fn simplify_moves_along_seams() {
+        let data = seamed_plane();
+        assert_eq!(data.vertex_count(), 17 * 18);
+        let lod = simplify(&data, 16).unwrap();
+        assert!(lod.triangle_count() <= 16, "{} triangles", lod.triangle_count());
+        // the seam stays closed and both sides keep their own texture coords
+        assert!((area(&lod) - 16.0).abs() < 1e-4);
+        for (a, b) in open_edges(&lod) {
+            assert!(a.x.abs() == 2.0 || a.z.abs() == 2.0, "{:?} {:?}", a, b);
+        }
+        for t in lod.indices.chunks(3) {
+            let right = t.iter().any(|&vertex| lod.positions[vertex as usize].x > 0.0);
+            for &vertex in t.iter().filter(|&&vertex| lod.positions[vertex as usize].x == 0.0) {
+                assert_eq!(lod.tex_coords[vertex as usize].x > 1.0, right);
+            }
+        }
+    }
+
+    #[test]
+    fn lod_chain_halves() {
+        let chain = lod_chain(&hills(), &LOD_RATIOS).unwrap();
+        let counts: Vec<usize> = chain.iter().map(|lod| lod.triangle_count()).collect();
+        assert_eq!(counts, vec![512, 256, 128, 64]);
+    }
+
+    #[test]
+    fn select_lod_is_monotonic() {
+        let ratios = [1.0, 0.5, 0.25, 0.125];
+        assert_eq!(select_lod(10.0, &ratios), 0);
+        assert_eq!(select_lod(FULL_DETAIL_SIZE, &ratios), 0);
+        assert_eq!(select_lod(0.01, &ratios), 3);
+        let mut previous = ratios.len() - 1;
+        for step in 0..=200 {
+            let lod = select_lod(step as f32 * 0.005, &ratios);
+            assert!(lod <= previous, "{} after {} at {}", lod, previous, step);
+            previous = lod;
+        }
+        assert_eq!(previous, 0);
+    }
+}
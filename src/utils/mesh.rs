@@ -0,0 +1,516 @@
+use std::error::Error;
+use std::fmt;
+use std::mem;
+use std::os::raw::c_void;
+use std::ptr;
+use std::slice;
+
+use cgmath::Point3;
+use gl;
+use gl::types::{GLenum, GLint, GLsizei, GLsizeiptr, GLuint};
+
+use utils::bounds::{Aabb, BoundingSphere};
+
+#[derive(Debug)]
+pub enum MeshError {
+    EmptyLayout,
+    InvalidComponents { location: GLuint, components: usize },
+    DuplicateLocation(GLuint),
+    /// the vertex data doesn't hold a whole number of vertices
+    DataLength { bytes: usize, stride: usize },
+    IndexOutOfRange { index: u32, vertex_count: usize },
+    /// the vertex or index count doesn't fit the primitive
+    PrimitiveCount { count: usize, primitive: Primitive },
+    /// a vertex attribute stream of `MeshData` doesn't have one value per vertex
+    StreamLength { stream: &'static str, len: usize, vertex_count: usize },
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MeshError::EmptyLayout => write!(f, "a vertex layout needs at least one attribute"),
+            MeshError::InvalidComponents { location, components } => write!(
+                f,
+                "attribute {} has {} components, expected 1 to 4",
+                location, components
+            ),
+            MeshError::DuplicateLocation(location) => write!(f, "attribute location {} is used twice", location),
+            MeshError::DataLength { bytes, stride } => write!(
+                f,
+                "{} bytes of vertex data is not a whole number of {} byte vertices",
+                bytes, stride
+            ),
+            MeshError::IndexOutOfRange { index, vertex_count } => {
+                write!(f, "index {} is out of range for {} vertices", index, vertex_count)
+            }
+            MeshError::PrimitiveCount { count, primitive } => {
+                write!(f, "{} vertices can't be drawn as {:?}", count, primitive)
+            }
+            MeshError::StreamLength {
+                stream,
+                len,
+                vertex_count,
+            } => write!(f, "{} {} for {} vertices", len, stream, vertex_count),
+        }
+    }
+}
+
+impl Error for MeshError {}
+
+/// type of each component of a vertex attribute
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComponentType {
+    Byte,
+    UnsignedByte,
+    Short,
+    UnsignedShort,
+    Int,
+    UnsignedInt,
+    Float,
+}
+
+impl ComponentType {
+    /// size in bytes
+    pub fn size(self) -> usize {
+        match self {
+            ComponentType::Byte | ComponentType::UnsignedByte => 1,
+            ComponentType::Short | ComponentType::UnsignedShort => 2,
+            ComponentType::Int | ComponentType::UnsignedInt | ComponentType::Float => 4,
+        }
+    }
+
+    pub fn gl_type(self) -> GLenum {
+        match self {
+            ComponentType::Byte => gl::BYTE,
+            ComponentType::UnsignedByte => gl::UNSIGNED_BYTE,
+            ComponentType::Short => gl::SHORT,
+            ComponentType::UnsignedShort => gl::UNSIGNED_SHORT,
+            ComponentType::Int => gl::INT,
+            ComponentType::UnsignedInt => gl::UNSIGNED_INT,
+            ComponentType::Float => gl::FLOAT,
+        }
+    }
+}
+
+/// one input of the vertex shader, e.g. `layout (location = 1) in vec2 aTexCoord`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VertexAttribute {
+    pub location: GLuint,
+    /// 1 to 4
+    pub components: usize,
+    pub component_type: ComponentType,
+    /// integer components are mapped to [0, 1] (or [-1, 1] if signed) instead of converted as is
+    pub normalized: bool,
+}
+
+impl VertexAttribute {
+    /// `components` floats
+    pub fn float(location: GLuint, components: usize) -> Self {
+        VertexAttribute {
+            location,
+            components,
+            component_type: ComponentType::Float,
+            normalized: false,
+        }
+    }
+
+    /// integer components the shader sees as floats in [0, 1], e.g. 8 bit colors
+    pub fn normalized(location: GLuint, components: usize, component_type: ComponentType) -> Self {
+        VertexAttribute {
+            location,
+            components,
+            component_type,
+            normalized: true,
+        }
+    }
+
+    /// size in bytes
+    pub fn size(&self) -> usize {
+        self.components * self.component_type.size()
+    }
+}
+
+/// Interleaved vertex attributes, packed one after another in the order given.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VertexLayout {
+    attributes: Vec<VertexAttribute>,
+    offsets: Vec<usize>,
+    stride: usize,
+}
+
+impl VertexLayout {
+    pub fn new(attributes: Vec<VertexAttribute>) -> Result<VertexLayout, MeshError> {
+        if attributes.is_empty() {
+            return Err(MeshError::EmptyLayout);
+        }
+        let mut offsets = Vec::with_capacity(attributes.len());
+        let mut stride = 0;
+        for (i, attribute) in attributes.iter().enumerate() {
+            if attribute.components < 1 || attribute.components > 4 {
+                return Err(MeshError::InvalidComponents {
+                    location: attribute.location,
+                    components: attribute.components,
+                });
+            }
+            if attributes[..i].iter().any(|other| other.location == attribute.location) {
+                return Err(MeshError::DuplicateLocation(attribute.location));
+            }
+            offsets.push(stride);
+            stride += attribute.size();
+        }
+        Ok(VertexLayout {
+            attributes,
+            offsets,
+            stride,
+        })
+    }
+
+    pub fn attributes(&self) -> &[VertexAttribute] {
+        &self.attributes
+    }
+
+    /// byte offset of the attribute at `location` within a vertex
+    pub fn offset(&self, location: GLuint) -> Option<usize> {
+        self.attributes
+            .iter()
+            .position(|attribute| attribute.location == location)
+            .map(|i| self.offsets[i])
+    }
+
+    /// size of a vertex in bytes
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// positions in `bytes` of vertex data, read from the 3 float attribute at location 0,
+    /// `None` if there's no such attribute
+    pub fn positions(&self, bytes: &[u8]) -> Option<Vec<Point3<f32>>> {
+        let i = self.attributes.iter().position(|attribute| attribute.location == 0)?;
+        let attribute = self.attributes[i];
+        if attribute.component_type != ComponentType::Float || attribute.components != 3 {
+            return None;
+        }
+        let offset = self.offsets[i];
+        let positions = bytes
+            .chunks_exact(self.stride)
+            .map(|vertex| {
+                let float = |at: usize| {
+                    let mut float = [0; 4];
+                    float.copy_from_slice(&vertex[offset + at..offset + at + 4]);
+                    f32::from_ne_bytes(float)
+                };
+                Point3::new(float(0), float(4), float(8))
+            })
+            .collect();
+        Some(positions)
+    }
+
+    /// number of vertices in `bytes` of vertex data
+    pub fn vertex_count(&self, bytes: usize) -> Result<usize, MeshError> {
+        if !bytes.is_multiple_of(self.stride) {
+            return Err(MeshError::DataLength {
+                bytes,
+                stride: self.stride,
+            });
+        }
+        Ok(bytes / self.stride)
+    }
+
+    /// point the attributes of the bound vertex array at the bound array buffer
+    pub unsafe fn apply(&self) {
+        for (attribute, offset) in self.attributes.iter().zip(self.offsets.iter()) {
+            gl::VertexAttribPointer(
+                attribute.location,
+                attribute.components as GLint,
+                attribute.component_type.gl_type(),
+                if attribute.normalized { gl::TRUE } else { gl::FALSE },
+                self.stride as GLsizei,
+                *offset as *const c_void,
+            );
+            gl::EnableVertexAttribArray(attribute.location);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Primitive {
+    Points,
+    Lines,
+    LineStrip,
+    LineLoop,
+    Triangles,
+    TriangleStrip,
+    TriangleFan,
+}
+
+impl Primitive {
+    pub fn gl_mode(self) -> GLenum {
+        match self {
+            Primitive::Points => gl::POINTS,
+            Primitive::Lines => gl::LINES,
+            Primitive::LineStrip => gl::LINE_STRIP,
+            Primitive::LineLoop => gl::LINE_LOOP,
+            Primitive::Triangles => gl::TRIANGLES,
+            Primitive::TriangleStrip => gl::TRIANGLE_STRIP,
+            Primitive::TriangleFan => gl::TRIANGLE_FAN,
+        }
+    }
+
+    /// whether `count` vertices make up whole primitives
+    pub fn accepts(self, count: usize) -> bool {
+        match self {
+            Primitive::Points => true,
+            Primitive::Lines => count.is_multiple_of(2),
+            Primitive::LineStrip | Primitive::LineLoop => count != 1,
+            Primitive::Triangles => count.is_multiple_of(3),
+            Primitive::TriangleStrip | Primitive::TriangleFan => count == 0 || count >= 3,
+        }
+    }
+}
+
+/// index data in the smallest type that fits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indices<'a> {
+    U8(&'a [u8]),
+    U16(&'a [u16]),
+    U32(&'a [u32]),
+}
+
+impl<'a> Indices<'a> {
+    pub fn len(&self) -> usize {
+        match self {
+            Indices::U8(indices) => indices.len(),
+            Indices::U16(indices) => indices.len(),
+            Indices::U32(indices) => indices.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn max(&self) -> Option<u32> {
+        match self {
+            Indices::U8(indices) => indices.iter().max().map(|&i| i as u32),
+            Indices::U16(indices) => indices.iter().max().map(|&i| i as u32),
+            Indices::U32(indices) => indices.iter().max().cloned(),
+        }
+    }
+
+    fn gl_type(&self) -> GLenum {
+        match self {
+            Indices::U8(_) => gl::UNSIGNED_BYTE,
+            Indices::U16(_) => gl::UNSIGNED_SHORT,
+            Indices::U32(_) => gl::UNSIGNED_INT,
+        }
+    }
+
+    fn bytes(&self) -> (*const c_void, usize) {
+        match self {
+            Indices::U8(indices) => (indices.as_ptr() as *const c_void, mem::size_of_val(*indices)),
+            Indices::U16(indices) => (indices.as_ptr() as *const c_void, mem::size_of_val(*indices)),
+            Indices::U32(indices) => (indices.as_ptr() as *const c_void, mem::size_of_val(*indices)),
+        }
+    }
+}
+
+/// A vertex array with the vertex buffer and the optional index buffer it draws from.
+pub struct Mesh {
+    vao: GLuint,
+    vbo: GLuint,
+    // 0 for meshes drawn without indices
+    ebo: GLuint,
+    index_type: GLenum,
+    // vertices or indices drawn
+    count: usize,
+    vertex_count: usize,
+    primitive: Primitive,
+    layout: VertexLayout,
+    // bounds of the vertices in model space, if known
+    bounds: Option<Aabb>,
+    bounding_sphere: Option<BoundingSphere>,
+}
+
+impl Mesh {
+    /// upload `vertices`, laid out as described by `layout`, and optionally `indices` into them.
+    /// `T` is usually `f32` or a `#[repr(C)]` vertex struct.
+    /// The mesh gets the bounds of the 3 float positions at location 0, if the layout has them.
+    pub unsafe fn new<T: Copy>(
+        layout: VertexLayout,
+        vertices: &[T],
+        indices: Option<Indices>,
+        primitive: Primitive,
+    ) -> Result<Mesh, MeshError> {
+        let vertex_bytes = mem::size_of_val(vertices);
+        let vertex_count = layout.vertex_count(vertex_bytes)?;
+        let count = match indices {
+            Some(ref indices) => {
+                if let Some(max) = indices.max() {
+                    if max as usize >= vertex_count {
+                        return Err(MeshError::IndexOutOfRange {
+                            index: max,
+                            vertex_count,
+                        });
+                    }
+                }
+                indices.len()
+            }
+            None => vertex_count,
+        };
+        if !primitive.accepts(count) {
+            return Err(MeshError::PrimitiveCount { count, primitive });
+        }
+
+        let (mut vao, mut vbo, mut ebo) = (0, 0, 0);
+        gl::GenVertexArrays(1, &mut vao);
+        gl::GenBuffers(1, &mut vbo);
+        gl::BindVertexArray(vao);
+
+        gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+        gl::BufferData(
+            gl::ARRAY_BUFFER,
+            vertex_bytes as GLsizeiptr,
+            vertices.as_ptr() as *const c_void,
+            gl::STATIC_DRAW,
+        );
+        layout.apply();
+
+        let mut index_type = 0;
+        if let Some(ref indices) = indices {
+            let (data, size) = indices.bytes();
+            gl::GenBuffers(1, &mut ebo);
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo);
+            gl::BufferData(gl::ELEMENT_ARRAY_BUFFER, size as GLsizeiptr, data, gl::STATIC_DRAW);
+            index_type = indices.gl_type();
+        }
+
+        gl::BindVertexArray(0);
+        gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+
+        let bytes = slice::from_raw_parts(vertices.as_ptr() as *const u8, vertex_bytes);
+        let positions = layout.positions(bytes).unwrap_or_default();
+        let bounds = Aabb::from_points(positions.iter().cloned());
+        let bounding_sphere = BoundingSphere::from_points(positions.iter().cloned());
+
+        Ok(Mesh {
+            vao,
+            vbo,
+            ebo,
+            index_type,
+            count,
+            vertex_count,
+            primitive,
+            layout,
+            bounds,
+            bounding_sphere,
+        })
+    }
+
+    /// give the mesh other bounds than the ones `Mesh::new` found, or bounds if it found none
+    pub fn with_bounds(mut self, aabb: Aabb, sphere: BoundingSphere) -> Mesh {
+        self.bounds = Some(aabb);
+        self.bounding_sphere = Some(sphere);
+        self
+    }
+
+    /// box around the vertices in model space, `transform` it by the model matrix for world space
+    pub fn bounds(&self) -> Option<Aabb> {
+        self.bounds
+    }
+
+    /// sphere around the vertices in model space, `transform` it by the model matrix for world space
+    pub fn bounding_sphere(&self) -> Option<BoundingSphere> {
+        self.bounding_sphere
+    }
+
+    pub fn layout(&self) -> &VertexLayout {
+        &self.layout
+    }
+
+    pub fn primitive(&self) -> Primitive {
+        self.primitive
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.vertex_count
+    }
+
+    /// number of indices, 0 for meshes without an index buffer
+    pub fn index_count(&self) -> usize {
+        if self.ebo != 0 {
+            self.count
+        } else {
+            0
+        }
+    }
+
+    pub unsafe fn bind(&self) {
+        gl::BindVertexArray(self.vao);
+    }
+
+    /// draw the whole mesh with the current shader
+    pub unsafe fn draw(&self) {
+        self.bind();
+        if self.ebo != 0 {
+            gl::DrawElements(self.primitive.gl_mode(), self.count as GLsizei, self.index_type, ptr::null());
+        } else {
+            gl::DrawArrays(self.primitive.gl_mode(), 0, self.count as GLsizei);
+        }
+    }
+
+    /// draw the whole mesh `instances` times with the current shader, attributes with a divisor
+    /// advance per instance
+    pub unsafe fn draw_instanced(&self, instances: usize) {
+        self.bind();
+        if self.ebo != 0 {
+            gl::DrawElementsInstanced(
+                self.primitive.gl_mode(),
+                self.count as GLsizei,
+                self.index_type,
+                ptr::null(),
+                instances as GLsizei,
+            );
+        } else {
+            gl::DrawArraysInstanced(self.primitive.gl_mode(), 0, self.count as GLsizei, instances as GLsizei);
+        }
+    }
+
+    pub unsafe fn delete(self) {
+        gl::DeleteVertexArrays(1, &self.vao);
+        gl::DeleteBuffers(1, &self.vbo);
+        if self.ebo != 0 {
+            gl::DeleteBuffers(1, &self.ebo);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytes(floats: &[f32]) -> Vec<u8> {
+        floats.iter().flat_map(|float| float.to_ne_bytes().to_vec()).collect()
+    }
+
+    #[test]
+    fn positions_at_location_0() {
+        let layout = VertexLayout::new(vec![
+            VertexAttribute::float(1, 2),
+            VertexAttribute::float(0, 3),
+        ])
+        .unwrap();
+        let data = bytes(&[0.0, 1.0, -1.0, 2.0, 3.0, 0.5, 0.5, 4.0, -5.0, 6.0]);
+        let positions = layout.positions(&data).unwrap();
+        assert_eq!(positions, vec![Point3::new(-1.0, 2.0, 3.0), Point3::new(4.0, -5.0, 6.0)]);
+        assert_eq!(layout.positions(&[]), Some(vec![]));
+    }
+
+    #[test]
+    fn no_positions_without_3_floats_at_location_0() {
+        let data = bytes(&[0.0; 12]);
+        let elsewhere = VertexLayout::new(vec![VertexAttribute::float(1, 3)]).unwrap();
+        assert_eq!(elsewhere.positions(&data), None);
+        let flat = VertexLayout::new(vec![VertexAttribute::float(0, 2)]).unwrap();
+        assert_eq!(flat.positions(&data), None);
+    }
+}
@@ -0,0 +1,247 @@
+use cgmath::{Point3, Vector2, Vector3, Vector4};
+
+use gl::types::GLuint;
+
+use utils::bounds::{Aabb, BoundingSphere};
+use utils::mesh::{Indices, Mesh, MeshError, Primitive, VertexAttribute, VertexLayout};
+
+/// the kinds of vertex data a `MeshData` can hold
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Semantic {
+    Position,
+    TexCoord,
+    Normal,
+    /// xyz tangent, w the handedness of the bitangent (1 or -1)
+    Tangent,
+    /// rgba in [0, 1]
+    Color,
+}
+
+impl Semantic {
+    /// attribute location used when no other is given, `coordinate.vs` expects
+    /// positions at 0 and texture coords at 1
+    pub fn default_location(self) -> GLuint {
+        match self {
+            Semantic::Position => 0,
+            Semantic::TexCoord => 1,
+            Semantic::Normal => 2,
+            Semantic::Tangent => 3,
+            Semantic::Color => 4,
+        }
+    }
+
+    pub fn components(self) -> usize {
+        match self {
+            Semantic::Position | Semantic::Normal => 3,
+            Semantic::TexCoord => 2,
+            Semantic::Tangent | Semantic::Color => 4,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Semantic::Position => "positions",
+            Semantic::TexCoord => "texture coords",
+            Semantic::Normal => "normals",
+            Semantic::Tangent => "tangents",
+            Semantic::Color => "colors",
+        }
+    }
+}
+
+/// Indexed triangle mesh on the CPU, one vector per kind of vertex data.
+///
+/// Every vector other than `positions` is either empty or holds one value per vertex.
+/// Loaders and generators produce it, `upload` turns it into a `Mesh`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MeshData {
+    pub positions: Vec<Point3<f32>>,
+    pub tex_coords: Vec<Vector2<f32>>,
+    pub normals: Vec<Vector3<f32>>,
+    pub tangents: Vec<Vector4<f32>>,
+    pub colors: Vec<Vector4<f32>>,
+    /// three per triangle, counter-clockwise
+    pub indices: Vec<u32>,
+}
+
+impl MeshData {
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    pub fn has(&self, semantic: Semantic) -> bool {
+        self.stream_len(semantic) > 0
+    }
+
+    /// the kinds of vertex data present
+    pub fn semantics(&self) -> Vec<Semantic> {
+        [
+            Semantic::Position,
+            Semantic::TexCoord,
+            Semantic::Normal,
+            Semantic::Tangent,
+            Semantic::Color,
+        ].iter()
+            .cloned()
+            .filter(|&semantic| self.has(semantic))
+            .collect()
+    }
+
+    fn stream_len(&self, semantic: Semantic) -> usize {
+        match semantic {
+            Semantic::Position => self.positions.len(),
+            Semantic::TexCoord => self.tex_coords.len(),
+            Semantic::Normal => self.normals.len(),
+            Semantic::Tangent => self.tangents.len(),
+            Semantic::Color => self.colors.len(),
+        }
+    }
+
+    /// box around the positions in model space, `None` without vertices
+    pub fn bounds(&self) -> Option<Aabb> {
+        Aabb::from_points(self.positions.iter().cloned())
+    }
+
+    /// sphere around the positions in model space, `None` without vertices
+    pub fn bounding_sphere(&self) -> Option<BoundingSphere> {
+        BoundingSphere::from_points(self.positions.iter().cloned())
+    }
+
+    /// add the vertices and triangles of `other`, both meshes should have the same streams
+    pub fn append(&mut self, other: &MeshData) {
+        let offset = self.vertex_count() as u32;
+        self.positions.extend_from_slice(&other.positions);
+        self.tex_coords.extend_from_slice(&other.tex_coords);
+        self.normals.extend_from_slice(&other.normals);
+        self.tangents.extend_from_slice(&other.tangents);
+        self.colors.extend_from_slice(&other.colors);
+        self.indices.extend(other.indices.iter().map(|&index| index + offset));
+    }
+
+    /// copy of a vertex with all its data at the end of the vertices, returns its index
+    pub fn duplicate_vertex(&mut self, vertex: usize) -> u32 {
+        self.positions.push(self.positions[vertex]);
+        if self.has(Semantic::TexCoord) {
+            self.tex_coords.push(self.tex_coords[vertex]);
+        }
+        if self.has(Semantic::Normal) {
+            self.normals.push(self.normals[vertex]);
+        }
+        if self.has(Semantic::Tangent) {
+            self.tangents.push(self.tangents[vertex]);
+        }
+        if self.has(Semantic::Color) {
+            self.colors.push(self.colors[vertex]);
+        }
+        self.positions.len() as u32 - 1
+    }
+
+    /// check that every stream has one value per vertex and the indices make up whole triangles
+    pub fn validate(&self) -> Result<(), MeshError> {
+        let vertex_count = self.vertex_count();
+        for semantic in self.semantics() {
+            let len = self.stream_len(semantic);
+            if len != vertex_count {
+                return Err(MeshError::StreamLength {
+                    stream: semantic.name(),
+                    len,
+                    vertex_count,
+                });
+            }
+        }
+        if !Primitive::Triangles.accepts(self.indices.len()) {
+            return Err(MeshError::PrimitiveCount {
+                count: self.indices.len(),
+                primitive: Primitive::Triangles,
+            });
+        }
+        if let Some(&index) = self.indices.iter().find(|&&index| index as usize >= vertex_count) {
+            return Err(MeshError::IndexOutOfRange { index, vertex_count });
+        }
+        Ok(())
+    }
+
+    /// components of `semantic` for one vertex, appended to `out`
+    fn push_vertex(&self, semantic: Semantic, vertex: usize, out: &mut Vec<f32>) {
+        match semantic {
+            Semantic::Position => {
+                let p = self.positions[vertex];
+                out.extend_from_slice(&[p.x, p.y, p.z]);
+            }
+            Semantic::TexCoord => {
+                let t = self.tex_coords[vertex];
+                out.extend_from_slice(&[t.x, t.y]);
+            }
+            Semantic::Normal => {
+                let n = self.normals[vertex];
+                out.extend_from_slice(&[n.x, n.y, n.z]);
+            }
+            Semantic::Tangent => {
+                let t = self.tangents[vertex];
+                out.extend_from_slice(&[t.x, t.y, t.z, t.w]);
+            }
+            Semantic::Color => {
+                let c = self.colors[vertex];
+                out.extend_from_slice(&[c.x, c.y, c.z, c.w]);
+            }
+        }
+    }
+
+    /// interleave the given streams into one vertex buffer, each at its attribute location
+    pub fn interleave(&self, attributes: &[(Semantic, GLuint)]) -> Result<(VertexLayout, Vec<f32>), MeshError> {
+        self.validate()?;
+        for &(semantic, _) in attributes {
+            if !self.has(semantic) {
+                return Err(MeshError::StreamLength {
+                    stream: semantic.name(),
+                    len: 0,
+                    vertex_count: self.vertex_count(),
+                });
+            }
+        }
+        let layout = VertexLayout::new(
+            attributes
+                .iter()
+                .map(|&(semantic, location)| VertexAttribute::float(location, semantic.components()))
+                .collect(),
+        )?;
+
+        let floats_per_vertex = layout.stride() / 4;
+        let mut vertices = Vec::with_capacity(self.vertex_count() * floats_per_vertex);
+        for vertex in 0..self.vertex_count() {
+            for &(semantic, _) in attributes {
+                self.push_vertex(semantic, vertex, &mut vertices);
+            }
+        }
+        Ok((layout, vertices))
+    }
+
+    /// every stream present, at its default location
+    pub fn default_attributes(&self) -> Vec<(Semantic, GLuint)> {
+        self.semantics()
+            .into_iter()
+            .map(|semantic| (semantic, semantic.default_location()))
+            .collect()
+    }
+
+    /// upload the given streams and the indices, in the smallest index type that fits,
+    /// the mesh gets the bounds of the positions
+    pub unsafe fn upload(&self, attributes: &[(Semantic, GLuint)]) -> Result<Mesh, MeshError> {
+        let (layout, vertices) = self.interleave(attributes)?;
+        let mesh = if self.vertex_count() <= u16::MAX as usize + 1 {
+            let indices: Vec<u16> = self.indices.iter().map(|&index| index as u16).collect();
+            Mesh::new(layout, &vertices, Some(Indices::U16(&indices)), Primitive::Triangles)?
+        } else {
+            Mesh::new(layout, &vertices, Some(Indices::U32(&self.indices)), Primitive::Triangles)?
+        };
+        // the positions may sit elsewhere than location 0
+        Ok(match (self.bounds(), self.bounding_sphere()) {
+            (Some(aabb), Some(sphere)) => mesh.with_bounds(aabb, sphere),
+            _ => mesh,
+        })
+    }
+}
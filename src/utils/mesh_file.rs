@@ -0,0 +1,618 @@
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::mem;
+use std::path::Path;
+
+use cgmath::{vec2, vec3, vec4, Point3};
+
+use utils::bounds::{Aabb, BoundingSphere};
+use utils::mesh::{Indices, Mesh, MeshError, Primitive, VertexAttribute, VertexLayout};
+use utils::mesh_data::{MeshData, Semantic};
+
+/// first bytes of every mesh file
+pub const MESH_FILE_MAGIC: [u8; 8] = *b"LOGLMESH";
+/// files with another major version can't be read, newer minor versions only add to the format
+pub const MESH_FILE_VERSION: (u16, u16) = (1, 0);
+pub const MESH_FILE_EXTENSION: &str = "mesh";
+
+const HEADER_SIZE: usize = 96;
+const ATTRIBUTE_SIZE: usize = 8;
+/// sections start at multiples of this, so the blobs can be used in place
+const SECTION_ALIGNMENT: usize = 4;
+/// the only component type version 1 writes, as numbered by `ComponentType`
+const FLOAT_COMPONENT: u8 = 6;
+
+#[derive(Debug)]
+pub enum MeshFileError {
+    Io(String),
+    /// not a mesh file at all
+    Magic,
+    Version { major: u16, minor: u16 },
+    /// the header, layout or blobs contradict each other or the file size
+    Invalid(String),
+    Mesh(MeshError),
+}
+
+impl fmt::Display for MeshFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MeshFileError::Io(message) | MeshFileError::Invalid(message) => write!(f, "{}", message),
+            MeshFileError::Magic => write!(f, "not a mesh file"),
+            MeshFileError::Version { major, minor } => write!(
+                f,
+                "mesh file version {}.{} can't be read, expected {}.x",
+                major, minor, MESH_FILE_VERSION.0
+            ),
+            MeshFileError::Mesh(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for MeshFileError {}
+
+impl From<MeshError> for MeshFileError {
+    fn from(e: MeshError) -> Self {
+        MeshFileError::Mesh(e)
+    }
+}
+
+fn invalid(message: String) -> MeshFileError {
+    MeshFileError::Invalid(message)
+}
+
+fn semantic_code(semantic: Semantic) -> u8 {
+    match semantic {
+        Semantic::Position => 0,
+        Semantic::TexCoord => 1,
+        Semantic::Normal => 2,
+        Semantic::Tangent => 3,
+        Semantic::Color => 4,
+    }
+}
+
+fn semantic_from_code(code: u8) -> Option<Semantic> {
+    match code {
+        0 => Some(Semantic::Position),
+        1 => Some(Semantic::TexCoord),
+        2 => Some(Semantic::Normal),
+        3 => Some(Semantic::Tangent),
+        4 => Some(Semantic::Color),
+        _ => None,
+    }
+}
+
+/// Encode a mesh, which has to have at least one vertex.
+///
+/// All little endian:
+///
+/// | offset | contents |
+/// | --- | --- |
+/// | 0 | magic `LOGLMESH` |
+/// | 8 | major and minor version, u16 each |
+/// | 12 | header size, u32 |
+/// | 16 | vertex count, index count, u32 each |
+/// | 24 | index size (2 or 4), attribute count, u8 each, 2 reserved bytes |
+/// | 28 | vertex stride, u32 |
+/// | 32 | box min and max, sphere center and radius, 10 f32 |
+/// | 72 | offsets of the layout, vertex blob and index blob with the sizes of both blobs, u32 each |
+/// | 92 | reserved |
+///
+/// The layout has 8 bytes per attribute: semantic, component count, component type, normalized
+/// and its offset in the vertex as u32. The vertex blob holds the interleaved vertices, the index
+/// blob triangles with counter-clockwise winding, both ready to be handed to GL.
+pub fn encode_mesh(data: &MeshData) -> Result<Vec<u8>, MeshFileError> {
+    let semantics = data.semantics();
+    let attributes: Vec<(Semantic, u32)> = semantics
+        .iter()
+        .map(|&semantic| (semantic, semantic.default_location()))
+        .collect();
+    let (aabb, sphere) = match (data.bounds(), data.bounding_sphere()) {
+        (Some(aabb), Some(sphere)) => (aabb, sphere),
+        _ => return Err(invalid("a mesh file needs at least one vertex".to_string())),
+    };
+    let (layout, vertices) = data.interleave(&attributes)?;
+    let index_size = if data.vertex_count() <= u16::MAX as usize + 1 { 2 } else { 4 };
+
+    let layout_offset = HEADER_SIZE;
+    let vertex_offset = align(layout_offset + semantics.len() * ATTRIBUTE_SIZE);
+    let vertex_bytes = vertices.len() * mem::size_of::<f32>();
+    let index_offset = align(vertex_offset + vertex_bytes);
+    let index_bytes = data.indices.len() * index_size;
+
+    let mut bytes = Vec::with_capacity(index_offset + index_bytes);
+    bytes.extend_from_slice(&MESH_FILE_MAGIC);
+    push_u16(&mut bytes, MESH_FILE_VERSION.0);
+    push_u16(&mut bytes, MESH_FILE_VERSION.1);
+    push_u32(&mut bytes, HEADER_SIZE as u32);
+    push_u32(&mut bytes, data.vertex_count() as u32);
+    push_u32(&mut bytes, data.indices.len() as u32);
+    bytes.extend_from_slice(&[index_size as u8, semantics.len() as u8, 0, 0]);
+    push_u32(&mut bytes, layout.stride() as u32);
+    for value in &[
+        aabb.min.x,
+        aabb.min.y,
+        aabb.min.z,
+        aabb.max.x,
+        aabb.max.y,
+        aabb.max.z,
+        sphere.center.x,
+        sphere.center.y,
+        sphere.center.z,
+        sphere.radius,
+    ] {
+        push_u32(&mut bytes, value.to_bits());
+    }
+    for &value in &[layout_offset, vertex_offset, vertex_bytes, index_offset, index_bytes, 0] {
+        push_u32(&mut bytes, value as u32);
+    }
+    debug_assert_eq!(bytes.len(), HEADER_SIZE);
+
+    for (&semantic, attribute) in semantics.iter().zip(layout.attributes()) {
+        bytes.extend_from_slice(&[
+            semantic_code(semantic),
+            attribute.components as u8,
+            FLOAT_COMPONENT,
+            attribute.normalized as u8,
+        ]);
+        push_u32(&mut bytes, layout.offset(attribute.location).unwrap() as u32);
+    }
+    bytes.resize(vertex_offset, 0);
+    for value in &vertices {
+        push_u32(&mut bytes, value.to_bits());
+    }
+    bytes.resize(index_offset, 0);
+    for &index in &data.indices {
+        if index_size == 2 {
+            push_u16(&mut bytes, index as u16);
+        } else {
+            push_u32(&mut bytes, index);
+        }
+    }
+    Ok(bytes)
+}
+
+pub fn save_mesh(path: &Path, data: &MeshData) -> Result<(), MeshFileError> {
+    let bytes = encode_mesh(data)?;
+    let mut file =
+        File::create(path).map_err(|e| MeshFileError::Io(format!("Failed to create {}: {}", path.display(), e)))?;
+    file.write_all(&bytes)
+        .map_err(|e| MeshFileError::Io(format!("Failed to write {}: {}", path.display(), e)))
+}
+
+/// the contents of a mesh file, to be parsed with `MeshFile::parse`
+pub fn read_mesh_file(path: &Path) -> Result<Vec<u8>, MeshFileError> {
+    let mut file = File::open(path).map_err(|e| MeshFileError::Io(format!("Failed to open {}: {}", path.display(), e)))?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)
+        .map_err(|e| MeshFileError::Io(format!("Failed to read {}: {}", path.display(), e)))?;
+    Ok(bytes)
+}
+
+/// A validated mesh file, borrowing its blobs from the bytes it was parsed from.
+pub struct MeshFile<'a> {
+    version: (u16, u16),
+    vertex_count: usize,
+    index_count: usize,
+    index_size: usize,
+    attributes: Vec<(Semantic, VertexAttribute)>,
+    layout: VertexLayout,
+    bounds: Aabb,
+    bounding_sphere: BoundingSphere,
+    vertices: &'a [u8],
+    indices: &'a [u8],
+}
+
+impl<'a> MeshFile<'a> {
+    /// Check the header, the layout and that the blobs fit the file, without copying them.
+    ///
+    /// Indices are checked against the vertex count, so uploading a parsed file can't read
+    /// out of bounds.
+    pub fn parse(bytes: &'a [u8]) -> Result<MeshFile<'a>, MeshFileError> {
+        if bytes.len() < MESH_FILE_MAGIC.len() || bytes[..MESH_FILE_MAGIC.len()] != MESH_FILE_MAGIC {
+            return Err(MeshFileError::Magic);
+        }
+        if bytes.len() < 12 {
+            return Err(invalid("file ends inside the version".to_string()));
+        }
+        let version = (read_u16(bytes, 8), read_u16(bytes, 10));
+        if version.0 != MESH_FILE_VERSION.0 {
+            return Err(MeshFileError::Version {
+                major: version.0,
+                minor: version.1,
+            });
+        }
+        if bytes.len() < HEADER_SIZE {
+            return Err(invalid(format!(
+                "file of {} bytes is shorter than the {} byte header",
+                bytes.len(),
+                HEADER_SIZE
+            )));
+        }
+        let header_size = read_u32(bytes, 12) as usize;
+        if header_size < HEADER_SIZE {
+            return Err(invalid(format!("header size {} is less than {}", header_size, HEADER_SIZE)));
+        }
+
+        let vertex_count = read_u32(bytes, 16) as usize;
+        let index_count = read_u32(bytes, 20) as usize;
+        let index_size = bytes[24] as usize;
+        let attribute_count = bytes[25] as usize;
+        let stride = read_u32(bytes, 28) as usize;
+        let float = |i: usize| f32::from_bits(read_u32(bytes, 32 + i * 4));
+        let bounds = Aabb::new(
+            Point3::new(float(0), float(1), float(2)),
+            Point3::new(float(3), float(4), float(5)),
+        );
+        let bounding_sphere = BoundingSphere::new(Point3::new(float(6), float(7), float(8)), float(9));
+        let section = |i: usize| read_u32(bytes, 72 + i * 4) as usize;
+        let (layout_offset, vertex_offset, vertex_bytes, index_offset, index_bytes) =
+            (section(0), section(1), section(2), section(3), section(4));
+
+        if index_size != 2 && index_size != 4 {
+            return Err(invalid(format!("index size {} is neither 2 nor 4", index_size)));
+        }
+        if !Primitive::Triangles.accepts(index_count) {
+            return Err(invalid(format!("{} indices don't make whole triangles", index_count)));
+        }
+        if (0..10).map(float).any(|value| !value.is_finite())
+            || bounds.min.x > bounds.max.x
+            || bounds.min.y > bounds.max.y
+            || bounds.min.z > bounds.max.z
+            || bounding_sphere.radius < 0.0
+        {
+            return Err(invalid("bounds aren't a valid box and sphere".to_string()));
+        }
+        let sections = [
+            ("layout", layout_offset, attribute_count * ATTRIBUTE_SIZE),
+            ("vertex", vertex_offset, vertex_bytes),
+            ("index", index_offset, index_bytes),
+        ];
+        for &(name, offset, size) in sections.iter() {
+            if offset < header_size || !offset.is_multiple_of(SECTION_ALIGNMENT) {
+                return Err(invalid(format!("{} section at {} is inside the header or misaligned", name, offset)));
+            }
+            if offset.checked_add(size).is_none_or(|end| end > bytes.len()) {
+                return Err(invalid(format!(
+                    "{} section of {} bytes at {} runs past the end of the {} byte file",
+                    name,
+                    size,
+                    offset,
+                    bytes.len()
+                )));
+            }
+        }
+        if vertex_count.checked_mul(stride) != Some(vertex_bytes) {
+            return Err(invalid(format!(
+                "{} bytes of vertices don't hold {} vertices of {} bytes",
+                vertex_bytes, vertex_count, stride
+            )));
+        }
+        if index_count * index_size != index_bytes {
+            return Err(invalid(format!(
+                "{} bytes of indices don't hold {} indices of {} bytes",
+                index_bytes, index_count, index_size
+            )));
+        }
+
+        let mut attributes = Vec::with_capacity(attribute_count);
+        let mut offsets = Vec::with_capacity(attribute_count);
+        for i in 0..attribute_count {
+            let entry = &bytes[layout_offset + i * ATTRIBUTE_SIZE..layout_offset + (i + 1) * ATTRIBUTE_SIZE];
+            let semantic = semantic_from_code(entry[0])
+                .ok_or_else(|| invalid(format!("attribute {} has unknown semantic {}", i, entry[0])))?;
+            if entry[1] as usize != semantic.components() || entry[2] != FLOAT_COMPONENT || entry[3] != 0 {
+                return Err(invalid(format!(
+                    "{} need {} floats per vertex",
+                    semantic.name(),
+                    semantic.components()
+                )));
+            }
+            if attributes.iter().any(|&(other, _)| other == semantic) {
+                return Err(invalid(format!("{} are in the layout twice", semantic.name())));
+            }
+            attributes.push((semantic, VertexAttribute::float(semantic.default_location(), semantic.components())));
+            offsets.push(read_u32(entry, 4) as usize);
+        }
+        if !attributes.iter().any(|&(semantic, _)| semantic == Semantic::Position) {
+            return Err(invalid("layout without positions".to_string()));
+        }
+        // the layout is packed in order, as `VertexLayout` lays attributes out
+        let layout = VertexLayout::new(attributes.iter().map(|&(_, attribute)| attribute).collect())?;
+        for (&(semantic, attribute), &offset) in attributes.iter().zip(&offsets) {
+            if layout.offset(attribute.location) != Some(offset) {
+                return Err(invalid(format!("{} at offset {} aren't packed in order", semantic.name(), offset)));
+            }
+        }
+        if layout.stride() != stride {
+            return Err(invalid(format!("stride {} doesn't match the {} byte layout", stride, layout.stride())));
+        }
+
+        let file = MeshFile {
+            version,
+            vertex_count,
+            index_count,
+            index_size,
+            attributes,
+            layout,
+            bounds,
+            bounding_sphere,
+            vertices: &bytes[vertex_offset..vertex_offset + vertex_bytes],
+            indices: &bytes[index_offset..index_offset + index_bytes],
+        };
+        if let Some(index) = (0..index_count).map(|i| file.index(i)).find(|&index| index as usize >= vertex_count) {
+            return Err(invalid(format!("index {} is out of range for {} vertices", index, vertex_count)));
+        }
+        Ok(file)
+    }
+
+    pub fn version(&self) -> (u16, u16) {
+        self.version
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.vertex_count
+    }
+
+    pub fn index_count(&self) -> usize {
+        self.index_count
+    }
+
+    /// the streams in the file, at their default locations
+    pub fn semantics(&self) -> Vec<Semantic> {
+        self.attributes.iter().map(|&(semantic, _)| semantic).collect()
+    }
+
+    pub fn layout(&self) -> &VertexLayout {
+        &self.layout
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        self.bounds
+    }
+
+    pub fn bounding_sphere(&self) -> BoundingSphere {
+        self.bounding_sphere
+    }
+
+    /// the interleaved vertices as stored, laid out by `layout`
+    pub fn vertex_bytes(&self) -> &'a [u8] {
+        self.vertices
+    }
+
+    /// the indices as stored, 2 or 4 bytes each
+    pub fn index_bytes(&self) -> &'a [u8] {
+        self.indices
+    }
+
+    fn index(&self, i: usize) -> u32 {
+        if self.index_size == 2 {
+            u32::from(read_u16(self.indices, i * 2))
+        } else {
+            read_u32(self.indices, i * 4)
+        }
+    }
+
+    /// Upload the blobs as they are, the mesh gets the bounds stored in the file.
+    ///
+    /// The indices are only copied if the bytes they were parsed from aren't aligned for them.
+    /// Like GL itself this assumes a little endian machine.
+    pub unsafe fn upload(&self) -> Result<Mesh, MeshError> {
+        let mesh = if self.index_size == 2 {
+            let (prefix, indices, suffix) = self.indices.align_to::<u16>();
+            let copied: Vec<u16>;
+            let indices = if prefix.is_empty() && suffix.is_empty() {
+                indices
+            } else {
+                copied = (0..self.index_count).map(|i| self.index(i) as u16).collect();
+                &copied
+            };
+            Mesh::new(self.layout.clone(), self.vertices, Some(Indices::U16(indices)), Primitive::Triangles)?
+        } else {
+            let (prefix, indices, suffix) = self.indices.align_to::<u32>();
+            let copied: Vec<u32>;
+            let indices = if prefix.is_empty() && suffix.is_empty() {
+                indices
+            } else {
+                copied = (0..self.index_count).map(|i| self.index(i)).collect();
+                &copied
+            };
+            Mesh::new(self.layout.clone(), self.vertices, Some(Indices::U32(indices)), Primitive::Triangles)?
+        };
+        Ok(mesh.with_bounds(self.bounds, self.bounding_sphere))
+    }
+
+    /// decode the blobs back into streams
+    pub fn to_mesh_data(&self) -> MeshData {
+        let mut data = MeshData::default();
+        let stride = self.layout.stride();
+        for &(semantic, attribute) in &self.attributes {
+            let offset = self.layout.offset(attribute.location).unwrap();
+            let component = |vertex: usize, i: usize| {
+                f32::from_bits(read_u32(self.vertices, vertex * stride + offset + i * 4))
+            };
+            let vertices = 0..self.vertex_count;
+            match semantic {
+                Semantic::Position => {
+                    data.positions = vertices
+                        .map(|v| Point3::new(component(v, 0), component(v, 1), component(v, 2)))
+                        .collect()
+                }
+                Semantic::TexCoord => data.tex_coords = vertices.map(|v| vec2(component(v, 0), component(v, 1))).collect(),
+                Semantic::Normal => {
+                    data.normals = vertices
+                        .map(|v| vec3(component(v, 0), component(v, 1), component(v, 2)))
+                        .collect()
+                }
+                Semantic::Tangent => {
+                    data.tangents = vertices
+                        .map(|v| vec4(component(v, 0), component(v, 1), component(v, 2), component(v, 3)))
+                        .collect()
+                }
+                Semantic::Color => {
+                    data.colors = vertices
+                        .map(|v| vec4(component(v, 0), component(v, 1), component(v, 2), component(v, 3)))
+                        .collect()
+                }
+            }
+        }
+        data.indices = (0..self.index_count).map(|i| self.index(i)).collect();
+        data
+    }
+}
+
+fn align(offset: usize) -> usize {
+    offset.div_ceil(SECTION_ALIGNMENT) * SECTION_ALIGNMENT
+}
+
+fn push_u16(bytes: &mut Vec<u8>, value: u16) {
+    bytes.extend_from_slice(&[value as u8, (value >> 8) as u8]);
+}
+
+fn push_u32(bytes: &mut Vec<u8>, value: u32) {
+    bytes.extend_from_slice(&[value as u8, (value >> 8) as u8, (value >> 16) as u8, (value >> 24) as u8]);
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from(bytes[offset]) | u16::from(bytes[offset + 1]) << 8
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from(bytes[offset])
+        | u32::from(bytes[offset + 1]) << 8
+        | u32::from(bytes[offset + 2]) << 16
+        | u32::from(bytes[offset + 3]) << 24
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::shapes;
+
+    /// a cube with every stream
+    fn cube() -> MeshData {
+        let mut data = shapes::cube(1.0);
+        data.colors = (0..data.vertex_count()).map(|i| vec4(i as f32 / 24.0, 0.5, 1.0, 1.0)).collect();
+        data
+    }
+
+    fn assert_invalid(bytes: &[u8]) {
+        match MeshFile::parse(bytes) {
+            Err(MeshFileError::Invalid(_)) => (),
+            Err(e) => panic!("expected an invalid file, got {:?}", e),
+            Ok(_) => panic!("expected an invalid file, got a mesh"),
+        }
+    }
+
+    fn set_u32(bytes: &mut [u8], offset: usize, value: u32) {
+        bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    #[test]
+    fn round_trip() {
+        let data = cube();
+        let bytes = encode_mesh(&data).unwrap();
+        let file = MeshFile::parse(&bytes).unwrap();
+        assert_eq!(file.version(), MESH_FILE_VERSION);
+        assert_eq!(file.vertex_count(), 24);
+        assert_eq!(file.index_count(), 36);
+        assert_eq!(file.semantics(), data.semantics());
+        assert_eq!(file.bounds(), data.bounds().unwrap());
+        assert_eq!(file.bounding_sphere(), data.bounding_sphere().unwrap());
+        // 2 byte indices for small meshes
+        assert_eq!(file.index_bytes().len(), 36 * 2);
+        assert_eq!(file.vertex_bytes().len(), 24 * file.layout().stride());
+        assert_eq!(file.to_mesh_data(), data);
+    }
+
+    #[test]
+    fn round_trip_with_32_bit_indices() {
+        let mut data = shapes::plane(1.0, 1.0, 300, 300);
+        data.normals.clear();
+        data.tangents.clear();
+        assert!(data.vertex_count() > u16::MAX as usize + 1);
+        let bytes = encode_mesh(&data).unwrap();
+        let file = MeshFile::parse(&bytes).unwrap();
+        assert_eq!(file.index_bytes().len(), data.indices.len() * 4);
+        assert_eq!(file.to_mesh_data(), data);
+    }
+
+    #[test]
+    fn empty_mesh_is_refused() {
+        assert!(encode_mesh(&MeshData::default()).is_err());
+    }
+
+    #[test]
+    fn truncated() {
+        let bytes = encode_mesh(&cube()).unwrap();
+        for &length in [0, 4, 10, 50, HEADER_SIZE, HEADER_SIZE + 20, bytes.len() / 2, bytes.len() - 1].iter() {
+            assert!(MeshFile::parse(&bytes[..length]).is_err(), "{} bytes", length);
+        }
+        assert_invalid(&bytes[..bytes.len() - 1]);
+    }
+
+    #[test]
+    fn corrupted_header() {
+        let bytes = encode_mesh(&cube()).unwrap();
+
+        let mut corrupted = bytes.clone();
+        corrupted[0] = b'X';
+        assert!(matches!(MeshFile::parse(&corrupted), Err(MeshFileError::Magic)));
+
+        let mut corrupted = bytes.clone();
+        corrupted[8] = 2;
+        assert!(matches!(MeshFile::parse(&corrupted), Err(MeshFileError::Version { major: 2, .. })));
+        // newer minor versions are still readable
+        let mut newer = bytes.clone();
+        newer[10] = 7;
+        assert_eq!(MeshFile::parse(&newer).unwrap().version(), (MESH_FILE_VERSION.0, 7));
+
+        let cases: Vec<(usize, u32)> = vec![
+            (12, 10),                           // header size below the fixed header
+            (16, 25),                           // vertex count not matching the vertex blob
+            (20, 35),                           // index count that isn't whole triangles
+            (20, 72),                           // more indices than the blob holds
+            (28, 12),                           // stride not matching the layout
+            (32, f32::NAN.to_bits()),           // bounds that aren't numbers
+            (32, 5.0f32.to_bits()),             // box min above its max
+            (68, (-1.0f32).to_bits()),          // negative sphere radius
+            (72, 8),                            // layout inside the header
+            (76, 98),                           // misaligned vertex blob
+            (84, bytes.len() as u32),           // index blob past the end
+            (88, u32::MAX),                     // index blob size overflowing
+        ];
+        for &(offset, value) in &cases {
+            let mut corrupted = bytes.clone();
+            set_u32(&mut corrupted, offset, value);
+            assert_invalid(&corrupted);
+        }
+
+        let mut corrupted = bytes.clone();
+        corrupted[24] = 3;
+        assert_invalid(&corrupted);
+    }
+
+    #[test]
+    fn corrupted_layout_and_indices() {
+        let bytes = encode_mesh(&cube()).unwrap();
+        let layout = read_u32(&bytes, 72) as usize;
+        let index_offset = read_u32(&bytes, 84) as usize;
+
+        // unknown semantic, wrong component count, positions twice
+        for &(byte, value) in &[(0, 9), (1, 2), (ATTRIBUTE_SIZE, 0)] {
+            let mut corrupted = bytes.clone();
+            corrupted[layout + byte] = value;
+            assert_invalid(&corrupted);
+        }
+        // an attribute that isn't where a packed layout puts it
+        let mut corrupted = bytes.clone();
+        set_u32(&mut corrupted, layout + ATTRIBUTE_SIZE + 4, 4);
+        assert_invalid(&corrupted);
+
+        // an index past the last vertex
+        let mut corrupted = bytes.clone();
+        corrupted[index_offset..index_offset + 2].copy_from_slice(&24u16.to_le_bytes());
+        assert_invalid(&corrupted);
+    }
+}
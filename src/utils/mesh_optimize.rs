@@ -0,0 +1,411 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use utils::mesh::MeshError;
+use utils::mesh_data::MeshData;
+
+// size of the FIFO cache simulated for the statistics, close to what GPUs have
+pub const STATS_CACHE_SIZE: usize = 16;
+
+// tuning of the triangle order, as given by Tom Forsyth's "Linear-Speed Vertex Cache Optimisation"
+const FORSYTH_CACHE_SIZE: usize = 32;
+const CACHE_DECAY_POWER: f32 = 1.5;
+const LAST_TRIANGLE_SCORE: f32 = 0.75;
+const VALENCE_BOOST_SCALE: f32 = 2.0;
+const VALENCE_BOOST_POWER: f32 = 0.5;
+
+/// how well the vertices and triangles of a mesh are laid out for the GPU
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeshStats {
+    pub vertex_count: usize,
+    pub triangle_count: usize,
+    /// average cache miss ratio, vertex shader runs per triangle: 3 is no reuse at all, 0.5 is ideal
+    pub acmr: f32,
+    /// average transformed vertex ratio, vertex shader runs per vertex: 1 is ideal
+    pub atvr: f32,
+}
+
+impl MeshStats {
+    pub fn new(data: &MeshData) -> MeshStats {
+        let misses = cache_misses(&data.indices, STATS_CACHE_SIZE) as f32;
+        let ratio = |count: usize| if count > 0 { misses / count as f32 } else { 0.0 };
+        MeshStats {
+            vertex_count: data.vertex_count(),
+            triangle_count: data.triangle_count(),
+            acmr: ratio(data.triangle_count()),
+            atvr: ratio(data.vertex_count()),
+        }
+    }
+}
+
+/// statistics before and after `optimize`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OptimizeReport {
+    pub before: MeshStats,
+    pub after: MeshStats,
+}
+
+impl fmt::Display for OptimizeReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} triangles, vertices {} -> {}, ACMR {:.3} -> {:.3}, ATVR {:.3} -> {:.3}",
+            self.after.triangle_count,
+            self.before.vertex_count,
+            self.after.vertex_count,
+            self.before.acmr,
+            self.after.acmr,
+            self.before.atvr,
+            self.after.atvr
+        )
+    }
+}
+
+/// weld, reorder the triangles for the vertex cache and then the vertices for fetching
+pub fn optimize(data: &mut MeshData) -> Result<OptimizeReport, MeshError> {
+    let before = MeshStats::new(data);
+    weld_vertices(data)?;
+    optimize_vertex_cache(data);
+    optimize_vertex_fetch(data);
+    Ok(OptimizeReport {
+        before,
+        after: MeshStats::new(data),
+    })
+}
+
+/// Merge vertices that are equal in every stream, leaving the mesh indexed without duplicates.
+///
+/// Vertices are compared bit for bit, apart from -0 and 0 which count as equal. Vertices no
+/// triangle uses are dropped. Fails if the mesh doesn't validate.
+pub fn weld_vertices(data: &mut MeshData) -> Result<(), MeshError> {
+    data.validate()?;
+    let mut unique: HashMap<Vec<u32>, u32> = HashMap::new();
+    let mut remap: Vec<Option<u32>> = vec![None; data.vertex_count()];
+    let mut kept: Vec<usize> = Vec::new();
+    let mut indices = Vec::with_capacity(data.indices.len());
+    for &index in &data.indices {
+        let vertex = index as usize;
+        let welded = match remap[vertex] {
+            Some(welded) => welded,
+            None => {
+                let next = kept.len() as u32;
+                let welded = *unique.entry(vertex_key(data, vertex)).or_insert(next);
+                if welded == next {
+                    kept.push(vertex);
+                }
+                remap[vertex] = Some(welded);
+                welded
+            }
+        };
+        indices.push(welded);
+    }
+    data.indices = indices;
+    keep_vertices(data, &kept);
+    Ok(())
+}
+
+/// bits of every component of a vertex
+fn vertex_key(data: &MeshData, vertex: usize) -> Vec<u32> {
+    let mut key = Vec::new();
+    let mut push = |components: &[f32]| key.extend(components.iter().map(|&c| (c + 0.0).to_bits()));
+    let p = data.positions[vertex];
+    push(&[p.x, p.y, p.z]);
+    if let Some(t) = data.tex_coords.get(vertex) {
+        push(&[t.x, t.y]);
+    }
+    if let Some(n) = data.normals.get(vertex) {
+        push(&[n.x, n.y, n.z]);
+    }
+    if let Some(t) = data.tangents.get(vertex) {
+        push(&[t.x, t.y, t.z, t.w]);
+    }
+    if let Some(c) = data.colors.get(vertex) {
+        push(&[c.x, c.y, c.z, c.w]);
+    }
+    key
+}
+
+/// keep only the given vertices, in that order, the indices have to refer to the new order already
+fn keep_vertices(data: &mut MeshData, kept: &[usize]) {
+    fn select<T: Copy>(stream: &mut Vec<T>, kept: &[usize]) {
+        if !stream.is_empty() {
+            *stream = kept.iter().map(|&vertex| stream[vertex]).collect();
+        }
+    }
+    select(&mut data.positions, kept);
+    select(&mut data.tex_coords, kept);
+    select(&mut data.normals, kept);
+    select(&mut data.tangents, kept);
+    select(&mut data.colors, kept);
+}
+
+/// Reorder the triangles so vertices are reused while they're still in the post-transform cache.
+///
+/// Greedy Forsyth ordering: the next triangle is the one whose vertices score best, vertices
+/// scoring higher the more recently they were used and the fewer triangles they have left.
+pub fn optimize_vertex_cache(data: &mut MeshData) {
+    let triangle_count = data.triangle_count();
+    if triangle_count == 0 {
+        return;
+    }
+    let vertex_count = data.vertex_count();
+
+    // triangles not yet emitted around each vertex
+    let mut vertex_triangles: Vec<Vec<usize>> = vec![Vec::new(); vertex_count];
+    for (triangle, corners) in data.indices.chunks(3).enumerate() {
+        for &vertex in corners {
+            vertex_triangles[vertex as usize].push(triangle);
+        }
+    }
+    let mut cache_position: Vec<Option<usize>> = vec![None; vertex_count];
+    let mut vertex_scores: Vec<f32> = (0..vertex_count)
+        .map(|vertex| vertex_score(None, vertex_triangles[vertex].len()))
+        .collect();
+    let mut triangle_scores: Vec<f32> = data
+        .indices
+        .chunks(3)
+        .map(|corners| corners.iter().map(|&vertex| vertex_scores[vertex as usize]).sum())
+        .collect();
+    let mut emitted = vec![false; triangle_count];
+
+    let mut order = Vec::with_capacity(data.indices.len());
+    let mut cache: Vec<u32> = Vec::with_capacity(FORSYTH_CACHE_SIZE + 3);
+    let mut best = best_triangle(&triangle_scores, &emitted, 0..triangle_count);
+    let mut next_unemitted = 0;
+    while let Some(triangle) = best {
+        emitted[triangle] = true;
+        let corners = [
+            data.indices[triangle * 3],
+            data.indices[triangle * 3 + 1],
+            data.indices[triangle * 3 + 2],
+        ];
+        order.extend_from_slice(&corners);
+        for &vertex in &corners {
+            vertex_triangles[vertex as usize].retain(|&other| other != triangle);
+        }
+
+        // the triangle's vertices move to the front of the cache, pushing others out the back
+        let mut new_cache: Vec<u32> = corners.to_vec();
+        new_cache.extend(cache.iter().filter(|vertex| !corners.contains(vertex)));
+        let evicted: Vec<u32> = new_cache.split_off(FORSYTH_CACHE_SIZE.min(new_cache.len()));
+        for &vertex in &evicted {
+            cache_position[vertex as usize] = None;
+        }
+        for (position, &vertex) in new_cache.iter().enumerate() {
+            cache_position[vertex as usize] = Some(position);
+        }
+        cache = new_cache;
+
+        // rescore everything touched, the next triangle is the best one around the cache
+        let mut touched: Vec<usize> = Vec::new();
+        for &vertex in cache.iter().chain(evicted.iter()) {
+            let vertex = vertex as usize;
+            vertex_scores[vertex] = vertex_score(cache_position[vertex], vertex_triangles[vertex].len());
+            touched.extend_from_slice(&vertex_triangles[vertex]);
+        }
+        for &triangle in &touched {
+            triangle_scores[triangle] = data.indices[triangle * 3..triangle * 3 + 3]
+                .iter()
+                .map(|&vertex| vertex_scores[vertex as usize])
+                .sum();
+        }
+        best = best_triangle(&triangle_scores, &emitted, touched.into_iter()).or_else(|| {
+            // nothing left around the cache, carry on with the next triangle in the original order
+            while next_unemitted < triangle_count && emitted[next_unemitted] {
+                next_unemitted += 1;
+            }
+            if next_unemitted < triangle_count {
+                Some(next_unemitted)
+            } else {
+                None
+            }
+        });
+    }
+    data.indices = order;
+}
+
+fn vertex_score(cache_position: Option<usize>, remaining_triangles: usize) -> f32 {
+    if remaining_triangles == 0 {
+        return -1.0;
+    }
+    let cache_score = match cache_position {
+        // the last triangle's vertices get a fixed score, so it doesn't matter which order they were in
+        Some(position) if position < 3 => LAST_TRIANGLE_SCORE,
+        Some(position) => {
+            let scale = 1.0 / (FORSYTH_CACHE_SIZE - 3) as f32;
+            (1.0 - (position - 3) as f32 * scale).powf(CACHE_DECAY_POWER)
+        }
+        None => 0.0,
+    };
+    // vertices with few triangles left are worth finishing off
+    let valence_boost = VALENCE_BOOST_SCALE * (remaining_triangles as f32).powf(-VALENCE_BOOST_POWER);
+    cache_score + valence_boost
+}
+
+fn best_triangle<I: Iterator<Item = usize>>(scores: &[f32], emitted: &[bool], candidates: I) -> Option<usize> {
+    let mut best: Option<usize> = None;
+    for triangle in candidates.filter(|&triangle| !emitted[triangle]) {
+        if best.is_none_or(|best| scores[triangle] > scores[best]) {
+            best = Some(triangle);
+        }
+    }
+    best
+}
+
+/// Reorder the vertices by first use, so they're read from memory mostly in order.
+///
+/// Vertices no triangle uses are dropped.
+pub fn optimize_vertex_fetch(data: &mut MeshData) {
+    let mut remap: Vec<Option<u32>> = vec![None; data.vertex_count()];
+    let mut kept: Vec<usize> = Vec::new();
+    for index in data.indices.iter_mut() {
+        let vertex = *index as usize;
+        *index = *remap[vertex].get_or_insert_with(|| {
+            kept.push(vertex);
+            kept.len() as u32 - 1
+        });
+    }
+    keep_vertices(data, &kept);
+}
+
+/// vertex shader runs for drawing `indices` with a FIFO post-transform cache of `cache_size` entries
+pub fn cache_misses(indices: &[u32], cache_size: usize) -> usize {
+    let mut cache: Vec<u32> = Vec::with_capacity(cache_size);
+    let mut next = 0;
+    let mut misses = 0;
+    for &vertex in indices {
+        if !cache.contains(&vertex) {
+            misses += 1;
+            if cache.len() < cache_size {
+                cache.push(vertex);
+            } else {
+                cache[next] = vertex;
+                next = (next + 1) % cache_size;
+            }
+        }
+    }
+    misses
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::shapes;
+
+    /// every corner its own vertex, like a mesh without an index buffer
+    fn unweld(data: &MeshData) -> MeshData {
+        let mut unwelded = data.clone();
+        let kept: Vec<usize> = data.indices.iter().map(|&index| index as usize).collect();
+        keep_vertices(&mut unwelded, &kept);
+        unwelded.indices = (0..kept.len() as u32).collect();
+        unwelded
+    }
+
+    /// the drawn triangles by vertex key, each starting at its smallest corner so winding is kept
+    fn triangles(data: &MeshData) -> Vec<Vec<Vec<u32>>> {
+        let mut triangles: Vec<Vec<Vec<u32>>> = data
+            .indices
+            .chunks(3)
+            .map(|corners| {
+                let mut keys: Vec<Vec<u32>> = corners.iter().map(|&vertex| vertex_key(data, vertex as usize)).collect();
+                let first = (0..3).min_by_key(|&k| keys[k].clone()).unwrap();
+                keys.rotate_left(first);
+                keys
+            }).collect();
+        triangles.sort();
+        triangles
+    }
+
+    #[test]
+    fn weld_cube() {
+        let cube = shapes::cube(1.0);
+        let mut data = unweld(&cube);
+        assert_eq!(data.vertex_count(), 36);
+        weld_vertices(&mut data).unwrap();
+        assert_eq!(data.vertex_count(), 24);
+        assert_eq!(triangles(&data), triangles(&cube));
+    }
+
+    #[test]
+    fn weld_treats_negative_zero_as_zero() {
+        let mut data = shapes::quad(1.0, 1.0);
+        data.append(&shapes::quad(1.0, 1.0));
+        data.positions[4].z = -0.0;
+        weld_vertices(&mut data).unwrap();
+        assert_eq!(data.vertex_count(), 4);
+        assert_eq!(data.indices, vec![0, 1, 2, 0, 2, 3, 0, 1, 2, 0, 2, 3]);
+    }
+
+    #[test]
+    fn weld_needs_a_valid_mesh() {
+        let mut data = shapes::quad(1.0, 1.0);
+        data.indices[5] = 4;
+        assert!(matches!(
+            weld_vertices(&mut data),
+            Err(MeshError::IndexOutOfRange { index: 4, vertex_count: 4 })
+        ));
+        let mut data = shapes::quad(1.0, 1.0);
+        data.normals.pop();
+        assert!(weld_vertices(&mut data).is_err());
+        assert!(optimize(&mut data).is_err());
+    }
+
+    #[test]
+    fn vertex_cache_order_keeps_triangles_and_cuts_misses() {
+        let meshes = [
+            shapes::plane(4.0, 4.0, 24, 24),
+            shapes::uv_sphere(1.0, 32, 16),
+            shapes::torus(2.0, 0.5, 24, 12),
+        ];
+        for data in &meshes {
+            // a poor starting order, triangles taken with a large stride
+            let triangle_count = data.triangle_count();
+            let mut scrambled = data.clone();
+            scrambled.indices = (0..triangle_count)
+                .map(|triangle| triangle * 7 % triangle_count)
+                .flat_map(|triangle| data.indices[triangle * 3..triangle * 3 + 3].to_vec())
+                .collect();
+            assert_eq!(triangles(&scrambled), triangles(data));
+
+            let mut optimized = scrambled.clone();
+            optimize_vertex_cache(&mut optimized);
+            assert_eq!(triangles(&optimized), triangles(data));
+            let before = cache_misses(&scrambled.indices, STATS_CACHE_SIZE);
+            let after = cache_misses(&optimized.indices, STATS_CACHE_SIZE);
+            assert!(after <= before, "{} misses before, {} after", before, after);
+            assert!(after <= cache_misses(&data.indices, STATS_CACHE_SIZE));
+        }
+    }
+
+    #[test]
+    fn vertex_fetch_order_keeps_triangles() {
+        let mut data = shapes::uv_sphere(1.0, 16, 8);
+        data.indices.reverse();
+        // a vertex no triangle uses
+        data.duplicate_vertex(0);
+        let before = triangles(&data);
+
+        optimize_vertex_fetch(&mut data);
+        assert_eq!(triangles(&data), before);
+        assert_eq!(data.vertex_count(), shapes::uv_sphere(1.0, 16, 8).vertex_count());
+        // vertices come in the order they're first used
+        let mut next = 0;
+        for &index in &data.indices {
+            assert!(index <= next);
+            if index == next {
+                next += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn stats() {
+        let mut data = unweld(&shapes::cube(1.0));
+        let report = optimize(&mut data).unwrap();
+        assert_eq!(report.before.vertex_count, 36);
+        assert_eq!(report.before.acmr, 3.0);
+        assert_eq!(report.after.vertex_count, 24);
+        assert_eq!(report.after.triangle_count, 12);
+        assert_eq!(report.after.acmr, 2.0);
+        assert_eq!(report.after.atvr, 1.0);
+    }
+}
@@ -1,2 +1,31 @@
 pub mod shader;
-pub mod input;
\ No newline at end of file
+pub mod input;
+pub mod input_script;
+pub mod instance_buffer;
+pub mod camera;
+pub mod camera_path;
+pub mod bookmarks;
+pub mod bounds;
+pub mod clock;
+pub mod debug_lines;
+pub mod frame_limiter;
+pub mod frame_stats;
+pub mod frustum;
+pub mod gltf;
+pub mod interpolation;
+pub mod lod;
+pub mod mesh;
+pub mod mesh_data;
+pub mod mesh_file;
+pub mod mesh_optimize;
+pub mod obj;
+pub mod ply;
+pub mod render_target;
+pub mod screen_quad;
+pub mod settings;
+pub mod shapes;
+pub mod stl;
+pub mod stream_buffer;
+pub mod tangent_space;
+pub mod texture;
+pub mod transform;
\ No newline at end of file
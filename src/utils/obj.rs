@@ -0,0 +1,742 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use cgmath::prelude::*;
+use cgmath::{vec2, vec3, vec4, Point3, Vector2, Vector3, Vector4};
+
+use utils::mesh_data::MeshData;
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io(String),
+    Parse { line: usize, message: String },
+    /// error in a material library referenced by the model
+    Material { file: String, line: usize, message: String },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ObjError::Io(message) => write!(f, "{}", message),
+            ObjError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+            ObjError::Material { file, line, message } => write!(f, "{} line {}: {}", file, line, message),
+        }
+    }
+}
+
+impl Error for ObjError {}
+
+/// material from an MTL file, texture paths are relative to the MTL file unless loaded with `load_obj`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Material {
+    pub name: String,
+    pub ambient: Vector3<f32>,
+    pub diffuse: Vector3<f32>,
+    pub specular: Vector3<f32>,
+    pub shininess: f32,
+    /// opacity, 1 is opaque
+    pub dissolve: f32,
+    pub diffuse_texture: Option<PathBuf>,
+    pub specular_texture: Option<PathBuf>,
+    pub normal_texture: Option<PathBuf>,
+}
+
+impl Material {
+    pub fn new(name: &str) -> Self {
+        Material {
+            name: name.to_string(),
+            ambient: vec3(0.0, 0.0, 0.0),
+            diffuse: vec3(0.8, 0.8, 0.8),
+            specular: vec3(0.0, 0.0, 0.0),
+            shininess: 0.0,
+            dissolve: 1.0,
+            diffuse_texture: None,
+            specular_texture: None,
+            normal_texture: None,
+        }
+    }
+}
+
+/// the faces of one object/group drawn with a single material
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjMesh {
+    /// name of the `o` statement the faces belong to
+    pub object: Option<String>,
+    /// name of the `g` statement the faces belong to
+    pub group: Option<String>,
+    /// index into `ObjModel::materials`
+    pub material: Option<usize>,
+    pub data: MeshData,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ObjModel {
+    pub meshes: Vec<ObjMesh>,
+    pub materials: Vec<Material>,
+}
+
+/// load an OBJ file and the material libraries it references, texture paths are
+/// resolved relative to the directory of their material library
+pub fn load_obj(path: &Path) -> Result<ObjModel, ObjError> {
+    let contents = read_file(path)?;
+    let directory = path.parent().unwrap_or_else(|| Path::new("")).to_path_buf();
+    parse_obj(&contents, &mut |library| {
+        let library_path = directory.join(library);
+        let contents = read_file(&library_path)?;
+        let mut materials = parse_mtl(&contents).map_err(|e| match e {
+            ObjError::Parse { line, message } => ObjError::Material {
+                file: library_path.display().to_string(),
+                line,
+                message,
+            },
+            e => e,
+        })?;
+        let library_directory = library_path.parent().unwrap_or_else(|| Path::new(""));
+        for material in materials.iter_mut() {
+            for texture in [
+                &mut material.diffuse_texture,
+                &mut material.specular_texture,
+                &mut material.normal_texture,
+            ] {
+                if let Some(ref mut texture) = *texture {
+                    *texture = library_directory.join(&*texture);
+                }
+            }
+        }
+        Ok(materials)
+    })
+}
+
+fn read_file(path: &Path) -> Result<String, ObjError> {
+    let mut file = File::open(path).map_err(|e| ObjError::Io(format!("Failed to open {}: {}", path.display(), e)))?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)
+        .map_err(|e| ObjError::Io(format!("Failed to read {}: {}", path.display(), e)))?;
+    Ok(contents)
+}
+
+/// Parse the contents of an OBJ file.
+///
+/// Supports `v` (with optional vertex colors), `vt`, `vn`, `f` with any of the
+/// `v`, `v/vt`, `v//vn` and `v/vt/vn` forms and negative indices, `o`, `g`, `mtllib` and `usemtl`.
+/// Polygons are triangulated, other statements are ignored. `load_materials` is called with
+/// every material library named by `mtllib`.
+pub fn parse_obj(
+    contents: &str,
+    load_materials: &mut dyn FnMut(&str) -> Result<Vec<Material>, ObjError>,
+) -> Result<ObjModel, ObjError> {
+    let mut parser = ObjParser::default();
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        parser
+            .parse_line(line, load_materials)
+            .map_err(|message| match message {
+                LineError::Message(message) => ObjError::Parse {
+                    line: index + 1,
+                    message,
+                },
+                LineError::Materials(e) => e,
+            })?;
+    }
+    parser.finish_mesh();
+    Ok(ObjModel {
+        meshes: parser.meshes,
+        materials: parser.materials,
+    })
+}
+
+enum LineError {
+    Message(String),
+    Materials(ObjError),
+}
+
+impl From<String> for LineError {
+    fn from(message: String) -> Self {
+        LineError::Message(message)
+    }
+}
+
+// a face corner, indices into the position, texture coord and normal lists
+type Corner = (usize, Option<usize>, Option<usize>);
+
+#[derive(Default)]
+struct ObjParser {
+    positions: Vec<Point3<f32>>,
+    colors: Vec<Option<Vector3<f32>>>,
+    tex_coords: Vec<Vector2<f32>>,
+    normals: Vec<Vector3<f32>>,
+    materials: Vec<Material>,
+    meshes: Vec<ObjMesh>,
+
+    // the mesh being built
+    object: Option<String>,
+    group: Option<String>,
+    material: Option<usize>,
+    corners: Vec<Corner>,
+    corner_indices: HashMap<Corner, u32>,
+    indices: Vec<u32>,
+}
+
+impl ObjParser {
+    fn parse_line(
+        &mut self,
+        line: &str,
+        load_materials: &mut dyn FnMut(&str) -> Result<Vec<Material>, ObjError>,
+    ) -> Result<(), LineError> {
+        let mut tokens = line.split_whitespace();
+        let keyword = tokens.next().unwrap_or("");
+        let arguments: Vec<&str> = tokens.collect();
+        match keyword {
+            "v" => {
+                let values = numbers(&arguments, "v")?;
+                match values.len() {
+                    3 | 4 => self.colors.push(None),
+                    6 => self.colors.push(Some(vec3(values[3], values[4], values[5]))),
+                    n => return Err(format!("a vertex needs 3, 4 or 6 numbers, got {}", n).into()),
+                }
+                self.positions.push(Point3::new(values[0], values[1], values[2]));
+            }
+            "vt" => {
+                let values = numbers(&arguments, "vt")?;
+                match values.len() {
+                    1 => self.tex_coords.push(vec2(values[0], 0.0)),
+                    2 | 3 => self.tex_coords.push(vec2(values[0], values[1])),
+                    n => return Err(format!("a texture coord needs 1 to 3 numbers, got {}", n).into()),
+                }
+            }
+            "vn" => {
+                let values = numbers(&arguments, "vn")?;
+                if values.len() != 3 {
+                    return Err(format!("a normal needs 3 numbers, got {}", values.len()).into());
+                }
+                self.normals.push(vec3(values[0], values[1], values[2]));
+            }
+            "f" => {
+                if arguments.len() < 3 {
+                    return Err(format!("a face needs at least 3 vertices, got {}", arguments.len()).into());
+                }
+                let corners = arguments
+                    .iter()
+                    .map(|argument| self.parse_corner(argument))
+                    .collect::<Result<Vec<Corner>, String>>()?;
+                self.add_face(&corners);
+            }
+            "o" => {
+                self.finish_mesh();
+                self.object = Some(arguments.join(" "));
+                self.group = None;
+            }
+            "g" => {
+                self.finish_mesh();
+                self.group = if arguments.is_empty() {
+                    None
+                } else {
+                    Some(arguments.join(" "))
+                };
+            }
+            "usemtl" => {
+                let name = arguments.join(" ");
+                let material = self
+                    .materials
+                    .iter()
+                    .position(|material| material.name == name)
+                    .ok_or_else(|| format!("unknown material '{}'", name))?;
+                if self.material != Some(material) {
+                    self.finish_mesh();
+                    self.material = Some(material);
+                }
+            }
+            "mtllib" => {
+                if arguments.is_empty() {
+                    return Err("mtllib needs a file name".to_string().into());
+                }
+                for library in arguments {
+                    let materials = load_materials(library).map_err(LineError::Materials)?;
+                    self.materials.extend(materials);
+                }
+            }
+            // smoothing groups, lines, points, curves and the like
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// `v`, `v/vt`, `v//vn` or `v/vt/vn`
+    fn parse_corner(&self, argument: &str) -> Result<Corner, String> {
+        let mut parts = argument.split('/');
+        let position = resolve_index(parts.next().unwrap_or(""), self.positions.len(), "vertex")?;
+        let tex_coord = match parts.next() {
+            Some("") | None => None,
+            Some(index) => Some(resolve_index(index, self.tex_coords.len(), "texture coord")?),
+        };
+        let normal = match parts.next() {
+            Some("") | None => None,
+            Some(index) => Some(resolve_index(index, self.normals.len(), "normal")?),
+        };
+        if parts.next().is_some() {
+            return Err(format!("'{}' is not a face vertex like v/vt/vn", argument));
+        }
+        Ok((position, tex_coord, normal))
+    }
+
+    fn add_face(&mut self, corners: &[Corner]) {
+        let points: Vec<Point3<f32>> = corners.iter().map(|corner| self.positions[corner.0]).collect();
+        let mut face_indices = Vec::with_capacity(corners.len());
+        for corner in corners {
+            let next_index = self.corners.len() as u32;
+            let index = *self.corner_indices.entry(*corner).or_insert(next_index);
+            if index == next_index {
+                self.corners.push(*corner);
+            }
+            face_indices.push(index);
+        }
+        for triangle in triangulate(&points) {
+            self.indices.extend(triangle.iter().map(|&i| face_indices[i]));
+        }
+    }
+
+    /// turn the faces parsed since the last object, group or material change into a mesh
+    fn finish_mesh(&mut self) {
+        if !self.indices.is_empty() {
+            let corners = &self.corners;
+            let mut data = MeshData {
+                positions: corners.iter().map(|corner| self.positions[corner.0]).collect(),
+                indices: self.indices.clone(),
+                ..MeshData::default()
+            };
+            // streams only some corners have are filled up with zeros
+            if corners.iter().any(|corner| corner.1.is_some()) {
+                data.tex_coords = corners
+                    .iter()
+                    .map(|corner| corner.1.map_or(Vector2::zero(), |i| self.tex_coords[i]))
+                    .collect();
+            }
+            if corners.iter().any(|corner| corner.2.is_some()) {
+                data.normals = corners
+                    .iter()
+                    .map(|corner| corner.2.map_or(Vector3::zero(), |i| self.normals[i]))
+                    .collect();
+            }
+            if corners.iter().any(|corner| self.colors[corner.0].is_some()) {
+                data.colors = corners
+                    .iter()
+                    .map(|corner| {
+                        let color = self.colors[corner.0].unwrap_or_else(|| vec3(1.0, 1.0, 1.0));
+                        vec4(color.x, color.y, color.z, 1.0)
+                    }).collect::<Vec<Vector4<f32>>>();
+            }
+            self.meshes.push(ObjMesh {
+                object: self.object.clone(),
+                group: self.group.clone(),
+                material: self.material,
+                data,
+            });
+        }
+        self.corners.clear();
+        self.corner_indices.clear();
+        self.indices.clear();
+    }
+}
+
+fn numbers(arguments: &[&str], keyword: &str) -> Result<Vec<f32>, String> {
+    arguments
+        .iter()
+        .map(|argument| match argument.parse::<f32>() {
+            Ok(value) if value.is_finite() => Ok(value),
+            _ => Err(format!("'{}' in {} is not a number", argument, keyword)),
+        }).collect()
+}
+
+/// 1 based index, negative indices count back from the last element
+fn resolve_index(token: &str, count: usize, kind: &str) -> Result<usize, String> {
+    let index = token
+        .parse::<i64>()
+        .map_err(|_| format!("'{}' is not a {} index", token, kind))?;
+    let resolved = if index > 0 {
+        index - 1
+    } else if index < 0 {
+        count as i64 + index
+    } else {
+        return Err(format!("{} indices start at 1, got 0", kind));
+    };
+    if resolved < 0 || resolved >= count as i64 {
+        return Err(format!("{} index {} is out of range, there are {} so far", kind, index, count));
+    }
+    Ok(resolved as usize)
+}
+
+/// Split a planar polygon into triangles by ear clipping, keeping its winding.
+/// Returns indices into `points`.
+pub fn triangulate(points: &[Point3<f32>]) -> Vec<[usize; 3]> {
+    let count = points.len();
+    if count < 3 {
+        return Vec::new();
+    }
+    if count == 3 {
+        return vec![[0, 1, 2]];
+    }
+
+    // polygon normal with Newell's method, robust for slightly non-planar polygons
+    let mut normal: Vector3<f32> = Vector3::zero();
+    for i in 0..count {
+        let (current, next) = (points[i], points[(i + 1) % count]);
+        normal.x += (current.y - next.y) * (current.z + next.z);
+        normal.y += (current.z - next.z) * (current.x + next.x);
+        normal.z += (current.x - next.x) * (current.y + next.y);
+    }
+    if normal.magnitude2() <= f32::EPSILON {
+        // degenerate polygon, any triangulation will do
+        return (1..count - 1).map(|i| [0, i, i + 1]).collect();
+    }
+
+    // project onto the plane the normal is most aligned with, flipping the axes so
+    // the polygon winds counter-clockwise in 2d
+    let (u_axis, v_axis) = if normal.x.abs() >= normal.y.abs() && normal.x.abs() >= normal.z.abs() {
+        if normal.x > 0.0 { (1, 2) } else { (2, 1) }
+    } else if normal.y.abs() >= normal.z.abs() {
+        if normal.y > 0.0 { (2, 0) } else { (0, 2) }
+    } else if normal.z > 0.0 {
+        (0, 1)
+    } else {
+        (1, 0)
+    };
+    let projected: Vec<Vector2<f32>> = points.iter().map(|p| vec2(p[u_axis], p[v_axis])).collect();
+    let cross = |a: Vector2<f32>, b: Vector2<f32>, c: Vector2<f32>| (b - a).perp_dot(c - a);
+
+    let mut remaining: Vec<usize> = (0..count).collect();
+    let mut triangles = Vec::with_capacity(count - 2);
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let is_ear = |i: usize| {
+            let (a, b, c) = (remaining[(i + n - 1) % n], remaining[i], remaining[(i + 1) % n]);
+            let (pa, pb, pc) = (projected[a], projected[b], projected[c]);
+            if cross(pa, pb, pc) <= 0.0 {
+                return false;
+            }
+            // no other vertex may lie inside the ear
+            remaining.iter().all(|&other| {
+                if other == a || other == b || other == c {
+                    return true;
+                }
+                let p = projected[other];
+                !(cross(pa, pb, p) >= 0.0 && cross(pb, pc, p) >= 0.0 && cross(pc, pa, p) >= 0.0)
+            })
+        };
+        // if rounding leaves no proper ear, clip the first vertex anyway instead of looping forever
+        let ear = (0..n).find(|&i| is_ear(i)).unwrap_or(0);
+        triangles.push([remaining[(ear + n - 1) % n], remaining[ear], remaining[(ear + 1) % n]]);
+        remaining.remove(ear);
+    }
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+    triangles
+}
+
+/// Parse the contents of an MTL file.
+///
+/// Supports `newmtl`, `Ka`, `Kd`, `Ks`, `Ns`, `d`, `Tr`, `map_Kd`, `map_Ks` and
+/// `map_Bump`/`bump`/`norm`, other statements are ignored.
+pub fn parse_mtl(contents: &str) -> Result<Vec<Material>, ObjError> {
+    let mut materials: Vec<Material> = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        parse_mtl_line(line, &mut materials).map_err(|message| ObjError::Parse {
+            line: index + 1,
+            message,
+        })?;
+    }
+    Ok(materials)
+}
+
+fn parse_mtl_line(line: &str, materials: &mut Vec<Material>) -> Result<(), String> {
+    let mut tokens = line.split_whitespace();
+    let keyword = tokens.next().unwrap_or("");
+    let arguments: Vec<&str> = tokens.collect();
+    if keyword == "newmtl" {
+        if arguments.is_empty() {
+            return Err("newmtl needs a material name".to_string());
+        }
+        materials.push(Material::new(&arguments.join(" ")));
+        return Ok(());
+    }
+
+    let known = [
+        "Ka", "Kd", "Ks", "Ns", "d", "Tr", "map_Kd", "map_Ks", "map_Bump", "map_bump", "bump", "norm",
+    ];
+    if !known.contains(&keyword) {
+        return Ok(());
+    }
+    let material = materials
+        .last_mut()
+        .ok_or_else(|| format!("{} before the first newmtl", keyword))?;
+    match keyword {
+        "Ka" => material.ambient = color(&arguments, keyword)?,
+        "Kd" => material.diffuse = color(&arguments, keyword)?,
+        "Ks" => material.specular = color(&arguments, keyword)?,
+        "Ns" => material.shininess = single_number(&arguments, keyword)?,
+        "d" => material.dissolve = single_number(&arguments, keyword)?,
+        "Tr" => material.dissolve = 1.0 - single_number(&arguments, keyword)?,
+        "map_Kd" => material.diffuse_texture = Some(texture_path(&arguments, keyword)?),
+        "map_Ks" => material.specular_texture = Some(texture_path(&arguments, keyword)?),
+        _ => material.normal_texture = Some(texture_path(&arguments, keyword)?),
+    }
+    Ok(())
+}
+
+/// `r g b`, or a single value for all three
+fn color(arguments: &[&str], keyword: &str) -> Result<Vector3<f32>, String> {
+    let values = numbers(arguments, keyword)?;
+    match values.len() {
+        1 => Ok(vec3(values[0], values[0], values[0])),
+        3 => Ok(vec3(values[0], values[1], values[2])),
+        n => Err(format!("{} needs 1 or 3 numbers, got {}", keyword, n)),
+    }
+}
+
+fn single_number(arguments: &[&str], keyword: &str) -> Result<f32, String> {
+    let values = numbers(arguments, keyword)?;
+    if values.len() != 1 {
+        return Err(format!("{} needs 1 number, got {}", keyword, values.len()));
+    }
+    Ok(values[0])
+}
+
+/// the file name at the end of a texture statement, skipping options like `-bm 1.0`
+fn texture_path(arguments: &[&str], keyword: &str) -> Result<PathBuf, String> {
+    arguments
+        .last()
+        .map(PathBuf::from)
+        .ok_or_else(|| format!("{} needs a file name", keyword))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MTL: &str = "\
+# two materials
+newmtl red
+Kd 1 0 0
+Ka 0.1
+Ks 0.5 0.5 0.5
+Ns 32
+d 0.5
+map_Kd textures/red.png
+map_Bump -bm 0.5 textures/red_normal.png
+
+newmtl glass
+Tr 0.75
+illum 2
+map_Ks glass.png
+";
+
+    fn parse(contents: &str) -> Result<ObjModel, ObjError> {
+        parse_obj(contents, &mut |library| {
+            assert_eq!(library, "materials.mtl");
+            parse_mtl(MTL)
+        })
+    }
+
+    /// area of a triangle along `normal`, negative if it winds the other way
+    fn signed_area(points: &[Point3<f32>], triangle: [usize; 3], normal: Vector3<f32>) -> f32 {
+        let [a, b, c] = triangle;
+        (points[b] - points[a]).cross(points[c] - points[a]).dot(normal) / 2.0
+    }
+
+    #[test]
+    fn indices() {
+        assert_eq!(resolve_index("1", 3, "vertex"), Ok(0));
+        assert_eq!(resolve_index("3", 3, "vertex"), Ok(2));
+        assert_eq!(resolve_index("-1", 3, "vertex"), Ok(2));
+        assert_eq!(resolve_index("-3", 3, "vertex"), Ok(0));
+        assert!(resolve_index("0", 3, "vertex").is_err());
+        assert!(resolve_index("4", 3, "vertex").is_err());
+        assert!(resolve_index("-4", 3, "vertex").is_err());
+        assert!(resolve_index("one", 3, "vertex").is_err());
+
+        // relative indices count back from the elements read so far
+        let model = parse(
+            "v 0 0 0\nv 1 0 0\nv 1 1 0\nvt 0 0\nvt 1 0\nvt 1 1\nf -3/-3 -2/-2 -1/-1\n\
+             v 0 1 0\nvt 0 1\nf 1/1 -2/-2 -1/-1",
+        ).unwrap();
+        let data = &model.meshes[0].data;
+        assert_eq!(data.indices, vec![0, 1, 2, 0, 2, 3]);
+        assert_eq!(data.positions[3], Point3::new(0.0, 1.0, 0.0));
+        assert_eq!(data.tex_coords[3], vec2(0.0, 1.0));
+        assert!(data.normals.is_empty());
+    }
+
+    #[test]
+    fn corners_are_shared() {
+        let model = parse("v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvn 0 0 1\nf 1//1 2//1 3//1 4//1\nf 1//1 3//1 2//1");
+        let model = model.unwrap();
+        let data = &model.meshes[0].data;
+        assert_eq!(data.vertex_count(), 4);
+        assert_eq!(data.triangle_count(), 3);
+        assert_eq!(data.normals, vec![vec3(0.0, 0.0, 1.0); 4]);
+    }
+
+    #[test]
+    fn concave_quad() {
+        // an arrowhead pointing along +x, the last vertex is the reflex one
+        let points = [
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(2.0, 1.0, 0.0),
+            Point3::new(0.0, 2.0, 0.0),
+            Point3::new(0.5, 1.0, 0.0),
+        ];
+        let triangles = triangulate(&points);
+        assert_eq!(triangles.len(), 2);
+        for &triangle in &triangles {
+            assert!(signed_area(&points, triangle, Vector3::unit_z()) > 0.0, "{:?}", triangle);
+            // the reflex vertex can't be cut off
+            assert!(triangle.contains(&3));
+        }
+        let area: f32 = triangles.iter().map(|&t| signed_area(&points, t, Vector3::unit_z())).sum();
+        assert!((area - 1.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn pentagons() {
+        // convex in the xy plane
+        let convex: Vec<Point3<f32>> = (0..5)
+            .map(|i| {
+                let angle = i as f32 * std::f32::consts::PI * 0.4;
+                Point3::new(angle.cos(), angle.sin(), 0.0)
+            }).collect();
+        // concave in the xz plane, facing +y
+        let concave = [
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, 2.0),
+            Point3::new(2.0, 0.0, 2.0),
+            Point3::new(1.0, 0.0, 1.0),
+            Point3::new(2.0, 0.0, 0.0),
+        ];
+        let expected_area = [2.5 * (std::f32::consts::PI * 0.4).sin(), 3.0];
+        for (points, (&normal, &expected_area)) in [&convex[..], &concave[..]]
+            .iter()
+            .zip([Vector3::unit_z(), Vector3::unit_y()].iter().zip(expected_area.iter()))
+        {
+            let triangles = triangulate(points);
+            assert_eq!(triangles.len(), 3);
+            for &triangle in &triangles {
+                assert!(signed_area(points, triangle, normal) > 0.0, "{:?}", triangle);
+            }
+            let area: f32 = triangles.iter().map(|&t| signed_area(points, t, normal)).sum();
+            assert!((area - expected_area).abs() < 1e-5, "{} != {}", area, expected_area);
+        }
+    }
+
+    #[test]
+    fn objects_groups_and_materials() {
+        let model = parse(
+            "mtllib materials.mtl\n\
+             v 0 0 0\nv 1 0 0\nv 0 1 0\n\
+             f 1 2 3\n\
+             o box\n\
+             f 1 2 3\n\
+             g lid\n\
+             f 1 2 3\n\
+             usemtl red\n\
+             f 1 2 3\n\
+             usemtl red\n\
+             f 3 2 1\n\
+             usemtl glass\n\
+             f 1 2 3\n\
+             o ball\n\
+             g\n\
+             f 1 2 3\n",
+        ).unwrap();
+        assert_eq!(model.materials.len(), 2);
+        let meshes: Vec<_> = model
+            .meshes
+            .iter()
+            .map(|mesh| {
+                (
+                    mesh.object.as_deref(),
+                    mesh.group.as_deref(),
+                    mesh.material,
+                    mesh.data.triangle_count(),
+                )
+            }).collect();
+        assert_eq!(
+            meshes,
+            vec![
+                (None, None, None, 1),
+                (Some("box"), None, None, 1),
+                (Some("box"), Some("lid"), None, 1),
+                (Some("box"), Some("lid"), Some(0), 2),
+                (Some("box"), Some("lid"), Some(1), 1),
+                (Some("ball"), None, Some(1), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn materials() {
+        let materials = parse_mtl(MTL).unwrap();
+        assert_eq!(materials.len(), 2);
+        let red = &materials[0];
+        assert_eq!(red.name, "red");
+        assert_eq!(red.diffuse, vec3(1.0, 0.0, 0.0));
+        assert_eq!(red.ambient, vec3(0.1, 0.1, 0.1));
+        assert_eq!(red.specular, vec3(0.5, 0.5, 0.5));
+        assert_eq!(red.shininess, 32.0);
+        assert_eq!(red.dissolve, 0.5);
+        assert_eq!(red.diffuse_texture, Some(PathBuf::from("textures/red.png")));
+        assert_eq!(red.normal_texture, Some(PathBuf::from("textures/red_normal.png")));
+        assert_eq!(red.specular_texture, None);
+        let glass = &materials[1];
+        assert_eq!(glass.dissolve, 0.25);
+        assert_eq!(glass.diffuse, Material::new("glass").diffuse);
+        assert_eq!(glass.specular_texture, Some(PathBuf::from("glass.png")));
+
+        for &(contents, line) in &[
+            ("Kd 1 1 1", 1),
+            ("newmtl a\nKd 1 1", 2),
+            ("newmtl a\n\n# shiny\nNs high", 4),
+            ("newmtl", 1),
+            ("newmtl a\nmap_Kd", 2),
+        ] {
+            match parse_mtl(contents) {
+                Err(ObjError::Parse { line: error_line, .. }) => assert_eq!(error_line, line, "{}", contents),
+                other => panic!("{}: {:?}", contents, other),
+            }
+        }
+    }
+
+    #[test]
+    fn errors_have_line_numbers() {
+        for &(contents, line) in &[
+            ("v 0 0 0\nv 1 0 0\n\n# face\nf 1 2 3", 5),
+            ("v 0 0\n", 1),
+            ("v 0 0 0\nvt 0 0\nf 1/1 1/2 1/1", 3),
+            ("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2", 4),
+            ("v 0 0 0\nf 1//1 1//1 1//1", 2),
+            ("v 0 0 0\nf 0 1 1", 2),
+            ("v 0 0 0\nf 1/1/1/1 1 1", 2),
+            ("v 0 0 0\nvn 0 nan 1", 2),
+            ("mtllib materials.mtl\nusemtl blue", 2),
+        ] {
+            match parse(contents) {
+                Err(ObjError::Parse { line: error_line, .. }) => assert_eq!(error_line, line, "{}", contents),
+                other => panic!("{}: {:?}", contents, other),
+            }
+        }
+        // errors from a material library are passed on as they are
+        let error = parse_obj("mtllib missing.mtl", &mut |_| Err(ObjError::Io("no such file".to_string())));
+        match error {
+            Err(ObjError::Io(message)) => assert_eq!(message, "no such file"),
+            other => panic!("{:?}", other),
+        }
+    }
+}
@@ -0,0 +1,710 @@
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use cgmath::{vec2, vec3, vec4, Point3};
+
+use utils::mesh_data::MeshData;
+use utils::obj::triangulate;
+
+#[derive(Debug)]
+pub enum PlyError {
+    Io(String),
+    /// error in the header or in the body of an ASCII file
+    Parse { line: usize, message: String },
+    /// error in the body of a binary file, or in the vertices and faces it describes
+    Data(String),
+}
+
+impl fmt::Display for PlyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlyError::Io(message) | PlyError::Data(message) => write!(f, "{}", message),
+            PlyError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for PlyError {}
+
+/// a vertex property `parse_ply` doesn't put into the mesh data, one value per vertex
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlyProperty {
+    pub name: String,
+    pub values: Vec<f32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlyModel {
+    pub data: MeshData,
+    /// the other scalar properties of the vertices, in the order of the header
+    pub properties: Vec<PlyProperty>,
+}
+
+pub fn load_ply(path: &Path) -> Result<PlyModel, PlyError> {
+    let mut file = File::open(path).map_err(|e| PlyError::Io(format!("Failed to open {}: {}", path.display(), e)))?;
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents)
+        .map_err(|e| PlyError::Io(format!("Failed to read {}: {}", path.display(), e)))?;
+    parse_ply(&contents)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ScalarType {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64,
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> Option<ScalarType> {
+        match name {
+            "char" | "int8" => Some(ScalarType::Int8),
+            "uchar" | "uint8" => Some(ScalarType::UInt8),
+            "short" | "int16" => Some(ScalarType::Int16),
+            "ushort" | "uint16" => Some(ScalarType::UInt16),
+            "int" | "int32" => Some(ScalarType::Int32),
+            "uint" | "uint32" => Some(ScalarType::UInt32),
+            "float" | "float32" => Some(ScalarType::Float32),
+            "double" | "float64" => Some(ScalarType::Float64),
+            _ => None,
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            ScalarType::Int8 | ScalarType::UInt8 => 1,
+            ScalarType::Int16 | ScalarType::UInt16 => 2,
+            ScalarType::Int32 | ScalarType::UInt32 | ScalarType::Float32 => 4,
+            ScalarType::Float64 => 8,
+        }
+    }
+
+    /// what integer colors are divided by to get into 0..1, `None` for floats which already are
+    fn color_scale(self) -> Option<f64> {
+        match self {
+            ScalarType::Int8 => Some(127.0),
+            ScalarType::UInt8 => Some(255.0),
+            ScalarType::Int16 => Some(32767.0),
+            ScalarType::UInt16 => Some(65535.0),
+            ScalarType::Int32 => Some(2147483647.0),
+            ScalarType::UInt32 => Some(4294967295.0),
+            ScalarType::Float32 | ScalarType::Float64 => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Property {
+    Scalar { name: String, ty: ScalarType },
+    List { name: String, count: ScalarType, item: ScalarType },
+}
+
+impl Property {
+    fn name(&self) -> &str {
+        match self {
+            Property::Scalar { name, .. } | Property::List { name, .. } => name,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+struct Header {
+    format: Format,
+    elements: Vec<Element>,
+    /// byte offset of the body
+    body: usize,
+    /// line the body starts on
+    body_line: usize,
+}
+
+/// Parse an ASCII, binary little endian or binary big endian PLY file.
+///
+/// The `vertex` element gives the positions from `x`, `y` and `z`, normals from `nx`, `ny` and
+/// `nz`, texture coords from `u`/`v`, `s`/`t` or `texture_u`/`texture_v` and colors from `red`,
+/// `green`, `blue` and `alpha`, integer colors scaled to 0..1. Every other scalar vertex property
+/// ends up in `PlyModel::properties`. Polygons of the `face` element's `vertex_indices` are
+/// triangulated, other elements are skipped.
+pub fn parse_ply(contents: &[u8]) -> Result<PlyModel, PlyError> {
+    let header = parse_header(contents)?;
+    let mut reader = match header.format {
+        Format::Ascii => {
+            let text = std::str::from_utf8(&contents[header.body..]).map_err(|_| PlyError::Parse {
+                line: header.body_line,
+                message: "ASCII body that isn't valid UTF-8".to_string(),
+            })?;
+            let body_line = header.body_line;
+            let tokens = text
+                .lines()
+                .enumerate()
+                .flat_map(|(index, line)| line.split_whitespace().map(move |token| (body_line + index, token)))
+                .collect();
+            Reader::Ascii { tokens, next: 0 }
+        }
+        format => Reader::Binary {
+            bytes: &contents[header.body..],
+            offset: 0,
+            big_endian: format == Format::BinaryBigEndian,
+        },
+    };
+
+    // scalar vertex properties by column, and the polygons of the faces
+    let mut columns: Vec<(String, ScalarType, Vec<f64>)> = Vec::new();
+    let mut polygons: Vec<Vec<u32>> = Vec::new();
+    for element in &header.elements {
+        // counts come from the file, check them against what's left of it before allocating for them
+        let min_size = element.properties.iter().map(|property| reader.min_size(property)).sum::<usize>();
+        if min_size == 0 {
+            continue;
+        }
+        if element.count.checked_mul(min_size).is_none_or(|size| size > reader.remaining()) {
+            return Err(PlyError::Data(format!(
+                "{} {} elements don't fit into the rest of the file",
+                element.count, element.name
+            )));
+        }
+        match element.name.as_str() {
+            "vertex" => {
+                columns = element
+                    .properties
+                    .iter()
+                    .filter_map(|property| match property {
+                        Property::Scalar { name, ty } => Some((name.clone(), *ty, Vec::with_capacity(element.count))),
+                        Property::List { .. } => None,
+                    }).collect();
+                for _ in 0..element.count {
+                    let mut column = 0;
+                    for property in &element.properties {
+                        match property {
+                            Property::Scalar { ty, .. } => {
+                                let value = reader.read(*ty)?;
+                                columns[column].2.push(value);
+                                column += 1;
+                            }
+                            Property::List { count, item, .. } => reader.skip_list(*count, *item)?,
+                        }
+                    }
+                }
+            }
+            "face" => {
+                for _ in 0..element.count {
+                    for property in &element.properties {
+                        match property {
+                            Property::List { name, count, item }
+                                if name == "vertex_indices" || name == "vertex_index" =>
+                            {
+                                let length = reader.read_length(*count)?;
+                                let mut polygon = Vec::with_capacity(length);
+                                for _ in 0..length {
+                                    let index = reader.read(*item)?;
+                                    if index < 0.0 || index.fract() != 0.0 || index > f64::from(u32::MAX) {
+                                        return Err(reader.error(format!("{} is not a vertex index", index)));
+                                    }
+                                    polygon.push(index as u32);
+                                }
+                                polygons.push(polygon);
+                            }
+                            Property::Scalar { ty, .. } => {
+                                reader.read(*ty)?;
+                            }
+                            Property::List { count, item, .. } => reader.skip_list(*count, *item)?,
+                        }
+                    }
+                }
+            }
+            _ => {
+                for _ in 0..element.count {
+                    for property in &element.properties {
+                        match property {
+                            Property::Scalar { ty, .. } => {
+                                reader.read(*ty)?;
+                            }
+                            Property::List { count, item, .. } => reader.skip_list(*count, *item)?,
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    build_model(columns, &polygons)
+}
+
+fn parse_header(contents: &[u8]) -> Result<Header, PlyError> {
+    let parse_error = |line: usize, message: &str| PlyError::Parse {
+        line,
+        message: message.to_string(),
+    };
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    let mut offset = 0;
+    let mut line_number = 0;
+    loop {
+        line_number += 1;
+        let end = match contents[offset..].iter().position(|&byte| byte == b'\n') {
+            Some(end) => offset + end,
+            None => return Err(parse_error(line_number, "header without end_header")),
+        };
+        let line = std::str::from_utf8(&contents[offset..end])
+            .map_err(|_| parse_error(line_number, "header that isn't valid UTF-8"))?;
+        offset = end + 1;
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+
+        if line_number == 1 {
+            if tokens != ["ply"] {
+                return Err(parse_error(line_number, "not a PLY file"));
+            }
+            continue;
+        }
+        match tokens.first().cloned().unwrap_or("") {
+            "" | "comment" | "obj_info" => (),
+            "format" => {
+                format = Some(match (tokens.get(1), tokens.get(2)) {
+                    (Some(&"ascii"), Some(&"1.0")) => Format::Ascii,
+                    (Some(&"binary_little_endian"), Some(&"1.0")) => Format::BinaryLittleEndian,
+                    (Some(&"binary_big_endian"), Some(&"1.0")) => Format::BinaryBigEndian,
+                    _ => return Err(parse_error(line_number, &format!("unsupported format '{}'", line.trim()))),
+                });
+            }
+            "element" => {
+                let count = match (tokens.get(1), tokens.get(2).and_then(|count| count.parse::<usize>().ok())) {
+                    (Some(_), Some(count)) if tokens.len() == 3 => count,
+                    _ => return Err(parse_error(line_number, "expected 'element <name> <count>'")),
+                };
+                elements.push(Element {
+                    name: tokens[1].to_string(),
+                    count,
+                    properties: Vec::new(),
+                });
+            }
+            "property" => {
+                let element = elements
+                    .last_mut()
+                    .ok_or_else(|| parse_error(line_number, "property before any element"))?;
+                let property = match tokens[1..] {
+                    ["list", count, item, name] => {
+                        let count = ScalarType::parse(count)
+                            .ok_or_else(|| parse_error(line_number, &format!("unknown type '{}'", count)))?;
+                        let item = ScalarType::parse(item)
+                            .ok_or_else(|| parse_error(line_number, &format!("unknown type '{}'", item)))?;
+                        if count == ScalarType::Float32 || count == ScalarType::Float64 {
+                            return Err(parse_error(line_number, "list lengths have to be integers"));
+                        }
+                        Property::List {
+                            name: name.to_string(),
+                            count,
+                            item,
+                        }
+                    }
+                    [ty, name] => Property::Scalar {
+                        name: name.to_string(),
+                        ty: ScalarType::parse(ty)
+                            .ok_or_else(|| parse_error(line_number, &format!("unknown type '{}'", ty)))?,
+                    },
+                    _ => return Err(parse_error(line_number, "expected 'property <type> <name>'")),
+                };
+                if element.properties.iter().any(|other| other.name() == property.name()) {
+                    return Err(parse_error(
+                        line_number,
+                        &format!("property '{}' given twice", property.name()),
+                    ));
+                }
+                element.properties.push(property);
+            }
+            "end_header" => break,
+            keyword => return Err(parse_error(line_number, &format!("unknown keyword '{}'", keyword))),
+        }
+    }
+    Ok(Header {
+        format: format.ok_or_else(|| parse_error(line_number, "header without format"))?,
+        elements,
+        body: offset,
+        body_line: line_number + 1,
+    })
+}
+
+/// the values of the body, whitespace separated or packed
+enum Reader<'a> {
+    Ascii { tokens: Vec<(usize, &'a str)>, next: usize },
+    Binary { bytes: &'a [u8], offset: usize, big_endian: bool },
+}
+
+impl<'a> Reader<'a> {
+    fn read(&mut self, ty: ScalarType) -> Result<f64, PlyError> {
+        match self {
+            Reader::Ascii { tokens, next } => {
+                let (line, token) = match tokens.get(*next) {
+                    Some(&token) => token,
+                    None => {
+                        return Err(PlyError::Parse {
+                            line: tokens.last().map_or(0, |&(line, _)| line),
+                            message: "file ends before all elements are read".to_string(),
+                        })
+                    }
+                };
+                *next += 1;
+                let value = token.parse::<f64>().map_err(|_| PlyError::Parse {
+                    line,
+                    message: format!("'{}' is not a number", token),
+                })?;
+                Ok(value)
+            }
+            Reader::Binary {
+                bytes,
+                offset,
+                big_endian,
+            } => {
+                let size = ty.size();
+                if *offset + size > bytes.len() {
+                    return Err(PlyError::Data(format!(
+                        "file ends {} bytes into the body, before all elements are read",
+                        bytes.len()
+                    )));
+                }
+                let value = read_scalar(&bytes[*offset..*offset + size], ty, *big_endian);
+                *offset += size;
+                Ok(value)
+            }
+        }
+    }
+
+    /// number of tokens or bytes left
+    fn remaining(&self) -> usize {
+        match self {
+            Reader::Ascii { tokens, next } => tokens.len().saturating_sub(*next),
+            Reader::Binary { bytes, offset, .. } => bytes.len().saturating_sub(*offset),
+        }
+    }
+
+    /// fewest tokens or bytes a value of `property` takes, lists can be empty
+    fn min_size(&self, property: &Property) -> usize {
+        match (self, property) {
+            (Reader::Ascii { .. }, _) => 1,
+            (Reader::Binary { .. }, Property::Scalar { ty, .. }) => ty.size(),
+            (Reader::Binary { .. }, Property::List { count, .. }) => count.size(),
+        }
+    }
+
+    /// length of a list, which has to fit into the rest of the file
+    fn read_length(&mut self, count: ScalarType) -> Result<usize, PlyError> {
+        let length = self.read(count)?;
+        if length < 0.0 || length.fract() != 0.0 {
+            return Err(self.error(format!("{} is not a list length", length)));
+        }
+        if length as usize > self.remaining() {
+            return Err(self.error(format!("list of {} items is longer than the rest of the file", length)));
+        }
+        Ok(length as usize)
+    }
+
+    fn skip_list(&mut self, count: ScalarType, item: ScalarType) -> Result<(), PlyError> {
+        let length = self.read_length(count)?;
+        match self {
+            Reader::Ascii { next, .. } => *next += length,
+            Reader::Binary { bytes, offset, .. } => {
+                *offset += length * item.size();
+                if *offset > bytes.len() {
+                    return Err(PlyError::Data("file ends inside a list, before all elements are read".to_string()));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// error about the value read last
+    fn error(&self, message: String) -> PlyError {
+        match self {
+            Reader::Ascii { tokens, next } => PlyError::Parse {
+                line: tokens.get(next.saturating_sub(1)).map_or(0, |&(line, _)| line),
+                message,
+            },
+            Reader::Binary { offset, .. } => PlyError::Data(format!("byte {} of the body: {}", offset, message)),
+        }
+    }
+}
+
+fn read_scalar(bytes: &[u8], ty: ScalarType, big_endian: bool) -> f64 {
+    let bits = if big_endian {
+        bytes.iter().fold(0u64, |bits, &byte| bits << 8 | u64::from(byte))
+    } else {
+        bytes.iter().rev().fold(0u64, |bits, &byte| bits << 8 | u64::from(byte))
+    };
+    match ty {
+        ScalarType::Int8 => f64::from(bits as u8 as i8),
+        ScalarType::UInt8 => f64::from(bits as u8),
+        ScalarType::Int16 => f64::from(bits as u16 as i16),
+        ScalarType::UInt16 => f64::from(bits as u16),
+        ScalarType::Int32 => f64::from(bits as u32 as i32),
+        ScalarType::UInt32 => f64::from(bits as u32),
+        ScalarType::Float32 => f64::from(f32::from_bits(bits as u32)),
+        ScalarType::Float64 => f64::from_bits(bits),
+    }
+}
+
+/// put the known vertex properties into mesh data, and the faces into triangles
+fn build_model(mut columns: Vec<(String, ScalarType, Vec<f64>)>, polygons: &[Vec<u32>]) -> Result<PlyModel, PlyError> {
+    // takes the first of the names found, they're alternative spellings of the same property
+    let mut take = |names: &[&str]| -> Option<(ScalarType, Vec<f64>)> {
+        let position = columns.iter().position(|column| names.contains(&column.0.as_str()))?;
+        let (_, ty, values) = columns.remove(position);
+        Some((ty, values))
+    };
+
+    let mut data = MeshData::default();
+    match (take(&["x"]), take(&["y"]), take(&["z"])) {
+        (Some((_, x)), Some((_, y)), Some((_, z))) => {
+            data.positions = (0..x.len())
+                .map(|i| Point3::new(x[i] as f32, y[i] as f32, z[i] as f32))
+                .collect();
+        }
+        _ => return Err(PlyError::Data("vertices need x, y and z".to_string())),
+    }
+    if let (Some((_, x)), Some((_, y)), Some((_, z))) = (take(&["nx"]), take(&["ny"]), take(&["nz"])) {
+        data.normals = (0..x.len()).map(|i| vec3(x[i] as f32, y[i] as f32, z[i] as f32)).collect();
+    }
+    if let (Some((_, u)), Some((_, v))) = (
+        take(&["u", "s", "texture_u", "texture_s"]),
+        take(&["v", "t", "texture_v", "texture_t"]),
+    ) {
+        data.tex_coords = (0..u.len()).map(|i| vec2(u[i] as f32, v[i] as f32)).collect();
+    }
+    let red = take(&["red", "r", "diffuse_red"]);
+    let green = take(&["green", "g", "diffuse_green"]);
+    let blue = take(&["blue", "b", "diffuse_blue"]);
+    let alpha = take(&["alpha", "a"]);
+    if let (Some(red), Some(green), Some(blue)) = (red, green, blue) {
+        let channel = |channel: &(ScalarType, Vec<f64>), i: usize| {
+            let (ty, ref values) = *channel;
+            (values[i] / ty.color_scale().unwrap_or(1.0)) as f32
+        };
+        data.colors = (0..red.1.len())
+            .map(|i| {
+                let a = alpha.as_ref().map_or(1.0, |alpha| channel(alpha, i));
+                vec4(channel(&red, i), channel(&green, i), channel(&blue, i), a)
+            }).collect();
+    }
+
+    let vertex_count = data.vertex_count() as u32;
+    for polygon in polygons {
+        if let Some(&index) = polygon.iter().find(|&&index| index >= vertex_count) {
+            return Err(PlyError::Data(format!(
+                "face refers to vertex {} of {}",
+                index, vertex_count
+            )));
+        }
+        match polygon.len() {
+            0..=2 => (),
+            3 => data.indices.extend_from_slice(polygon),
+            _ => {
+                let points: Vec<Point3<f32>> = polygon.iter().map(|&index| data.positions[index as usize]).collect();
+                for triangle in triangulate(&points) {
+                    data.indices.extend(triangle.iter().map(|&corner| polygon[corner]));
+                }
+            }
+        }
+    }
+
+    Ok(PlyModel {
+        data,
+        properties: columns
+            .into_iter()
+            .map(|(name, _, values)| PlyProperty {
+                name,
+                values: values.into_iter().map(|value| value as f32).collect(),
+            }).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "element vertex 3
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+property float confidence
+element face 2
+property list uchar int vertex_indices
+end_header
+";
+
+    /// a triangle and a quad over three vertices, the quad's fourth corner is the first again
+    /// to keep the vertex count small, with colors and a property `parse_ply` doesn't know
+    const VERTICES: [([f32; 3], [u8; 3], f32); 3] = [
+        ([0.0, 0.0, 0.0], [255, 0, 0], 0.5),
+        ([1.0, 0.0, 0.0], [0, 255, 0], 0.25),
+        ([0.0, 1.0, 0.0], [0, 0, 51], 1.0),
+    ];
+    const FACES: [&[i32]; 2] = [&[0, 1, 2], &[2, 1, 0]];
+
+    fn ascii() -> Vec<u8> {
+        let mut text = format!("ply\nformat ascii 1.0\ncomment made by hand\n{}", HEADER);
+        for &(position, color, confidence) in VERTICES.iter() {
+            text += &format!(
+                "{} {} {} {} {} {} {}\n",
+                position[0], position[1], position[2], color[0], color[1], color[2], confidence
+            );
+        }
+        for face in FACES.iter() {
+            text += &format!("{} {}\n", face.len(), face.iter().map(|i| i.to_string()).collect::<Vec<_>>().join(" "));
+        }
+        text.into_bytes()
+    }
+
+    fn binary(big_endian: bool) -> Vec<u8> {
+        let format = if big_endian { "binary_big_endian" } else { "binary_little_endian" };
+        let mut bytes = format!("ply\nformat {} 1.0\n{}", format, HEADER).into_bytes();
+        let float = |value: f32| if big_endian { value.to_be_bytes() } else { value.to_le_bytes() };
+        let int = |value: i32| if big_endian { value.to_be_bytes() } else { value.to_le_bytes() };
+        for &(position, color, confidence) in VERTICES.iter() {
+            for &component in position.iter() {
+                bytes.extend_from_slice(&float(component));
+            }
+            bytes.extend_from_slice(&color);
+            bytes.extend_from_slice(&float(confidence));
+        }
+        for face in FACES.iter() {
+            bytes.push(face.len() as u8);
+            for &index in face.iter() {
+                bytes.extend_from_slice(&int(index));
+            }
+        }
+        bytes
+    }
+
+    fn is_data_error(result: Result<PlyModel, PlyError>) -> bool {
+        match result {
+            Err(PlyError::Data(_)) => true,
+            other => panic!("expected a data error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn ascii_vertices_colors_and_properties() {
+        let model = parse_ply(&ascii()).unwrap();
+        let data = &model.data;
+        assert_eq!(data.positions, vec![Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0), Point3::new(0.0, 1.0, 0.0)]);
+        assert_eq!(data.indices, vec![0, 1, 2, 2, 1, 0]);
+        // uchar colors scaled to 0..1, opaque without alpha
+        assert_eq!(data.colors, vec![vec4(1.0, 0.0, 0.0, 1.0), vec4(0.0, 1.0, 0.0, 1.0), vec4(0.0, 0.0, 0.2, 1.0)]);
+        assert!(data.normals.is_empty() && data.tex_coords.is_empty());
+        assert_eq!(
+            model.properties,
+            vec![PlyProperty {
+                name: "confidence".to_string(),
+                values: vec![0.5, 0.25, 1.0],
+            }]
+        );
+    }
+
+    #[test]
+    fn binary_matches_ascii() {
+        let expected = parse_ply(&ascii()).unwrap();
+        assert_eq!(parse_ply(&binary(false)).unwrap(), expected);
+        assert_eq!(parse_ply(&binary(true)).unwrap(), expected);
+    }
+
+    #[test]
+    fn normals_tex_coords_and_polygons() {
+        let text = "ply
+format ascii 1.0
+element vertex 4
+property double x
+property double y
+property double z
+property float nx
+property float ny
+property float nz
+property float s
+property float t
+element edge 1
+property int vertex1
+property int vertex2
+element face 1
+property uchar flags
+property list uchar uint vertex_indices
+end_header
+0 0 0 0 0 1 0 0
+1 0 0 0 0 1 1 0
+1 1 0 0 0 1 1 1
+0 1 0 0 0 1 0 1
+0 1
+7 4 0 1 2 3
+";
+        let data = parse_ply(text.as_bytes()).unwrap().data;
+        assert_eq!(data.normals, vec![vec3(0.0, 0.0, 1.0); 4]);
+        assert_eq!(data.tex_coords[2], vec2(1.0, 1.0));
+        // the quad is split into two triangles
+        assert_eq!(data.indices.len(), 6);
+        data.validate().unwrap();
+    }
+
+    #[test]
+    fn truncated_bodies() {
+        let ascii = ascii();
+        // cut off in the middle of the faces
+        match parse_ply(&ascii[..ascii.len() - 4]) {
+            Err(PlyError::Parse { .. }) => (),
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+        for &big_endian in [false, true].iter() {
+            let binary = binary(big_endian);
+            for &cut in [1, 5, 20].iter() {
+                assert!(is_data_error(parse_ply(&binary[..binary.len() - cut])));
+            }
+        }
+        assert!(parse_ply(b"ply\nformat ascii 1.0\nelement vertex 1\n").is_err());
+    }
+
+    #[test]
+    fn faces_out_of_range() {
+        let text = String::from_utf8(ascii()).unwrap().replace("3 2 1 0", "3 2 1 3");
+        assert!(is_data_error(parse_ply(text.as_bytes())));
+        let text = String::from_utf8(ascii()).unwrap().replace("3 2 1 0", "3 2 1 -1");
+        assert!(parse_ply(text.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn impossible_counts() {
+        let huge = "ply
+format binary_little_endian 1.0
+element vertex 18446744073709551615
+property float x
+property float y
+property float z
+end_header
+";
+        assert!(is_data_error(parse_ply(huge.as_bytes())));
+        assert!(is_data_error(parse_ply(huge.replace("binary_little_endian", "ascii").as_bytes())));
+
+        // a list claiming more items than the file has bytes left
+        let mut bytes = binary(false);
+        let faces = bytes.len() - 2 * (1 + 3 * 4);
+        bytes[faces] = 255;
+        assert!(is_data_error(parse_ply(&bytes)));
+    }
+}
@@ -0,0 +1,89 @@
+use std::ptr;
+
+use gl;
+use gl::types::{GLint, GLuint};
+
+/// Offscreen framebuffer with a color texture and a depth/stencil renderbuffer.
+pub struct RenderTarget {
+    framebuffer: GLuint,
+    color_texture: GLuint,
+    depth_stencil: GLuint,
+    width: i32,
+    height: i32,
+}
+
+impl RenderTarget {
+    pub unsafe fn new(width: i32, height: i32) -> RenderTarget {
+        let (mut framebuffer, mut color_texture, mut depth_stencil) = (0, 0, 0);
+        gl::GenFramebuffers(1, &mut framebuffer);
+        gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer);
+
+        // color attachment
+        gl::GenTextures(1, &mut color_texture);
+        gl::BindTexture(gl::TEXTURE_2D, color_texture);
+        gl::TexImage2D(
+            gl::TEXTURE_2D,
+            0,
+            gl::RGB as i32,
+            width,
+            height,
+            0,
+            gl::RGB,
+            gl::UNSIGNED_BYTE,
+            ptr::null(),
+        );
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+        gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_2D, color_texture, 0);
+
+        // depth and stencil attachment, never sampled so a renderbuffer will do
+        gl::GenRenderbuffers(1, &mut depth_stencil);
+        gl::BindRenderbuffer(gl::RENDERBUFFER, depth_stencil);
+        gl::RenderbufferStorage(gl::RENDERBUFFER, gl::DEPTH24_STENCIL8, width, height);
+        gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::DEPTH_STENCIL_ATTACHMENT, gl::RENDERBUFFER, depth_stencil);
+
+        if gl::CheckFramebufferStatus(gl::FRAMEBUFFER) != gl::FRAMEBUFFER_COMPLETE {
+            println!("ERROR::FRAMEBUFFER:: Framebuffer is not complete!");
+        }
+        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+
+        RenderTarget {
+            framebuffer,
+            color_texture,
+            depth_stencil,
+            width,
+            height,
+        }
+    }
+
+    /// render target the size of the current viewport
+    pub unsafe fn with_viewport_size() -> RenderTarget {
+        let mut viewport: [GLint; 4] = [0; 4];
+        gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+        RenderTarget::new(viewport[2], viewport[3])
+    }
+
+    /// direct rendering into this target until `unbind` is called
+    pub unsafe fn bind(&self) {
+        gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer);
+    }
+
+    /// go back to rendering to the window
+    pub unsafe fn unbind(&self) {
+        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+    }
+
+    pub fn color_texture(&self) -> GLuint {
+        self.color_texture
+    }
+
+    pub fn size(&self) -> (i32, i32) {
+        (self.width, self.height)
+    }
+
+    pub unsafe fn delete(self) {
+        gl::DeleteFramebuffers(1, &self.framebuffer);
+        gl::DeleteTextures(1, &self.color_texture);
+        gl::DeleteRenderbuffers(1, &self.depth_stencil);
+    }
+}
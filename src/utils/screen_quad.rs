@@ -0,0 +1,95 @@
+use std::ffi::CString;
+use std::path::Path;
+
+use gl;
+use gl::types::GLuint;
+
+use utils::mesh::{Mesh, Primitive, VertexAttribute, VertexLayout};
+use utils::shader::Shader;
+
+/// Quad covering the whole screen, for compositing textures and tinting the screen.
+/// Both draws blend over whatever is already in the framebuffer and ignore depth.
+pub struct ScreenQuad {
+    mesh: Mesh,
+    texture_shader: Shader,
+    color_shader: Shader,
+}
+
+impl ScreenQuad {
+    pub unsafe fn new() -> ScreenQuad {
+        let vertices: [f32; 24] = [
+            // positions // texture coords
+            -1.0,  1.0,  0.0, 1.0,
+            -1.0, -1.0,  0.0, 0.0,
+             1.0, -1.0,  1.0, 0.0,
+
+            -1.0,  1.0,  0.0, 1.0,
+             1.0, -1.0,  1.0, 0.0,
+             1.0,  1.0,  1.0, 1.0,
+        ];
+
+        let layout = VertexLayout::new(vec![
+            VertexAttribute::float(0, 2), // position
+            VertexAttribute::float(1, 2), // texture coord
+        ]).unwrap();
+        let mesh = Mesh::new(layout, &vertices, None, Primitive::Triangles).unwrap();
+
+        let texture_shader = Shader::new(
+            Path::new("src/shaders/screen.vs"),
+            Path::new("src/shaders/screen_texture.fs"),
+        );
+        texture_shader.use_program();
+        texture_shader.set_int(&CString::new("screenTexture").unwrap(), 0);
+
+        let color_shader = Shader::new(
+            Path::new("src/shaders/screen.vs"),
+            Path::new("src/shaders/screen_color.fs"),
+        );
+
+        ScreenQuad {
+            mesh,
+            texture_shader,
+            color_shader,
+        }
+    }
+
+    /// draw `texture` over the screen with the given opacity
+    pub unsafe fn draw_texture(&self, texture: GLuint, alpha: f32) {
+        self.texture_shader.use_program();
+        self.texture_shader.set_float(&CString::new("alpha").unwrap(), alpha);
+        gl::ActiveTexture(gl::TEXTURE0);
+        gl::BindTexture(gl::TEXTURE_2D, texture);
+        self.draw();
+    }
+
+    /// tint the whole screen with a color, its alpha sets the opacity
+    pub unsafe fn draw_color(&self, r: f32, g: f32, b: f32, a: f32) {
+        self.color_shader.use_program();
+        self.color_shader.set_vec4(&CString::new("color").unwrap(), r, g, b, a);
+        self.draw();
+    }
+
+    /// draw with blending on and depth testing off, leaving both as they were
+    unsafe fn draw(&self) {
+        let depth_test = gl::IsEnabled(gl::DEPTH_TEST) == gl::TRUE;
+        let blend = gl::IsEnabled(gl::BLEND) == gl::TRUE;
+        gl::Disable(gl::DEPTH_TEST);
+        gl::Enable(gl::BLEND);
+        gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+
+        self.mesh.draw();
+
+        if !blend {
+            gl::Disable(gl::BLEND);
+        }
+        if depth_test {
+            gl::Enable(gl::DEPTH_TEST);
+        }
+    }
+
+    pub unsafe fn delete(self) {
+        self.mesh.delete();
+        self.texture_shader.delete();
+        self.color_shader.delete();
+    }
+}
@@ -0,0 +1,345 @@
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::time::Duration;
+
+const MAX_WINDOW_SIZE: u32 = 16384;
+// the shaders are written for glsl 330
+const MIN_GL_VERSION: (u8, u8) = (3, 3);
+const MAX_TICK_MS: u64 = 1000;
+
+/// every setting, as used in config files and as `--name` command line flags
+pub const SETTING_NAMES: [&str; 8] = [
+    "width",
+    "height",
+    "title",
+    "gl-version",
+    "vsync",
+    "tick-ms",
+    "max-fps",
+    "clear-color",
+];
+
+#[derive(Debug)]
+pub enum SettingsError {
+    Io(String),
+    Parse { line: usize, message: String },
+    Argument { flag: String, message: String },
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SettingsError::Io(message) => write!(f, "{}", message),
+            SettingsError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+            SettingsError::Argument { flag, message } => write!(f, "{}: {}", flag, message),
+        }
+    }
+}
+
+impl Error for SettingsError {}
+
+/// Window, context and game loop settings.
+///
+/// Settings start out with their defaults, are then read from an optional config file
+/// and finally overridden by command line flags. Config files hold one `name = value`
+/// per line, blank lines and lines starting with `#` are ignored:
+///
+/// ```text
+/// width = 1280
+/// height = 720
+/// title = SPOOKY
+/// gl-version = 3.3
+/// vsync = true
+/// tick-ms = 16
+/// max-fps = 0
+/// clear-color = 0.2 0.3 0.3 1.0
+/// ```
+///
+/// The same settings can be given on the command line, e.g. `--width 1280 --vsync false`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    pub width: u32,
+    pub height: u32,
+    pub title: String,
+    /// requested OpenGL core version (major, minor)
+    pub gl_version: (u8, u8),
+    pub vsync: bool,
+    /// length of a fixed update in milliseconds
+    pub tick_ms: u64,
+    /// frame rate limit, 0 doesn't limit
+    pub max_fps: u32,
+    /// rgba, each in [0, 1]
+    pub clear_color: [f32; 4],
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            width: 800,
+            height: 600,
+            title: "SPOOKY".to_string(),
+            gl_version: (3, 3),
+            vsync: true,
+            tick_ms: 16,
+            max_fps: 0,
+            clear_color: [0.2, 0.3, 0.3, 1.0],
+        }
+    }
+}
+
+impl Settings {
+    pub fn load(path: &Path) -> Result<Settings, SettingsError> {
+        let mut file = File::open(path)
+            .map_err(|e| SettingsError::Io(format!("Failed to open {}: {}", path.display(), e)))?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)
+            .map_err(|e| SettingsError::Io(format!("Failed to read {}: {}", path.display(), e)))?;
+        Settings::parse(&contents)
+    }
+
+    /// defaults overridden by the settings in a config file
+    pub fn parse(contents: &str) -> Result<Settings, SettingsError> {
+        let mut settings = Settings::default();
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let parse_error = |message| SettingsError::Parse {
+                line: index + 1,
+                message,
+            };
+            let mut parts = line.splitn(2, '=');
+            let name = parts.next().unwrap_or("").trim();
+            let value = parts
+                .next()
+                .ok_or_else(|| parse_error(format!("expected 'name = value', got '{}'", line)))?
+                .trim();
+            settings.set(name, value).map_err(parse_error)?;
+        }
+        Ok(settings)
+    }
+
+    /// override settings with any `--name value` flags in `args`, other arguments are left alone
+    pub fn apply_args(&mut self, args: &[String]) -> Result<(), SettingsError> {
+        for (i, arg) in args.iter().enumerate() {
+            let name = match arg.strip_prefix("--") {
+                Some(name) if SETTING_NAMES.contains(&name) => name,
+                _ => continue,
+            };
+            let argument_error = |message| SettingsError::Argument {
+                flag: arg.clone(),
+                message,
+            };
+            let value = args
+                .get(i + 1)
+                .ok_or_else(|| argument_error("requires a value".to_string()))?;
+            self.set(name, value).map_err(argument_error)?;
+        }
+        Ok(())
+    }
+
+    /// parse and validate a single setting
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match name {
+            "width" => self.width = parse_window_size(value)?,
+            "height" => self.height = parse_window_size(value)?,
+            "title" => self.title = value.to_string(),
+            "gl-version" => self.gl_version = parse_gl_version(value)?,
+            "vsync" => self.vsync = parse_bool(value)?,
+            "tick-ms" => {
+                self.tick_ms = match value.parse::<u64>() {
+                    Ok(ms) if (1..=MAX_TICK_MS).contains(&ms) => ms,
+                    _ => return Err(format!("'{}' is not a tick length between 1 and {} ms", value, MAX_TICK_MS)),
+                }
+            }
+            "max-fps" => {
+                self.max_fps = value
+                    .parse::<u32>()
+                    .map_err(|_| format!("'{}' is not a number of frames", value))?
+            }
+            "clear-color" => self.clear_color = parse_color(value)?,
+            _ => return Err(format!("unknown setting '{}', expected one of: {}", name, SETTING_NAMES.join(", "))),
+        }
+        Ok(())
+    }
+
+    pub fn tick_duration(&self) -> Duration {
+        Duration::from_millis(self.tick_ms)
+    }
+
+    /// frame rate limit for `FrameLimiter`
+    pub fn frame_limit(&self) -> Option<u32> {
+        if self.max_fps > 0 {
+            Some(self.max_fps)
+        } else {
+            None
+        }
+    }
+
+    pub fn aspect_ratio(&self) -> f32 {
+        self.width as f32 / self.height as f32
+    }
+}
+
+fn parse_window_size(value: &str) -> Result<u32, String> {
+    match value.parse::<u32>() {
+        Ok(size) if (1..=MAX_WINDOW_SIZE).contains(&size) => Ok(size),
+        _ => Err(format!("'{}' is not a window size between 1 and {}", value, MAX_WINDOW_SIZE)),
+    }
+}
+
+fn parse_gl_version(value: &str) -> Result<(u8, u8), String> {
+    let error = || format!("'{}' is not an OpenGL version like 3.3", value);
+    let mut parts = value.splitn(2, '.');
+    let major = parts.next().unwrap_or("").parse::<u8>().map_err(|_| error())?;
+    let minor = parts.next().ok_or_else(error)?.parse::<u8>().map_err(|_| error())?;
+    if (major, minor) < MIN_GL_VERSION {
+        return Err(format!(
+            "OpenGL {}.{} is too old, at least {}.{} is needed",
+            major, minor, MIN_GL_VERSION.0, MIN_GL_VERSION.1
+        ));
+    }
+    Ok((major, minor))
+}
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value {
+        "true" | "on" | "yes" => Ok(true),
+        "false" | "off" | "no" => Ok(false),
+        _ => Err(format!("'{}' is not true or false", value)),
+    }
+}
+
+/// rgba color, components separated by spaces or commas, alpha defaults to 1
+fn parse_color(value: &str) -> Result<[f32; 4], String> {
+    let components: Vec<&str> = value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|component| !component.is_empty())
+        .collect();
+    if components.len() != 3 && components.len() != 4 {
+        return Err(format!("'{}' is not a color, expected 3 or 4 components", value));
+    }
+    let mut color = [1.0; 4];
+    for (i, component) in components.iter().enumerate() {
+        color[i] = match component.parse::<f32>() {
+            Ok(c) if (0.0..=1.0).contains(&c) => c,
+            _ => return Err(format!("color component '{}' is not a number between 0 and 1", component)),
+        };
+    }
+    Ok(color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn parse() {
+        let settings = Settings::parse(
+            "# window\n\
+             width = 1280\n\
+             height=720\n\
+             \n\
+             title = a = b\n\
+             gl-version = 4.1\n\
+             vsync = off\n\
+             tick-ms = 10\n\
+             max-fps = 144\n\
+             clear-color = 0, 0.5, 1\n",
+        ).unwrap();
+        assert_eq!(
+            settings,
+            Settings {
+                width: 1280,
+                height: 720,
+                title: "a = b".to_string(),
+                gl_version: (4, 1),
+                vsync: false,
+                tick_ms: 10,
+                max_fps: 144,
+                clear_color: [0.0, 0.5, 1.0, 1.0],
+            }
+        );
+        assert_eq!(Settings::parse("").unwrap(), Settings::default());
+        assert_eq!(settings.frame_limit(), Some(144));
+        assert_eq!(settings.tick_duration(), Duration::from_millis(10));
+    }
+
+    #[test]
+    fn parse_errors_have_line_numbers() {
+        match Settings::parse("width = 640\n\nheight 480\n") {
+            Err(SettingsError::Parse { line: 3, .. }) => (),
+            other => panic!("{:?}", other),
+        }
+        match Settings::parse("# nothing\ndepth = 24") {
+            Err(SettingsError::Parse { line: 2, message }) => assert!(message.contains("unknown setting 'depth'")),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn args_override_the_config() {
+        let mut settings = Settings::parse("width = 1280\nvsync = false\nmax-fps = 60").unwrap();
+        settings
+            .apply_args(&args(&["app", "--demo", "model", "--width", "640", "--vsync", "true", "--width", "320"]))
+            .unwrap();
+        // the last flag wins, settings without a flag keep the config's value
+        assert_eq!(settings.width, 320);
+        assert!(settings.vsync);
+        assert_eq!(settings.max_fps, 60);
+        assert_eq!(settings.height, 600);
+
+        match settings.apply_args(&args(&["app", "--height"])) {
+            Err(SettingsError::Argument { flag, .. }) => assert_eq!(flag, "--height"),
+            other => panic!("{:?}", other),
+        }
+        match settings.apply_args(&args(&["app", "--tick-ms", "0"])) {
+            Err(SettingsError::Argument { flag, .. }) => assert_eq!(flag, "--tick-ms"),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn bounds() {
+        let valid = [
+            ("width", "1"),
+            ("width", "16384"),
+            ("height", "16384"),
+            ("gl-version", "3.3"),
+            ("gl-version", "4.6"),
+            ("tick-ms", "1"),
+            ("tick-ms", "1000"),
+            ("clear-color", "0 0 0 0"),
+            ("clear-color", "1,1,1,1"),
+        ];
+        for &(name, value) in &valid {
+            assert!(Settings::default().set(name, value).is_ok(), "{} = {}", name, value);
+        }
+        let invalid = [
+            ("width", "0"),
+            ("width", "16385"),
+            ("height", "-1"),
+            ("gl-version", "3.2"),
+            ("gl-version", "2.1"),
+            ("gl-version", "3"),
+            ("tick-ms", "0"),
+            ("tick-ms", "1001"),
+            ("vsync", "maybe"),
+            ("max-fps", "-30"),
+            ("clear-color", "0.5 0.5"),
+            ("clear-color", "0 0 1.5"),
+            ("clear-color", "0 -0.1 0 1"),
+        ];
+        for &(name, value) in &invalid {
+            assert!(Settings::default().set(name, value).is_err(), "{} = {}", name, value);
+        }
+    }
+}
@@ -68,6 +68,12 @@ impl Shader {
         gl::UseProgram(self.id)
     }
 
+    /// delete the shader program
+    /// ------------------------------------------------------------------------
+    pub unsafe fn delete(self) {
+        gl::DeleteProgram(self.id)
+    }
+
     /// utility uniform functions
     /// ------------------------------------------------------------------------
     pub unsafe fn set_bool(&self, name: &CStr, value: bool) {
@@ -90,6 +96,10 @@ impl Shader {
         gl::Uniform3f(gl::GetUniformLocation(self.id, name.as_ptr()), x, y, z);
     }
     /// ------------------------------------------------------------------------
+    pub unsafe fn set_vec4(&self, name: &CStr, x: f32, y: f32, z: f32, w: f32) {
+        gl::Uniform4f(gl::GetUniformLocation(self.id, name.as_ptr()), x, y, z, w);
+    }
+    /// ------------------------------------------------------------------------
     pub unsafe fn set_matrix4(&self, name: &CStr, mat: &Matrix4<f32>) {
         gl::UniformMatrix4fv(gl::GetUniformLocation(self.id, name.as_ptr()), 1, gl::FALSE, mat.as_ptr());
     }
@@ -98,8 +108,7 @@ impl Shader {
     /// ------------------------------------------------------------------------
     unsafe fn check_compile_errors(&self, shader: u32, type_: &str) {
         let mut success = gl::FALSE as GLint;
-        let mut info_log = Vec::with_capacity(1024);
-        info_log.set_len(1024 - 1); // subtract 1 to skip the trailing null character
+        let mut info_log = vec![0u8; 1024];
         if type_ != "PROGRAM" {
             gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut success);
             if success != gl::TRUE as GLint {
@@ -0,0 +1,447 @@
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+use cgmath::prelude::*;
+use cgmath::{vec2, vec3, vec4, Point3, Vector3};
+
+use utils::mesh_data::MeshData;
+use utils::mesh_optimize::optimize_vertex_fetch;
+
+// Generators for common shapes, centered on the origin with y up.
+//
+// Every shape has positions, normals, texture coords (v pointing up, like the images loaded
+// by the demos) and tangents with the bitangent along increasing v. Triangles are
+// counter-clockwise seen from outside. Segment counts below the minimum a shape needs are raised to it.
+
+/// cube with each face mapped to the whole texture
+pub fn cube(size: f32) -> MeshData {
+    // outward normal and the direction u increases in, v increases along normal x u
+    let faces = [
+        (vec3(0.0, 0.0, 1.0), vec3(1.0, 0.0, 0.0)),
+        (vec3(0.0, 0.0, -1.0), vec3(-1.0, 0.0, 0.0)),
+        (vec3(1.0, 0.0, 0.0), vec3(0.0, 0.0, -1.0)),
+        (vec3(-1.0, 0.0, 0.0), vec3(0.0, 0.0, 1.0)),
+        (vec3(0.0, 1.0, 0.0), vec3(1.0, 0.0, 0.0)),
+        (vec3(0.0, -1.0, 0.0), vec3(1.0, 0.0, 0.0)),
+    ];
+    let half = size / 2.0;
+    let mut data = MeshData::default();
+    for &(normal, u_axis) in faces.iter() {
+        let v_axis: Vector3<f32> = normal.cross(u_axis);
+        let first = data.positions.len() as u32;
+        for &(u, v) in [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)].iter() {
+            let position = (normal + u_axis * (2.0 * u - 1.0) + v_axis * (2.0 * v - 1.0)) * half;
+            data.positions.push(Point3::from_vec(position));
+            data.normals.push(normal);
+            data.tex_coords.push(vec2(u, v));
+            data.tangents.push(u_axis.extend(1.0));
+        }
+        push_quad(&mut data, first, first + 1, first + 2, first + 3);
+    }
+    data
+}
+
+/// rectangle in the xy plane facing +z, corners in the order bottom left, bottom right, top right, top left
+pub fn quad(width: f32, height: f32) -> MeshData {
+    let mut data = MeshData::default();
+    for &(u, v) in [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)].iter() {
+        data.positions.push(Point3::new((u - 0.5) * width, (v - 0.5) * height, 0.0));
+        data.normals.push(vec3(0.0, 0.0, 1.0));
+        data.tex_coords.push(vec2(u, v));
+        data.tangents.push(vec4(1.0, 0.0, 0.0, 1.0));
+    }
+    push_quad(&mut data, 0, 1, 2, 3);
+    data
+}
+
+/// grid in the xz plane facing +y, `columns` cells along x and `rows` along z, v increases towards -z
+pub fn plane(width: f32, depth: f32, columns: u32, rows: u32) -> MeshData {
+    let columns = columns.max(1);
+    let rows = rows.max(1);
+    let mut data = MeshData::default();
+    for row in 0..=rows {
+        let v = row as f32 / rows as f32;
+        for column in 0..=columns {
+            let u = column as f32 / columns as f32;
+            data.positions.push(Point3::new((u - 0.5) * width, 0.0, (0.5 - v) * depth));
+            data.normals.push(vec3(0.0, 1.0, 0.0));
+            data.tex_coords.push(vec2(u, v));
+            data.tangents.push(vec4(1.0, 0.0, 0.0, 1.0));
+        }
+    }
+    for row in 0..rows {
+        for column in 0..columns {
+            let bottom_left = row * (columns + 1) + column;
+            let top_left = bottom_left + columns + 1;
+            push_quad(&mut data, bottom_left, bottom_left + 1, top_left + 1, top_left);
+        }
+    }
+    data
+}
+
+/// sphere made of `sectors` slices around y and `stacks` rings from pole to pole
+pub fn uv_sphere(radius: f32, sectors: u32, stacks: u32) -> MeshData {
+    let stacks = stacks.max(2);
+    let profile: Vec<ProfilePoint> = (0..=stacks)
+        .map(|stack| {
+            let v = stack as f32 / stacks as f32;
+            let (sin, cos) = ((v - 0.5) * PI).sin_cos();
+            ProfilePoint::new(radius * cos, radius * sin, cos, sin, v)
+        }).collect();
+    revolve(&profile, sectors)
+}
+
+/// Sphere from an icosahedron with every triangle split into four `subdivisions` times.
+///
+/// Texture coords are the same spherical mapping as `uv_sphere`, vertices on the seam and
+/// at the poles are split so no triangle stretches across the texture.
+pub fn icosphere(radius: f32, subdivisions: u32) -> MeshData {
+    let t = (1.0 + 5.0f32.sqrt()) / 2.0;
+    let mut directions: Vec<Vector3<f32>> = [
+        (-1.0, t, 0.0),
+        (1.0, t, 0.0),
+        (-1.0, -t, 0.0),
+        (1.0, -t, 0.0),
+        (0.0, -1.0, t),
+        (0.0, 1.0, t),
+        (0.0, -1.0, -t),
+        (0.0, 1.0, -t),
+        (t, 0.0, -1.0),
+        (t, 0.0, 1.0),
+        (-t, 0.0, -1.0),
+        (-t, 0.0, 1.0),
+    ].iter()
+        .map(|&(x, y, z)| vec3(x, y, z).normalize())
+        .collect();
+    let mut triangles: Vec<[u32; 3]> = vec![
+        [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+        [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+        [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+        [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+    ];
+
+    for _ in 0..subdivisions {
+        // the vertex in the middle of each edge, shared by the triangles on both sides
+        let mut midpoints: HashMap<(u32, u32), u32> = HashMap::new();
+        let mut midpoint = |a: u32, b: u32, directions: &mut Vec<Vector3<f32>>| -> u32 {
+            let key = (a.min(b), a.max(b));
+            *midpoints.entry(key).or_insert_with(|| {
+                directions.push((directions[a as usize] + directions[b as usize]).normalize());
+                directions.len() as u32 - 1
+            })
+        };
+        let mut subdivided = Vec::with_capacity(triangles.len() * 4);
+        for &[a, b, c] in &triangles {
+            let ab = midpoint(a, b, &mut directions);
+            let bc = midpoint(b, c, &mut directions);
+            let ca = midpoint(c, a, &mut directions);
+            subdivided.extend_from_slice(&[[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]);
+        }
+        triangles = subdivided;
+    }
+
+    let mut data = MeshData::default();
+    let push_vertex = |data: &mut MeshData, direction: Vector3<f32>, u: f32| -> u32 {
+        let angle = (u - 0.5) * 2.0 * PI;
+        data.positions.push(Point3::from_vec(direction * radius));
+        data.normals.push(direction);
+        data.tex_coords.push(vec2(u, 0.5 + direction.y.asin() / PI));
+        data.tangents.push(vec4(angle.cos(), 0.0, -angle.sin(), 1.0));
+        data.positions.len() as u32 - 1
+    };
+    let longitude = |direction: Vector3<f32>| 0.5 + direction.x.atan2(direction.z) / (2.0 * PI);
+    let is_pole = |direction: Vector3<f32>| direction.x.abs() < 1e-6 && direction.z.abs() < 1e-6;
+
+    for &direction in &directions {
+        push_vertex(&mut data, direction, longitude(direction));
+    }
+    // copies of the vertices on the left of the seam moved past u = 1, shared by the triangles crossing it
+    let mut seam: HashMap<u32, u32> = HashMap::new();
+    for triangle in &triangles {
+        let mut corners = *triangle;
+        let pole: Vec<bool> = triangle.iter().map(|&i| is_pole(directions[i as usize])).collect();
+        let mut u: Vec<f32> = triangle.iter().map(|&i| data.tex_coords[i as usize].x).collect();
+        // triangles crossing the seam at the back get the corners on the left side moved past u = 1
+        let longitudes: Vec<f32> = (0..3).filter(|&k| !pole[k]).map(|k| u[k]).collect();
+        let min = longitudes.iter().cloned().fold(1.0, f32::min);
+        let max = longitudes.iter().cloned().fold(0.0, f32::max);
+        if max - min > 0.5 {
+            for k in (0..3).filter(|&k| !pole[k]) {
+                if u[k] < 0.5 {
+                    u[k] += 1.0;
+                    corners[k] = *seam
+                        .entry(triangle[k])
+                        .or_insert_with(|| push_vertex(&mut data, directions[triangle[k] as usize], u[k]));
+                }
+            }
+        }
+        // the poles have no longitude, give them the one between the other two corners
+        for k in (0..3).filter(|&k| pole[k]) {
+            let between = (u[(k + 1) % 3] + u[(k + 2) % 3]) / 2.0;
+            corners[k] = push_vertex(&mut data, directions[triangle[k] as usize], between);
+        }
+        data.indices.extend_from_slice(&corners);
+    }
+    // the poles only live on in the copies made for each triangle
+    optimize_vertex_fetch(&mut data);
+    data
+}
+
+/// cylinder along y with capped ends
+pub fn cylinder(radius: f32, height: f32, sectors: u32) -> MeshData {
+    let half = height / 2.0;
+    let mut data = revolve(
+        &[
+            ProfilePoint::new(radius, -half, 1.0, 0.0, 0.0),
+            ProfilePoint::new(radius, half, 1.0, 0.0, 1.0),
+        ],
+        sectors,
+    );
+    data.append(&disc(radius, half, true, sectors));
+    data.append(&disc(radius, -half, false, sectors));
+    data
+}
+
+/// cone along y with its tip at the top and a capped base
+pub fn cone(radius: f32, height: f32, sectors: u32) -> MeshData {
+    let half = height / 2.0;
+    // the side normals lean up by the slope of the cone
+    let normal = vec2(height, radius).normalize();
+    let mut data = revolve(
+        &[
+            ProfilePoint::new(radius, -half, normal.x, normal.y, 0.0),
+            ProfilePoint::new(0.0, half, normal.x, normal.y, 1.0),
+        ],
+        sectors,
+    );
+    data.append(&disc(radius, -half, false, sectors));
+    data
+}
+
+/// Cylinder along y with half spheres on both ends, `height` is the length of the
+/// straight part and `rings` the number of rings of each half sphere.
+///
+/// v follows the distance along the surface, so the texture isn't stretched over the ends.
+pub fn capsule(radius: f32, height: f32, sectors: u32, rings: u32) -> MeshData {
+    let rings = rings.max(1);
+    let half = height / 2.0;
+    let length = PI * radius + height;
+    let mut profile = Vec::new();
+    for &(center, first_angle) in [(-half, -PI / 2.0), (half, 0.0)].iter() {
+        for ring in 0..=rings {
+            let angle = first_angle + ring as f32 / rings as f32 * PI / 2.0;
+            let (sin, cos) = angle.sin_cos();
+            // distance from the bottom pole along the surface
+            let distance = if center < 0.0 {
+                (angle + PI / 2.0) * radius
+            } else {
+                PI / 2.0 * radius + height + angle * radius
+            };
+            profile.push(ProfilePoint::new(radius * cos, center + radius * sin, cos, sin, distance / length));
+        }
+    }
+    revolve(&profile, sectors)
+}
+
+/// ring around y, `major_radius` from the center to the middle of the tube and
+/// `minor_radius` the radius of the tube, which has to be the smaller one
+pub fn torus(major_radius: f32, minor_radius: f32, major_segments: u32, minor_segments: u32) -> MeshData {
+    let minor_segments = minor_segments.max(3);
+    // around the tube from the inside, under it, out and over it
+    let profile: Vec<ProfilePoint> = (0..=minor_segments)
+        .map(|segment| {
+            let v = segment as f32 / minor_segments as f32;
+            let (sin, cos) = (v * 2.0 * PI - PI).sin_cos();
+            ProfilePoint::new(major_radius + minor_radius * cos, minor_radius * sin, cos, sin, v)
+        }).collect();
+    revolve(&profile, major_segments)
+}
+
+/// point of a profile revolved around y, the normal in the same (radius, y) plane
+struct ProfilePoint {
+    radius: f32,
+    y: f32,
+    normal_radius: f32,
+    normal_y: f32,
+    v: f32,
+}
+
+impl ProfilePoint {
+    fn new(radius: f32, y: f32, normal_radius: f32, normal_y: f32, v: f32) -> Self {
+        ProfilePoint {
+            radius,
+            y,
+            normal_radius,
+            normal_y,
+            v,
+        }
+    }
+}
+
+/// Sweep a profile going up the outside of a shape once around the y axis.
+///
+/// Each point becomes a ring of `sectors + 1` vertices starting and ending at -z, the last one
+/// repeating the first with u = 1. Points with a radius of 0 are poles, the triangles they would
+/// collapse are left out and each pole vertex gets the u in the middle of the one triangle it's in,
+/// so a pole ring has only `sectors` vertices.
+fn revolve(profile: &[ProfilePoint], sectors: u32) -> MeshData {
+    let sectors = sectors.max(3);
+    let mut data = MeshData::default();
+    for point in profile {
+        // poles computed with sin and cos end up a rounding error off the axis, put them on it
+        let (radius, offset) = if point.radius > 0.0 { (point.radius, 0.0) } else { (0.0, 0.5) };
+        for sector in 0..=sectors {
+            let u = (sector as f32 + offset) / sectors as f32;
+            // the last vertex of a ring uses the angle of the first, so the seam matches bit for bit
+            let around = ((sector % sectors) as f32 + offset) / sectors as f32;
+            let (sin, cos) = ((around - 0.5) * 2.0 * PI).sin_cos();
+            data.positions.push(Point3::new(radius * sin, point.y, radius * cos));
+            data.normals.push(vec3(point.normal_radius * sin, point.normal_y, point.normal_radius * cos).normalize());
+            data.tex_coords.push(vec2(u, point.v));
+            data.tangents.push(vec4(cos, 0.0, -sin, 1.0));
+        }
+    }
+
+    let columns = sectors + 1;
+    for (ring, points) in profile.windows(2).enumerate() {
+        for sector in 0..sectors {
+            let bottom_left = ring as u32 * columns + sector;
+            let top_left = bottom_left + columns;
+            if points[1].radius <= 0.0 {
+                data.indices.extend_from_slice(&[bottom_left, bottom_left + 1, top_left]);
+            } else if points[0].radius > 0.0 {
+                data.indices.extend_from_slice(&[bottom_left, bottom_left + 1, top_left + 1]);
+            }
+            if points[1].radius > 0.0 {
+                data.indices.extend_from_slice(&[bottom_left, top_left + 1, top_left]);
+            }
+        }
+    }
+    // drop the last vertex of the pole rings, which no triangle uses
+    optimize_vertex_fetch(&mut data);
+    data
+}
+
+/// flat cap at height `y` facing up or down, the texture mapped onto it from that side
+fn disc(radius: f32, y: f32, facing_up: bool, sectors: u32) -> MeshData {
+    let sectors = sectors.max(3);
+    let (normal_y, v_sign) = if facing_up { (1.0, -1.0) } else { (-1.0, 1.0) };
+    let mut data = MeshData::default();
+    let mut push_vertex = |x: f32, z: f32| {
+        data.positions.push(Point3::new(x, y, z));
+        data.normals.push(vec3(0.0, normal_y, 0.0));
+        data.tex_coords.push(vec2(0.5 + x / (2.0 * radius), 0.5 + v_sign * z / (2.0 * radius)));
+        data.tangents.push(vec4(1.0, 0.0, 0.0, 1.0));
+    };
+    push_vertex(0.0, 0.0);
+    for sector in 0..sectors {
+        let (sin, cos) = (sector as f32 / sectors as f32 * 2.0 * PI).sin_cos();
+        push_vertex(radius * sin, radius * cos);
+    }
+    for sector in 0..sectors {
+        let current = 1 + sector;
+        let next = 1 + (sector + 1) % sectors;
+        if facing_up {
+            data.indices.extend_from_slice(&[0, current, next]);
+        } else {
+            data.indices.extend_from_slice(&[0, next, current]);
+        }
+    }
+    data
+}
+
+/// two triangles, corners counter-clockwise
+fn push_quad(data: &mut MeshData, a: u32, b: u32, c: u32, d: u32) {
+    data.indices.extend_from_slice(&[a, b, c, a, c, d]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::mesh_data::Semantic;
+
+    /// the point a face around the given point should face away from
+    type Inside = fn(Point3<f32>) -> Point3<f32>;
+
+    fn shapes() -> Vec<(&'static str, MeshData, Inside)> {
+        fn origin(_: Point3<f32>) -> Point3<f32> {
+            Point3::new(0.0, 0.0, 0.0)
+        }
+        fn below(point: Point3<f32>) -> Point3<f32> {
+            Point3::new(point.x, point.y - 1.0, point.z)
+        }
+        fn behind(point: Point3<f32>) -> Point3<f32> {
+            Point3::new(point.x, point.y, point.z - 1.0)
+        }
+        // the middle of the tube of `torus(1.0, ..)` closest to the point
+        fn tube(point: Point3<f32>) -> Point3<f32> {
+            let around = vec3(point.x, 0.0, point.z).normalize();
+            Point3::from_vec(around)
+        }
+        vec![
+            ("cube", cube(1.0), origin),
+            ("quad", quad(2.0, 1.0), behind),
+            ("plane", plane(2.0, 3.0, 4, 5), below),
+            ("uv_sphere", uv_sphere(1.0, 16, 8), origin),
+            ("icosphere 0", icosphere(1.0, 0), origin),
+            ("icosphere 2", icosphere(1.0, 2), origin),
+            ("cylinder", cylinder(0.5, 2.0, 12), origin),
+            ("cone", cone(0.5, 1.0, 12), origin),
+            ("capsule", capsule(0.5, 1.0, 12, 4), origin),
+            ("torus", torus(1.0, 0.25, 16, 8), tube),
+        ]
+    }
+
+    #[test]
+    fn shapes_validate_and_use_every_vertex() {
+        for (name, data, _) in shapes() {
+            data.validate().unwrap_or_else(|error| panic!("{}: {}", name, error));
+            assert!(data.triangle_count() > 0, "{}", name);
+            for semantic in &[Semantic::Normal, Semantic::TexCoord, Semantic::Tangent] {
+                assert!(data.has(*semantic), "{} has no {}", name, semantic.name());
+            }
+            let mut used = vec![false; data.vertex_count()];
+            for &index in &data.indices {
+                used[index as usize] = true;
+            }
+            let unused = used.iter().filter(|&&used| !used).count();
+            assert_eq!(unused, 0, "{} has {} unused vertices", name, unused);
+        }
+    }
+
+    #[test]
+    fn shapes_wind_counter_clockwise_outward() {
+        for (name, data, inside) in shapes() {
+            for triangle in data.indices.chunks(3) {
+                let [a, b, c] = [0, 1, 2].map(|k| data.positions[triangle[k] as usize]);
+                let face = (b - a).cross(c - a);
+                assert!(face.magnitude() > 1e-6, "{} has a degenerate triangle {:?}", name, triangle);
+                let center = Point3::centroid(&[a, b, c]);
+                assert!(face.dot(center - inside(center)) > 0.0, "{} triangle {:?} faces inward", name, triangle);
+                for &vertex in triangle {
+                    let normal = data.normals[vertex as usize];
+                    assert!(face.dot(normal) > 0.0, "{} triangle {:?} winds against its normals", name, triangle);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn shapes_have_unit_normals_and_tangents() {
+        for (name, data, _) in shapes() {
+            for (normal, tangent) in data.normals.iter().zip(data.tangents.iter()) {
+                assert!((normal.magnitude() - 1.0).abs() < 1e-5, "{} normal {:?}", name, normal);
+                assert!((tangent.truncate().magnitude() - 1.0).abs() < 1e-5, "{} tangent {:?}", name, tangent);
+                assert!(tangent.w == 1.0 || tangent.w == -1.0, "{} tangent {:?}", name, tangent);
+                assert!(normal.dot(tangent.truncate()).abs() < 1e-5, "{} tangent {:?} off normal {:?}", name, tangent, normal);
+            }
+        }
+    }
+
+    #[test]
+    fn pole_rings_drop_the_unused_vertex() {
+        // 17 rings of 33 vertices, less one on each pole ring
+        assert_eq!(uv_sphere(1.0, 32, 16).vertex_count(), 17 * 33 - 2);
+        assert_eq!(cone(1.0, 1.0, 8).vertex_count(), 9 + 8 + 9);
+    }
+}
@@ -0,0 +1,337 @@
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use cgmath::prelude::*;
+use cgmath::{vec3, Deg, Point3, Vector3};
+
+use utils::mesh_data::MeshData;
+use utils::mesh_optimize::weld_vertices;
+use utils::obj::triangulate;
+use utils::tangent_space::generate_normals;
+
+// edges between facets closer than this are shaded smooth, sharper ones stay creased
+pub const STL_SMOOTHING_ANGLE: Deg<f32> = Deg(30.0);
+
+const BINARY_HEADER_SIZE: usize = 84;
+const BINARY_TRIANGLE_SIZE: usize = 50;
+
+#[derive(Debug)]
+pub enum StlError {
+    Io(String),
+    /// binary file that is cut short or has a wrong triangle count
+    Binary(String),
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for StlError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StlError::Io(message) | StlError::Binary(message) => write!(f, "{}", message),
+            StlError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for StlError {}
+
+pub fn load_stl(path: &Path) -> Result<MeshData, StlError> {
+    let mut file = File::open(path).map_err(|e| StlError::Io(format!("Failed to open {}: {}", path.display(), e)))?;
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents)
+        .map_err(|e| StlError::Io(format!("Failed to read {}: {}", path.display(), e)))?;
+    parse_stl(&contents)
+}
+
+/// Parse a binary or ASCII STL file.
+///
+/// STL stores every triangle on its own, so corners at the same position are welded into one
+/// vertex. Triangles wound against their stored facet normal are flipped, then normals are
+/// computed smooth across edges under `STL_SMOOTHING_ANGLE`.
+pub fn parse_stl(contents: &[u8]) -> Result<MeshData, StlError> {
+    // binary files may start with "solid" too, their size gives them away
+    let facets = if is_binary(contents) {
+        parse_binary(contents)?
+    } else if contents.starts_with(b"solid") {
+        let text = std::str::from_utf8(contents).map_err(|_| StlError::Parse {
+            line: 1,
+            message: "ASCII STL that isn't valid UTF-8".to_string(),
+        })?;
+        parse_ascii(text)?
+    } else {
+        return Err(StlError::Binary(format!(
+            "{} bytes is neither an ASCII STL nor a binary one",
+            contents.len()
+        )));
+    };
+
+    let mut data = MeshData::default();
+    for facet in facets {
+        let computed = (facet.corners[1] - facet.corners[0]).cross(facet.corners[2] - facet.corners[0]);
+        let first = data.positions.len() as u32;
+        data.positions.extend_from_slice(&facet.corners);
+        if computed.dot(facet.normal) < 0.0 {
+            data.indices.extend_from_slice(&[first, first + 2, first + 1]);
+        } else {
+            data.indices.extend_from_slice(&[first, first + 1, first + 2]);
+        }
+    }
+    // can't fail, the streams and indices were just built to match
+    weld_vertices(&mut data).unwrap();
+    generate_normals(&mut data, STL_SMOOTHING_ANGLE).unwrap();
+    Ok(data)
+}
+
+struct Facet {
+    /// normal stored in the file, zero if it didn't give one
+    normal: Vector3<f32>,
+    corners: [Point3<f32>; 3],
+}
+
+fn is_binary(contents: &[u8]) -> bool {
+    contents.len() >= BINARY_HEADER_SIZE
+        && contents.len() == BINARY_HEADER_SIZE + read_u32(contents, 80) as usize * BINARY_TRIANGLE_SIZE
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from(bytes[offset])
+        | u32::from(bytes[offset + 1]) << 8
+        | u32::from(bytes[offset + 2]) << 16
+        | u32::from(bytes[offset + 3]) << 24
+}
+
+fn read_vector(bytes: &[u8], offset: usize) -> Vector3<f32> {
+    let component = |i: usize| f32::from_bits(read_u32(bytes, offset + i * 4));
+    vec3(component(0), component(1), component(2))
+}
+
+/// 80 byte header, triangle count, then per triangle a normal, three corners and two unused bytes
+fn parse_binary(contents: &[u8]) -> Result<Vec<Facet>, StlError> {
+    if contents.len() < BINARY_HEADER_SIZE {
+        return Err(StlError::Binary("binary STL shorter than its header".to_string()));
+    }
+    let count = read_u32(contents, 80) as usize;
+    if contents.len() < BINARY_HEADER_SIZE + count * BINARY_TRIANGLE_SIZE {
+        return Err(StlError::Binary(format!(
+            "binary STL with {} triangles needs {} bytes, got {}",
+            count,
+            BINARY_HEADER_SIZE + count * BINARY_TRIANGLE_SIZE,
+            contents.len()
+        )));
+    }
+    Ok((0..count)
+        .map(|triangle| {
+            let offset = BINARY_HEADER_SIZE + triangle * BINARY_TRIANGLE_SIZE;
+            Facet {
+                normal: read_vector(contents, offset),
+                corners: [
+                    Point3::from_vec(read_vector(contents, offset + 12)),
+                    Point3::from_vec(read_vector(contents, offset + 24)),
+                    Point3::from_vec(read_vector(contents, offset + 36)),
+                ],
+            }
+        }).collect())
+}
+
+/// `facet normal`, `outer loop`, three or more `vertex` lines, `endloop`, `endfacet`,
+/// possibly in several `solid`s
+fn parse_ascii(contents: &str) -> Result<Vec<Facet>, StlError> {
+    let mut facets = Vec::new();
+    let mut normal: Option<Vector3<f32>> = None;
+    let mut corners: Vec<Point3<f32>> = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let parse_error = |message: String| StlError::Parse {
+            line: index + 1,
+            message,
+        };
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.first().cloned().unwrap_or("") {
+            "" | "solid" | "endsolid" | "outer" | "endloop" => (),
+            "facet" => {
+                if normal.is_some() {
+                    return Err(parse_error("facet inside a facet".to_string()));
+                }
+                normal = Some(match tokens.get(1) {
+                    Some(&"normal") => numbers(&tokens[2..]).map_err(parse_error)?,
+                    _ => return Err(parse_error("expected 'facet normal x y z'".to_string())),
+                });
+            }
+            "vertex" => {
+                if normal.is_none() {
+                    return Err(parse_error("vertex outside a facet".to_string()));
+                }
+                corners.push(Point3::from_vec(numbers(&tokens[1..]).map_err(parse_error)?));
+            }
+            "endfacet" => {
+                let facet_normal = normal
+                    .take()
+                    .ok_or_else(|| parse_error("endfacet without facet".to_string()))?;
+                if corners.len() < 3 {
+                    return Err(parse_error(format!("facet with {} vertices", corners.len())));
+                }
+                for triangle in triangulate(&corners) {
+                    facets.push(Facet {
+                        normal: facet_normal,
+                        corners: [corners[triangle[0]], corners[triangle[1]], corners[triangle[2]]],
+                    });
+                }
+                corners.clear();
+            }
+            keyword => return Err(parse_error(format!("unknown keyword '{}'", keyword))),
+        }
+    }
+    if normal.is_some() {
+        return Err(StlError::Parse {
+            line: contents.lines().count(),
+            message: "facet without endfacet".to_string(),
+        });
+    }
+    Ok(facets)
+}
+
+fn numbers(tokens: &[&str]) -> Result<Vector3<f32>, String> {
+    if tokens.len() != 3 {
+        return Err(format!("expected 3 numbers, got {}", tokens.len()));
+    }
+    let mut values = [0.0; 3];
+    for (value, token) in values.iter_mut().zip(tokens) {
+        *value = token
+            .parse::<f32>()
+            .map_err(|_| format!("'{}' is not a number", token))?;
+    }
+    Ok(vec3(values[0], values[1], values[2]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// the four faces of a tetrahedron with its corner on the origin, wound outwards
+    fn tetrahedron() -> Vec<(Vector3<f32>, [Point3<f32>; 3])> {
+        let o = Point3::new(0.0, 0.0, 0.0);
+        let x = Point3::new(1.0, 0.0, 0.0);
+        let y = Point3::new(0.0, 1.0, 0.0);
+        let z = Point3::new(0.0, 0.0, 1.0);
+        let slanted = vec3(1.0, 1.0, 1.0).normalize();
+        vec![
+            (vec3(0.0, 0.0, -1.0), [o, y, x]),
+            (vec3(0.0, -1.0, 0.0), [o, x, z]),
+            (vec3(-1.0, 0.0, 0.0), [o, z, y]),
+            (slanted, [x, y, z]),
+        ]
+    }
+
+    /// `header` padded to 80 bytes, the triangle count and the triangles
+    fn binary(header: &[u8], facets: &[(Vector3<f32>, [Point3<f32>; 3])]) -> Vec<u8> {
+        let mut bytes = header.to_vec();
+        bytes.resize(80, b' ');
+        bytes.extend_from_slice(&(facets.len() as u32).to_le_bytes());
+        for &(normal, corners) in facets {
+            let vectors = [normal, corners[0].to_vec(), corners[1].to_vec(), corners[2].to_vec()];
+            for vector in vectors.iter() {
+                for &component in &[vector.x, vector.y, vector.z] {
+                    bytes.extend_from_slice(&component.to_le_bytes());
+                }
+            }
+            bytes.extend_from_slice(&[0, 0]);
+        }
+        bytes
+    }
+
+    fn ascii(facets: &[(Vector3<f32>, [Point3<f32>; 3])]) -> String {
+        let mut text = "solid tetrahedron\n".to_string();
+        for &(normal, corners) in facets {
+            text += &format!("  facet normal {} {} {}\n    outer loop\n", normal.x, normal.y, normal.z);
+            for corner in corners.iter() {
+                text += &format!("      vertex {} {} {}\n", corner.x, corner.y, corner.z);
+            }
+            text += "    endloop\n  endfacet\n";
+        }
+        text + "endsolid tetrahedron\n"
+    }
+
+    /// every triangle's winding agrees with the normals of its corners
+    fn assert_wound_outwards(data: &MeshData) {
+        for triangle in data.indices.chunks(3) {
+            let [a, b, c] = [triangle[0] as usize, triangle[1] as usize, triangle[2] as usize];
+            let face = (data.positions[b] - data.positions[a]).cross(data.positions[c] - data.positions[a]);
+            for &vertex in &[a, b, c] {
+                assert!(face.dot(data.normals[vertex]) > 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn binary_and_ascii_agree() {
+        let from_binary = parse_stl(&binary(b"made by hand", &tetrahedron())).unwrap();
+        let from_ascii = parse_stl(ascii(&tetrahedron()).as_bytes()).unwrap();
+        assert_eq!(from_binary, from_ascii);
+        from_binary.validate().unwrap();
+        assert_eq!(from_binary.indices.len(), 12);
+        // the faces meet at sharper angles than the smoothing angle, so each keeps its own corners
+        assert_eq!(from_binary.vertex_count(), 12);
+        assert_wound_outwards(&from_binary);
+    }
+
+    #[test]
+    fn binary_header_starting_with_solid() {
+        let data = parse_stl(&binary(b"solid but actually binary", &tetrahedron())).unwrap();
+        assert_eq!(data.indices.len(), 12);
+    }
+
+    #[test]
+    fn welds_corners_and_smooths_flat_facets() {
+        // a square split into two triangles, both facing +z
+        let (a, b, c, d) = (
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(1.0, 1.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+        );
+        let up = vec3(0.0, 0.0, 1.0);
+        let data = parse_stl(&binary(b"", &[(up, [a, b, c]), (up, [a, c, d])])).unwrap();
+        assert_eq!(data.vertex_count(), 4);
+        assert_eq!(data.normals, vec![up; 4]);
+    }
+
+    #[test]
+    fn flips_triangles_against_their_normal() {
+        let flipped: Vec<(Vector3<f32>, [Point3<f32>; 3])> = tetrahedron()
+            .into_iter()
+            .map(|(normal, corners)| (normal, [corners[0], corners[2], corners[1]]))
+            .collect();
+        assert_wound_outwards(&parse_stl(&binary(b"", &flipped)).unwrap());
+        assert_wound_outwards(&parse_stl(ascii(&flipped).as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn truncated_binary() {
+        let bytes = binary(b"", &tetrahedron());
+        for &cut in [1, 50, bytes.len() - 81].iter() {
+            match parse_stl(&bytes[..bytes.len() - cut]) {
+                Err(StlError::Binary(_)) => (),
+                other => panic!("expected a binary error, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn malformed_ascii() {
+        let text = ascii(&tetrahedron());
+        let broken = [
+            text.replace("endfacet\n", ""),
+            text.replacen("  facet normal", "vertex 0 0 0\n  facet normal", 1),
+            text.replacen("vertex 0 0 0", "vertex 0 0", 1),
+            text.replacen("vertex 0 0 0", "vertex 0 zero 0", 1),
+            text.replacen("outer loop", "inner loop\nloop", 1),
+        ];
+        for text in broken.iter() {
+            match parse_stl(text.as_bytes()) {
+                Err(StlError::Parse { .. }) => (),
+                other => panic!("expected a parse error for\n{}\ngot {:?}", text, other),
+            }
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::{Deref, DerefMut, Range};
+use std::ptr;
+use std::slice;
+
+use gl;
+use gl::types::{GLenum, GLintptr, GLsizeiptr, GLsync, GLuint};
+
+// how long a single wait for the GPU may take before waiting again, in nanoseconds
+const WAIT_TIMEOUT: u64 = 1_000_000_000;
+
+/// Buffer for data written every frame, used as a ring so the driver never has to wait for the
+/// GPU to finish reading before it can be written again.
+///
+/// `allocate` hands out a mapped slice after the data written last and returns the offset it
+/// starts at, wrapping around to the start once the end is reached. `fence` has to follow the
+/// draws reading what was written: writing over a range waits for the GPU only when the draws
+/// reading it before haven't finished yet. Everything written between two fences has to fit.
+pub struct StreamBuffer {
+    buffer: GLuint,
+    target: GLenum,
+    capacity: usize,
+    /// byte after the data written last
+    head: usize,
+    /// ranges written since the last fence
+    unfenced: Vec<Range<usize>>,
+    /// ranges written before each fence, oldest first
+    fences: VecDeque<(Vec<Range<usize>>, GLsync)>,
+}
+
+impl StreamBuffer {
+    /// buffer of `capacity` bytes bound to `target` when in use, `ARRAY_BUFFER` for vertices
+    pub unsafe fn new(target: GLenum, capacity: usize) -> StreamBuffer {
+        let mut buffer = 0;
+        gl::GenBuffers(1, &mut buffer);
+        gl::BindBuffer(target, buffer);
+        gl::BufferData(target, capacity as GLsizeiptr, ptr::null(), gl::STREAM_DRAW);
+        gl::BindBuffer(target, 0);
+        StreamBuffer {
+            buffer,
+            target,
+            capacity,
+            head: 0,
+            unfenced: Vec::new(),
+            fences: VecDeque::new(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// bind the buffer to its target, to set up vertex attributes sourced from it
+    pub unsafe fn bind(&self) {
+        gl::BindBuffer(self.target, self.buffer);
+    }
+
+    /// Map room for `count` values at an offset that is a multiple of `alignment` bytes, which has
+    /// to be a multiple of the alignment of `T`. Vertices aligned to the stride of their layout
+    /// can be drawn starting at the offset divided by the stride.
+    ///
+    /// The buffer stays bound to its target. Panics if the values don't fit next to what was
+    /// written since the last fence.
+    pub unsafe fn allocate<T: Copy>(&mut self, count: usize, alignment: usize) -> StreamSlice<'_, T> {
+        assert!(
+            alignment > 0 && alignment.is_multiple_of(mem::align_of::<T>()),
+            "stream buffer offsets have to be aligned to the values written"
+        );
+        let size = count * mem::size_of::<T>();
+        let mut offset = self.head.div_ceil(alignment) * alignment;
+        if offset + size > self.capacity {
+            offset = 0;
+        }
+        let range = offset..offset + size;
+        assert!(
+            range.end <= self.capacity,
+            "{} bytes don't fit a stream buffer of {} bytes",
+            size,
+            self.capacity
+        );
+        assert!(
+            !self.unfenced.iter().any(|written| overlaps(written, &range)),
+            "more was written to a stream buffer of {} bytes since the last fence than fits",
+            self.capacity
+        );
+        self.wait_for(&range);
+        self.head = range.end;
+
+        self.bind();
+        let pointer = if size == 0 {
+            ptr::NonNull::dangling().as_ptr()
+        } else {
+            self.unfenced.push(range);
+            let access = gl::MAP_WRITE_BIT | gl::MAP_INVALIDATE_RANGE_BIT | gl::MAP_UNSYNCHRONIZED_BIT;
+            let pointer = gl::MapBufferRange(self.target, offset as GLintptr, size as GLsizeiptr, access) as *mut T;
+            assert!(!pointer.is_null(), "failed to map {} bytes of a stream buffer", size);
+            assert!((pointer as usize).is_multiple_of(mem::align_of::<T>()), "stream buffer mapped unaligned");
+            pointer
+        };
+        StreamSlice {
+            target: self.target,
+            buffer: self.buffer,
+            offset,
+            mapped: size > 0,
+            data: slice::from_raw_parts_mut(pointer, count),
+            buffer_lifetime: PhantomData,
+        }
+    }
+
+    /// copy `data` into the buffer, returns the offset it starts at, see `allocate`
+    pub unsafe fn write<T: Copy>(&mut self, data: &[T], alignment: usize) -> usize {
+        let mut slice = self.allocate(data.len(), alignment);
+        slice.copy_from_slice(data);
+        slice.offset()
+    }
+
+    /// mark everything written so far as in use by the draws issued since
+    pub unsafe fn fence(&mut self) {
+        if self.unfenced.is_empty() {
+            return;
+        }
+        let sync = gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0);
+        self.fences.push_back((mem::take(&mut self.unfenced), sync));
+    }
+
+    /// wait until the GPU is done with the draws that read `range` before
+    unsafe fn wait_for(&mut self, range: &Range<usize>) {
+        // the GPU finishes commands in order, so the newest fence over the range covers the older ones
+        let newest = match self
+            .fences
+            .iter()
+            .rposition(|(written, _)| written.iter().any(|written| overlaps(written, range)))
+        {
+            Some(newest) => newest,
+            None => return,
+        };
+        let sync = self.fences[newest].1;
+        loop {
+            match gl::ClientWaitSync(sync, gl::SYNC_FLUSH_COMMANDS_BIT, WAIT_TIMEOUT) {
+                gl::TIMEOUT_EXPIRED => continue,
+                gl::WAIT_FAILED => panic!("failed to wait for a stream buffer fence"),
+                _ => break,
+            }
+        }
+        for (_, sync) in self.fences.drain(..=newest) {
+            gl::DeleteSync(sync);
+        }
+    }
+
+    pub unsafe fn delete(mut self) {
+        for (_, sync) in self.fences.drain(..) {
+            gl::DeleteSync(sync);
+        }
+        gl::DeleteBuffers(1, &self.buffer);
+    }
+}
+
+/// Values mapped from a `StreamBuffer` for writing, unmapped when dropped.
+pub struct StreamSlice<'a, T: 'a> {
+    target: GLenum,
+    buffer: GLuint,
+    offset: usize,
+    mapped: bool,
+    data: &'a mut [T],
+    /// nothing else may be written to the buffer until this is unmapped
+    buffer_lifetime: PhantomData<&'a mut StreamBuffer>,
+}
+
+impl<'a, T> StreamSlice<'a, T> {
+    /// bytes from the start of the buffer to the first value
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl<'a, T> Deref for StreamSlice<'a, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.data
+    }
+}
+
+impl<'a, T> DerefMut for StreamSlice<'a, T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        self.data
+    }
+}
+
+impl<'a, T> Drop for StreamSlice<'a, T> {
+    fn drop(&mut self) {
+        if self.mapped {
+            unsafe {
+                gl::BindBuffer(self.target, self.buffer);
+                gl::UnmapBuffer(self.target);
+            }
+        }
+    }
+}
+
+fn overlaps(a: &Range<usize>, b: &Range<usize>) -> bool {
+    a.start < b.end && b.start < a.end
+}
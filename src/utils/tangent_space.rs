@@ -0,0 +1,646 @@
+use std::collections::HashMap;
+
+use cgmath::prelude::*;
+use cgmath::{vec3, Deg, Point3, Rad, Vector3, Vector4};
+
+use utils::mesh::MeshError;
+use utils::mesh_data::{MeshData, Semantic};
+
+// faces within this much of the smoothing angle still count as inside it
+const ANGLE_EPSILON: f32 = 1e-5;
+
+/// Replace the normals of a mesh with ones computed from its triangles.
+///
+/// Every corner gets the average normal of the faces around its position whose normals are at
+/// most `smoothing_angle` apart from its own face, weighted by the angle of the faces at the
+/// corner. 0 gives flat shading, 180 smooths everything. Faces are found by position, so
+/// vertices split by texture coords are still smoothed over. Vertices that end up needing more
+/// than one normal are split. Tangents are dropped, they no longer match the normals.
+pub fn generate_normals(data: &mut MeshData, smoothing_angle: Deg<f32>) -> Result<(), MeshError> {
+    data.validate()?;
+    let min_cos = Rad::from(smoothing_angle).0.cos() - ANGLE_EPSILON;
+    let face_normals: Vec<Vector3<f32>> = data
+        .indices
+        .chunks(3)
+        .map(|triangle| {
+            let normal = face_normal(data, triangle);
+            if normal.magnitude2() > 0.0 {
+                normal.normalize()
+            } else {
+                normal
+            }
+        }).collect();
+    let angles = corner_angles(data);
+
+    let mut corners_at: HashMap<[u32; 3], Vec<usize>> = HashMap::new();
+    for (corner, &vertex) in data.indices.iter().enumerate() {
+        corners_at
+            .entry(position_key(data.positions[vertex as usize]))
+            .or_default()
+            .push(corner);
+    }
+
+    let mut corner_normals = vec![Vector3::zero(); data.indices.len()];
+    for corners in corners_at.values() {
+        for &corner in corners {
+            let own = face_normals[corner / 3];
+            let mut sum: Vector3<f32> = corners
+                .iter()
+                .filter(|&&other| other == corner || own.dot(face_normals[other / 3]) >= min_cos)
+                .map(|&other| face_normals[other / 3] * angles[other])
+                .sum();
+            if sum.magnitude2() == 0.0 {
+                // only degenerate faces here, fall back to whatever the neighbours say
+                sum = corners.iter().map(|&other| face_normals[other / 3]).sum();
+            }
+            corner_normals[corner] = if sum.magnitude2() > 0.0 {
+                sum.normalize()
+            } else {
+                vec3(0.0, 1.0, 0.0)
+            };
+        }
+    }
+
+    data.tangents.clear();
+    data.normals = vec![Vector3::zero(); data.vertex_count()];
+    split_corners(data, &corner_normals, |data, vertex, normal| data.normals[vertex] = *normal);
+    Ok(())
+}
+
+/// Compute MikkTSpace tangents from the normals and texture coords of a mesh.
+///
+/// These match the tangents normal maps are baked against by most tools. Corners with the same
+/// position, normal and texture coords are one vertex, its tangent is the average of the direction
+/// u increases in over the triangles around it, each projected onto the normal and weighted by
+/// its angle at the corner. Triangles only share a tangent if they're connected through edges
+/// around the vertex and have the same handedness, vertices whose corners disagree are split.
+/// The handedness in `w` says whether the bitangent is `normal x tangent` or points the other way.
+///
+/// Where MikkTSpace leaves a corner without a tangent, because none of its triangles has texture
+/// space, it gets the tangent of another corner of its vertex or one perpendicular to the normal.
+pub fn generate_tangents(data: &mut MeshData) -> Result<(), MeshError> {
+    data.validate()?;
+    for &semantic in [Semantic::Normal, Semantic::TexCoord].iter() {
+        if !data.has(semantic) {
+            return Err(MeshError::StreamLength {
+                stream: semantic.name(),
+                len: 0,
+                vertex_count: data.vertex_count(),
+            });
+        }
+    }
+
+    let welded = weld_corners(data);
+    let mut triangles: Vec<TriangleSpace> = data
+        .indices
+        .chunks(3)
+        .map(|triangle| TriangleSpace::new(data, triangle))
+        .collect();
+    // triangles with two corners at the same position take no part, their corners are filled in at the end
+    let degenerate: Vec<bool> = welded
+        .chunks(3)
+        .map(|triangle| {
+            let [a, b, c] = [0, 1, 2].map(|k| data.positions[triangle[k] as usize]);
+            a == b || a == c || b == c
+        }).collect();
+    let neighbors = triangle_neighbors(&welded, &degenerate);
+
+    // group the triangles around each vertex that are connected through edges and have the same handedness
+    let mut groups: Vec<TangentGroup> = Vec::new();
+    let mut corner_groups: Vec<Option<usize>> = vec![None; data.indices.len()];
+    for triangle in 0..triangles.len() {
+        if degenerate[triangle] || triangles[triangle].any_handedness {
+            continue;
+        }
+        for k in 0..3 {
+            if corner_groups[triangle * 3 + k].is_some() {
+                continue;
+            }
+            groups.push(TangentGroup {
+                vertex: welded[triangle * 3 + k],
+                right_handed: triangles[triangle].right_handed,
+                triangles: Vec::new(),
+            });
+            let group = groups.len() - 1;
+            groups[group].triangles.push(triangle);
+            corner_groups[triangle * 3 + k] = Some(group);
+            for &neighbor in &[neighbors[triangle * 3 + k], neighbors[triangle * 3 + (k + 2) % 3]] {
+                if let Some(neighbor) = neighbor {
+                    assign_to_group(
+                        neighbor,
+                        group,
+                        &mut groups,
+                        &mut corner_groups,
+                        &mut triangles,
+                        &welded,
+                        &neighbors,
+                    );
+                }
+            }
+        }
+    }
+
+    let mut corner_tangents: Vec<Option<Vector4<f32>>> = vec![None; data.indices.len()];
+    for (index, group) in groups.iter().enumerate() {
+        let normal = data.normals[group.vertex as usize];
+        let projected: Vec<(Vector3<f32>, Vector3<f32>)> = group
+            .triangles
+            .iter()
+            .map(|&triangle| {
+                let space = &triangles[triangle];
+                (project(space.u_direction, normal), project(space.v_direction, normal))
+            }).collect();
+        let w = if group.right_handed { 1.0 } else { -1.0 };
+        // MikkTSpace leaves triangles out whose texture directions are turned by more than its
+        // default threshold of 180 degrees, which only leaves out exactly opposite ones
+        let mut evaluated: Vec<(Vec<usize>, Vector3<f32>)> = Vec::new();
+        for (member, &triangle) in group.triangles.iter().enumerate() {
+            let mut similar: Vec<usize> = (0..group.triangles.len())
+                .filter(|&other| {
+                    let (u, v) = projected[member];
+                    let (other_u, other_v) = projected[other];
+                    other == member
+                        || triangles[triangle].any_handedness
+                        || triangles[group.triangles[other]].any_handedness
+                        || (u.dot(other_u) > -1.0 && v.dot(other_v) > -1.0)
+                }).map(|other| group.triangles[other])
+                .collect();
+            similar.sort_unstable();
+            let tangent = match evaluated.iter().find(|(members, _)| *members == similar) {
+                Some(&(_, tangent)) => tangent,
+                None => {
+                    let tangent = average_tangent(data, &welded, &triangles, &similar, group.vertex, normal);
+                    evaluated.push((similar, tangent));
+                    tangent
+                }
+            };
+            let k = (0..3).find(|&k| corner_groups[triangle * 3 + k] == Some(index)).unwrap();
+            corner_tangents[triangle * 3 + k] = Some(tangent.extend(w));
+        }
+    }
+
+    // corners left over take the tangent of another corner of their vertex
+    let mut vertex_tangents: HashMap<u32, Vector4<f32>> = HashMap::new();
+    for (corner, tangent) in corner_tangents.iter().enumerate() {
+        if let Some(tangent) = *tangent {
+            vertex_tangents.entry(welded[corner]).or_insert(tangent);
+        }
+    }
+    let corner_tangents: Vec<Vector4<f32>> = corner_tangents
+        .iter()
+        .enumerate()
+        .map(|(corner, tangent)| match (*tangent, vertex_tangents.get(&welded[corner])) {
+            (Some(tangent), _) | (None, Some(&tangent)) => tangent,
+            (None, None) => any_perpendicular(data.normals[data.indices[corner] as usize]).extend(1.0),
+        }).collect();
+
+    data.tangents = vec![Vector4::zero(); data.vertex_count()];
+    split_corners(data, &corner_tangents, |data, vertex, tangent| data.tangents[vertex] = *tangent);
+    Ok(())
+}
+
+/// texture space of a triangle
+struct TriangleSpace {
+    /// direction u increases in, normalized
+    u_direction: Vector3<f32>,
+    /// direction v increases in, normalized
+    v_direction: Vector3<f32>,
+    right_handed: bool,
+    /// the texture coords have no area, the triangle joins whichever group reaches it first
+    any_handedness: bool,
+}
+
+impl TriangleSpace {
+    fn new(data: &MeshData, triangle: &[u32]) -> TriangleSpace {
+        let [a, b, c] = [triangle[0] as usize, triangle[1] as usize, triangle[2] as usize];
+        let edge_1 = data.positions[b] - data.positions[a];
+        let edge_2 = data.positions[c] - data.positions[a];
+        let uv_1 = data.tex_coords[b] - data.tex_coords[a];
+        let uv_2 = data.tex_coords[c] - data.tex_coords[a];
+        let area = uv_1.x * uv_2.y - uv_1.y * uv_2.x;
+        let right_handed = area > 0.0;
+        let mut space = TriangleSpace {
+            u_direction: Vector3::zero(),
+            v_direction: Vector3::zero(),
+            right_handed,
+            any_handedness: true,
+        };
+        if is_zero(area) {
+            return space;
+        }
+        // scaled by the area of the texture coords, which the sign takes out again
+        let u_direction = edge_1 * uv_2.y - edge_2 * uv_1.y;
+        let v_direction = edge_2 * uv_1.x - edge_1 * uv_2.x;
+        let sign = if right_handed { 1.0 } else { -1.0 };
+        let (u_length, v_length) = (u_direction.magnitude(), v_direction.magnitude());
+        if !is_zero(u_length) {
+            space.u_direction = u_direction * (sign / u_length);
+        }
+        if !is_zero(v_length) {
+            space.v_direction = v_direction * (sign / v_length);
+        }
+        space.any_handedness = is_zero(u_length / area.abs()) || is_zero(v_length / area.abs());
+        space
+    }
+}
+
+/// triangles around a vertex sharing a tangent, before leaving out opposite ones
+struct TangentGroup {
+    /// the vertex as returned by `weld_corners`
+    vertex: u32,
+    right_handed: bool,
+    triangles: Vec<usize>,
+}
+
+/// Add a triangle to a group if it has the group's handedness, then its neighbors around the
+/// group's vertex. Triangles that could have either handedness take the group's.
+fn assign_to_group(
+    triangle: usize,
+    group: usize,
+    groups: &mut Vec<TangentGroup>,
+    corner_groups: &mut [Option<usize>],
+    triangles: &mut [TriangleSpace],
+    welded: &[u32],
+    neighbors: &[Option<usize>],
+) {
+    let k = match (0..3).find(|&k| welded[triangle * 3 + k] == groups[group].vertex) {
+        Some(k) => k,
+        None => return,
+    };
+    if corner_groups[triangle * 3 + k].is_some() {
+        return;
+    }
+    let unassigned = corner_groups[triangle * 3..triangle * 3 + 3].iter().all(Option::is_none);
+    if triangles[triangle].any_handedness && unassigned {
+        triangles[triangle].right_handed = groups[group].right_handed;
+    }
+    if triangles[triangle].right_handed != groups[group].right_handed {
+        return;
+    }
+    groups[group].triangles.push(triangle);
+    corner_groups[triangle * 3 + k] = Some(group);
+    for &neighbor in &[neighbors[triangle * 3 + k], neighbors[triangle * 3 + (k + 2) % 3]] {
+        if let Some(neighbor) = neighbor {
+            assign_to_group(neighbor, group, groups, corner_groups, triangles, welded, neighbors);
+        }
+    }
+}
+
+/// u directions of `members` projected onto the normal at `vertex`, weighted by the angles there
+fn average_tangent(
+    data: &MeshData,
+    welded: &[u32],
+    triangles: &[TriangleSpace],
+    members: &[usize],
+    vertex: u32,
+    normal: Vector3<f32>,
+) -> Vector3<f32> {
+    let mut sum = Vector3::zero();
+    for &triangle in members.iter().filter(|&&triangle| !triangles[triangle].any_handedness) {
+        let k = (0..3).find(|&k| welded[triangle * 3 + k] == vertex).unwrap();
+        let position = |k: usize| data.positions[welded[triangle * 3 + k % 3] as usize];
+        let previous = project(position(k + 2) - position(k), normal);
+        let next = project(position(k + 1) - position(k), normal);
+        let angle = previous.dot(next).clamp(-1.0, 1.0).acos();
+        sum += project(triangles[triangle].u_direction, normal) * angle;
+    }
+    if is_zero_vector(sum) {
+        any_perpendicular(normal)
+    } else {
+        sum.normalize()
+    }
+}
+
+/// `vector` in the plane perpendicular to `normal`, normalized unless it's zero
+fn project(vector: Vector3<f32>, normal: Vector3<f32>) -> Vector3<f32> {
+    let projected = vector - normal * normal.dot(vector);
+    if is_zero_vector(projected) {
+        projected
+    } else {
+        projected.normalize()
+    }
+}
+
+/// zero as far as MikkTSpace is concerned, which is anything smaller than the smallest normal float
+fn is_zero(value: f32) -> bool {
+    value.abs() <= f32::MIN_POSITIVE
+}
+
+fn is_zero_vector(vector: Vector3<f32>) -> bool {
+    is_zero(vector.x) && is_zero(vector.y) && is_zero(vector.z)
+}
+
+type CornerKey = ([u32; 3], [u32; 3], [u32; 2]);
+
+/// for every corner the first vertex with the same position, normal and texture coords
+fn weld_corners(data: &MeshData) -> Vec<u32> {
+    let mut first: HashMap<CornerKey, u32> = HashMap::new();
+    data.indices
+        .iter()
+        .map(|&vertex| {
+            let normal = data.normals[vertex as usize];
+            let tex_coord = data.tex_coords[vertex as usize];
+            let key = (
+                position_key(data.positions[vertex as usize]),
+                position_key(Point3::from_vec(normal)),
+                [(tex_coord.x + 0.0).to_bits(), (tex_coord.y + 0.0).to_bits()],
+            );
+            *first.entry(key).or_insert(vertex)
+        }).collect()
+}
+
+/// For every corner the triangle on the other side of the edge from it to the next corner.
+///
+/// Only triangles wound the same way are neighbors, an edge shared by more than two triangles
+/// pairs each with the next one that's still free.
+fn triangle_neighbors(welded: &[u32], degenerate: &[bool]) -> Vec<Option<usize>> {
+    let edge = |corner: usize| (welded[corner], welded[corner - corner % 3 + (corner + 1) % 3]);
+    let mut edges: HashMap<(u32, u32), Vec<usize>> = HashMap::new();
+    for corner in 0..welded.len() {
+        if !degenerate[corner / 3] {
+            edges.entry(edge(corner)).or_default().push(corner);
+        }
+    }
+    let mut neighbors = vec![None; welded.len()];
+    for corner in 0..welded.len() {
+        if degenerate[corner / 3] || neighbors[corner].is_some() {
+            continue;
+        }
+        let (from, to) = edge(corner);
+        let other = edges.get(&(to, from)).and_then(|corners| {
+            corners
+                .iter()
+                .cloned()
+                .find(|&other| other / 3 > corner / 3 && neighbors[other].is_none())
+        });
+        if let Some(other) = other {
+            neighbors[corner] = Some(other / 3);
+            neighbors[other] = Some(corner / 3);
+        }
+    }
+    neighbors
+}
+
+/// the bitangent belonging to a normal and a tangent from `generate_tangents`
+pub fn bitangent(normal: Vector3<f32>, tangent: Vector4<f32>) -> Vector3<f32> {
+    normal.cross(tangent.truncate()) * tangent.w
+}
+
+/// Give every corner the value computed for it, splitting vertices whose corners disagree.
+///
+/// The first value seen for a vertex stays with it, every other value gets a copy of the vertex.
+fn split_corners<T, F>(data: &mut MeshData, corner_values: &[T], set: F)
+where
+    T: Copy + PartialEq,
+    F: Fn(&mut MeshData, usize, &T),
+{
+    let mut vertex_values: Vec<Vec<(T, u32)>> = vec![Vec::new(); data.vertex_count()];
+    for (corner, value) in corner_values.iter().enumerate() {
+        let vertex = data.indices[corner] as usize;
+        let existing = vertex_values[vertex]
+            .iter()
+            .find(|&&(existing, _)| existing == *value)
+            .map(|&(_, index)| index);
+        let index = match existing {
+            Some(index) => index,
+            None => {
+                let index = if vertex_values[vertex].is_empty() {
+                    vertex as u32
+                } else {
+                    data.duplicate_vertex(vertex)
+                };
+                set(data, index as usize, value);
+                vertex_values[vertex].push((*value, index));
+                index
+            }
+        };
+        data.indices[corner] = index;
+    }
+}
+
+/// normal of a triangle scaled by twice its area
+fn face_normal(data: &MeshData, triangle: &[u32]) -> Vector3<f32> {
+    let a = data.positions[triangle[0] as usize];
+    let b = data.positions[triangle[1] as usize];
+    let c = data.positions[triangle[2] as usize];
+    (b - a).cross(c - a)
+}
+
+/// angle of each triangle at each of its corners, in radians
+fn corner_angles(data: &MeshData) -> Vec<f32> {
+    let mut angles = Vec::with_capacity(data.indices.len());
+    for triangle in data.indices.chunks(3) {
+        for k in 0..3 {
+            let corner = data.positions[triangle[k] as usize];
+            let next = data.positions[triangle[(k + 1) % 3] as usize] - corner;
+            let previous = data.positions[triangle[(k + 2) % 3] as usize] - corner;
+            angles.push(if next.magnitude2() > 0.0 && previous.magnitude2() > 0.0 {
+                next.angle(previous).0
+            } else {
+                0.0
+            });
+        }
+    }
+    angles
+}
+
+/// bit pattern of a position, with -0 and 0 treated as the same
+fn position_key(position: Point3<f32>) -> [u32; 3] {
+    [
+        (position.x + 0.0).to_bits(),
+        (position.y + 0.0).to_bits(),
+        (position.z + 0.0).to_bits(),
+    ]
+}
+
+fn any_perpendicular(normal: Vector3<f32>) -> Vector3<f32> {
+    let axis = if normal.x.abs() < 0.9 {
+        vec3(1.0, 0.0, 0.0)
+    } else {
+        vec3(0.0, 1.0, 0.0)
+    };
+    (axis - normal * normal.dot(axis)).normalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::shapes;
+    use cgmath::vec2;
+
+    /// shapes with analytic tangents, fine enough for the per triangle directions to stay close to them
+    fn shapes() -> Vec<(&'static str, MeshData)> {
+        vec![
+            ("cube", shapes::cube(1.0)),
+            ("uv_sphere", shapes::uv_sphere(1.0, 32, 16)),
+            ("icosphere", shapes::icosphere(1.0, 3)),
+            ("torus", shapes::torus(1.0, 0.3, 32, 16)),
+        ]
+    }
+
+    /// directions u and v increase in across a triangle
+    fn texture_directions(data: &MeshData, triangle: &[u32]) -> (Vector3<f32>, Vector3<f32>) {
+        let [a, b, c] = [triangle[0] as usize, triangle[1] as usize, triangle[2] as usize];
+        let edge_1 = data.positions[b] - data.positions[a];
+        let edge_2 = data.positions[c] - data.positions[a];
+        let uv_1 = data.tex_coords[b] - data.tex_coords[a];
+        let uv_2 = data.tex_coords[c] - data.tex_coords[a];
+        let determinant = uv_1.x * uv_2.y - uv_2.x * uv_1.y;
+        (
+            (edge_1 * uv_2.y - edge_2 * uv_1.y) / determinant,
+            (edge_2 * uv_1.x - edge_1 * uv_2.x) / determinant,
+        )
+    }
+
+    #[test]
+    fn flat_cube_normals() {
+        let cube = shapes::cube(2.0);
+        // positions only, welded into the 8 corners
+        let mut welded = MeshData::default();
+        let mut corners: HashMap<[u32; 3], u32> = HashMap::new();
+        for &vertex in &cube.indices {
+            let position = cube.positions[vertex as usize];
+            let index = *corners.entry(position_key(position)).or_insert_with(|| {
+                welded.positions.push(position);
+                welded.positions.len() as u32 - 1
+            });
+            welded.indices.push(index);
+        }
+        assert_eq!(welded.vertex_count(), 8);
+
+        generate_normals(&mut welded, Deg(60.0)).unwrap();
+        // every face gets its own 4 vertices back
+        assert_eq!(welded.vertex_count(), 24);
+        for (corner, &vertex) in welded.indices.iter().enumerate() {
+            let expected = cube.normals[cube.indices[corner] as usize];
+            let normal = welded.normals[vertex as usize];
+            assert!((normal - expected).magnitude() < 1e-5, "{:?} != {:?}", normal, expected);
+            assert_eq!(welded.positions[vertex as usize], cube.positions[cube.indices[corner] as usize]);
+        }
+    }
+
+    #[test]
+    fn smooth_sphere_normals() {
+        for &(name, ref sphere) in &[("uv_sphere", shapes::uv_sphere(2.0, 32, 16)), ("icosphere", shapes::icosphere(2.0, 3))] {
+            let mut data = sphere.clone();
+            data.normals.clear();
+            data.tangents.clear();
+            generate_normals(&mut data, Deg(60.0)).unwrap();
+            // smoothed over the seam, so nothing had to be split
+            assert_eq!(data.vertex_count(), sphere.vertex_count(), "{}", name);
+            for &vertex in &data.indices {
+                let normal = data.normals[vertex as usize];
+                let expected = data.positions[vertex as usize].to_vec().normalize();
+                assert!((normal.magnitude() - 1.0).abs() < 1e-5, "{}", name);
+                assert!(normal.dot(expected) > 0.999, "{}: {:?} != {:?}", name, normal, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn tangents_follow_u() {
+        for (name, shape) in shapes() {
+            let mut data = shape.clone();
+            data.tangents.clear();
+            generate_tangents(&mut data).unwrap();
+            data.validate().unwrap();
+            for (triangle_index, triangle) in data.indices.chunks(3).enumerate() {
+                let (u_direction, v_direction) = texture_directions(&data, triangle);
+                for (k, &vertex) in triangle.iter().enumerate() {
+                    let tangent = data.tangents[vertex as usize];
+                    let normal = data.normals[vertex as usize];
+                    let analytic = shape.tangents[shape.indices[triangle_index * 3 + k] as usize];
+                    assert!((tangent.truncate().magnitude() - 1.0).abs() < 1e-5, "{}", name);
+                    assert!(tangent.truncate().dot(normal).abs() < 1e-5, "{}", name);
+                    assert!(tangent.truncate().dot(u_direction) > 0.0, "{}: {:?} against {:?}", name, tangent, u_direction);
+                    assert!(bitangent(normal, tangent).dot(v_direction) > 0.0, "{}: {:?}", name, tangent);
+                    assert_eq!(tangent.w, analytic.w, "{}", name);
+                    assert!(tangent.truncate().dot(analytic.truncate()) > 0.8, "{}: {:?} != {:?}", name, tangent, analytic);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn mirrored_texture_flips_handedness() {
+        let mut quad = shapes::quad(1.0, 1.0);
+        for tex_coord in quad.tex_coords.iter_mut() {
+            tex_coord.x = 1.0 - tex_coord.x;
+        }
+        quad.tangents.clear();
+        generate_tangents(&mut quad).unwrap();
+        assert_eq!(quad.vertex_count(), 4);
+        for &tangent in &quad.tangents {
+            assert!((tangent - Vector4::new(-1.0, 0.0, 0.0, -1.0)).magnitude() < 1e-5, "{:?}", tangent);
+            assert!((bitangent(vec3(0.0, 0.0, 1.0), tangent) - vec3(0.0, 1.0, 0.0)).magnitude() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn tangents_need_normals_and_tex_coords() {
+        let mut data = shapes::cube(1.0);
+        data.normals.clear();
+        assert!(generate_tangents(&mut data).is_err());
+        let mut data = shapes::cube(1.0);
+        data.tex_coords.clear();
+        assert!(generate_tangents(&mut data).is_err());
+    }
+
+    #[test]
+    fn tangents_match_mikktspace() {
+        // a bent strip with skewed texture coords, mirrored on the right half
+        let mut data = MeshData {
+            positions: vec![
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, 0.2),
+                Point3::new(2.0, 0.0, 0.0),
+                Point3::new(0.0, 1.0, 0.3),
+                Point3::new(1.0, 1.0, 0.6),
+                Point3::new(2.0, 1.0, 0.1),
+            ],
+            normals: vec![
+                vec3(-0.1, -0.3, 1.0).normalize(),
+                vec3(0.0, -0.4, 1.0).normalize(),
+                vec3(0.2, -0.1, 1.0).normalize(),
+                vec3(-0.3, -0.2, 1.0).normalize(),
+                vec3(0.0, -0.5, 1.0).normalize(),
+                vec3(0.4, 0.1, 1.0).normalize(),
+            ],
+            tex_coords: vec![
+                vec2(0.0, 0.0),
+                vec2(0.6, 0.1),
+                vec2(1.0, 0.0),
+                vec2(0.1, 1.0),
+                vec2(0.5, 0.9),
+                vec2(0.2, -0.5),
+            ],
+            indices: vec![0, 1, 4, 0, 4, 3, 1, 2, 5, 1, 5, 4],
+            ..MeshData::default()
+        };
+        // per corner, from the reference implementation of MikkTSpace
+        let reference = [
+            [0.99416804, 0.021121297, 0.1057532, 1.0],
+            [0.99818385, -0.05593272, -0.022373121, 1.0],
+            [0.9954284, 0.08542655, 0.042713284, 1.0],
+            [0.99416804, 0.021121297, 0.1057532, 1.0],
+            [0.9954284, 0.08542655, 0.042713284, 1.0],
+            [0.94792295, 0.09621462, 0.30361983, 1.0],
+            [0.348806, -0.8701637, -0.34806547, -1.0],
+            [0.9585301, -0.19170603, -0.21087663, -1.0],
+            [0.45917654, -0.88321316, -0.095349304, -1.0],
+            [0.348806, -0.8701637, -0.34806547, -1.0],
+            [0.45917654, -0.88321316, -0.095349304, -1.0],
+            [-0.5172259, -0.76549447, -0.38274723, -1.0],
+        ];
+
+        generate_tangents(&mut data).unwrap();
+        // the vertices shared by both halves are split
+        assert_eq!(data.vertex_count(), 8);
+        for (corner, expected) in reference.iter().enumerate() {
+            let tangent = data.tangents[data.indices[corner] as usize];
+            let expected = Vector4::new(expected[0], expected[1], expected[2], expected[3]);
+            let error = (tangent - expected).magnitude();
+            assert!(error < 1e-5, "corner {}: {:?}, expected {:?}", corner, tangent, expected);
+        }
+    }
+}
@@ -0,0 +1,276 @@
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_void};
+use std::path::Path;
+
+use gl;
+use gl::types::{GLenum, GLfloat, GLint, GLuint};
+
+use image;
+use image::{DynamicImage, GenericImageView};
+
+use cgmath::{vec4, Vector4};
+
+// from EXT_texture_filter_anisotropic, core since OpenGL 4.6
+const TEXTURE_MAX_ANISOTROPY: GLenum = 0x84FE;
+const MAX_TEXTURE_MAX_ANISOTROPY: GLenum = 0x84FF;
+const ANISOTROPY_EXTENSIONS: [&str; 2] = ["GL_EXT_texture_filter_anisotropic", "GL_ARB_texture_filter_anisotropic"];
+
+/// what happens to texture coordinates outside [0, 1]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wrap {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+    /// the border color of the `TextureOptions`
+    ClampToBorder,
+}
+
+impl Wrap {
+    /// wrap mode of a `GL_TEXTURE_WRAP_*` parameter value
+    pub fn from_gl(value: GLenum) -> Option<Wrap> {
+        match value {
+            gl::REPEAT => Some(Wrap::Repeat),
+            gl::MIRRORED_REPEAT => Some(Wrap::MirroredRepeat),
+            gl::CLAMP_TO_EDGE => Some(Wrap::ClampToEdge),
+            gl::CLAMP_TO_BORDER => Some(Wrap::ClampToBorder),
+            _ => None,
+        }
+    }
+
+    fn gl(self) -> GLenum {
+        match self {
+            Wrap::Repeat => gl::REPEAT,
+            Wrap::MirroredRepeat => gl::MIRRORED_REPEAT,
+            Wrap::ClampToEdge => gl::CLAMP_TO_EDGE,
+            Wrap::ClampToBorder => gl::CLAMP_TO_BORDER,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    Nearest,
+    Linear,
+}
+
+/// How a texture is sampled, built up from the defaults: repeating, trilinear filtering and
+/// no anisotropic filtering.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextureOptions {
+    pub wrap_s: Wrap,
+    pub wrap_t: Wrap,
+    pub wrap_r: Wrap,
+    pub min_filter: Filter,
+    pub mag_filter: Filter,
+    /// generate mipmaps and sample them when minifying
+    pub mipmaps: bool,
+    /// how neighbouring mipmap levels are blended
+    pub mipmap_filter: Filter,
+    pub border_color: Vector4<f32>,
+    /// samples taken along the direction a texture is stretched in, 1 turns it off,
+    /// limited to what the driver supports and ignored without support
+    pub anisotropy: f32,
+}
+
+impl Default for TextureOptions {
+    fn default() -> Self {
+        TextureOptions {
+            wrap_s: Wrap::Repeat,
+            wrap_t: Wrap::Repeat,
+            wrap_r: Wrap::Repeat,
+            min_filter: Filter::Linear,
+            mag_filter: Filter::Linear,
+            mipmaps: true,
+            mipmap_filter: Filter::Linear,
+            border_color: vec4(0.0, 0.0, 0.0, 0.0),
+            anisotropy: 1.0,
+        }
+    }
+}
+
+impl TextureOptions {
+    /// the same wrap mode in every direction
+    pub fn wrap(self, wrap: Wrap) -> Self {
+        TextureOptions {
+            wrap_s: wrap,
+            wrap_t: wrap,
+            wrap_r: wrap,
+            ..self
+        }
+    }
+
+    pub fn wrap_s(self, wrap_s: Wrap) -> Self {
+        TextureOptions { wrap_s, ..self }
+    }
+
+    pub fn wrap_t(self, wrap_t: Wrap) -> Self {
+        TextureOptions { wrap_t, ..self }
+    }
+
+    pub fn wrap_r(self, wrap_r: Wrap) -> Self {
+        TextureOptions { wrap_r, ..self }
+    }
+
+    pub fn min_filter(self, min_filter: Filter) -> Self {
+        TextureOptions { min_filter, ..self }
+    }
+
+    pub fn mag_filter(self, mag_filter: Filter) -> Self {
+        TextureOptions { mag_filter, ..self }
+    }
+
+    pub fn mipmaps(self, mipmaps: bool) -> Self {
+        TextureOptions { mipmaps, ..self }
+    }
+
+    pub fn mipmap_filter(self, mipmap_filter: Filter) -> Self {
+        TextureOptions { mipmap_filter, ..self }
+    }
+
+    pub fn border_color(self, border_color: Vector4<f32>) -> Self {
+        TextureOptions { border_color, ..self }
+    }
+
+    pub fn anisotropy(self, anisotropy: f32) -> Self {
+        TextureOptions { anisotropy, ..self }
+    }
+
+    fn gl_min_filter(&self) -> GLenum {
+        match (self.min_filter, self.mipmaps, self.mipmap_filter) {
+            (Filter::Nearest, false, _) => gl::NEAREST,
+            (Filter::Linear, false, _) => gl::LINEAR,
+            (Filter::Nearest, true, Filter::Nearest) => gl::NEAREST_MIPMAP_NEAREST,
+            (Filter::Linear, true, Filter::Nearest) => gl::LINEAR_MIPMAP_NEAREST,
+            (Filter::Nearest, true, Filter::Linear) => gl::NEAREST_MIPMAP_LINEAR,
+            (Filter::Linear, true, Filter::Linear) => gl::LINEAR_MIPMAP_LINEAR,
+        }
+    }
+
+    fn gl_mag_filter(&self) -> GLenum {
+        match self.mag_filter {
+            Filter::Nearest => gl::NEAREST,
+            Filter::Linear => gl::LINEAR,
+        }
+    }
+}
+
+/// one of the texture units textures are bound to, which sampler uniforms refer to by number
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextureUnit(pub u32);
+
+impl TextureUnit {
+    /// value for the sampler uniforms reading from this unit
+    pub fn sampler(self) -> i32 {
+        self.0 as i32
+    }
+
+    /// make this the unit texture calls act on
+    pub unsafe fn activate(self) {
+        gl::ActiveTexture(gl::TEXTURE0 + self.0);
+    }
+
+    /// leave this unit without a 2D texture
+    pub unsafe fn unbind(self) {
+        self.activate();
+        gl::BindTexture(gl::TEXTURE_2D, 0);
+    }
+}
+
+/// 2D RGBA texture, deleted when dropped.
+pub struct Texture {
+    id: GLuint,
+    width: u32,
+    height: u32,
+}
+
+impl Texture {
+    /// texture from an image file, see `from_image`
+    pub unsafe fn load(path: &Path, options: &TextureOptions) -> image::ImageResult<Texture> {
+        Ok(Texture::from_image(&image::open(path)?, options))
+    }
+
+    /// texture from a decoded image, flipped so its first row ends up at texture coordinate 0
+    pub unsafe fn from_image(image: &DynamicImage, options: &TextureOptions) -> Texture {
+        let image = image.flipv();
+        let (width, height) = image.dimensions();
+        Texture::from_rgba(width, height, &image.to_rgba().into_raw(), options)
+    }
+
+    /// texture from 8 bit RGBA pixels, row by row from the bottom
+    pub unsafe fn from_rgba(width: u32, height: u32, pixels: &[u8], options: &TextureOptions) -> Texture {
+        assert_eq!(pixels.len(), width as usize * height as usize * 4, "four bytes per pixel");
+        let mut id = 0;
+        gl::GenTextures(1, &mut id);
+        gl::BindTexture(gl::TEXTURE_2D, id);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, options.wrap_s.gl() as GLint);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, options.wrap_t.gl() as GLint);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_R, options.wrap_r.gl() as GLint);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, options.gl_min_filter() as GLint);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, options.gl_mag_filter() as GLint);
+        let border: [GLfloat; 4] = options.border_color.into();
+        gl::TexParameterfv(gl::TEXTURE_2D, gl::TEXTURE_BORDER_COLOR, border.as_ptr());
+        if options.anisotropy > 1.0 {
+            if let Some(max_anisotropy) = max_anisotropy() {
+                gl::TexParameterf(gl::TEXTURE_2D, TEXTURE_MAX_ANISOTROPY, options.anisotropy.min(max_anisotropy));
+            }
+        }
+
+        gl::TexImage2D(
+            gl::TEXTURE_2D,
+            0,
+            gl::RGBA as GLint,
+            width as GLint,
+            height as GLint,
+            0,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            pixels.as_ptr() as *const c_void,
+        );
+        if options.mipmaps {
+            gl::GenerateMipmap(gl::TEXTURE_2D);
+        }
+        gl::BindTexture(gl::TEXTURE_2D, 0);
+
+        Texture { id, width, height }
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// bind to `unit`, which stays the active unit
+    pub unsafe fn bind(&self, unit: TextureUnit) {
+        unit.activate();
+        gl::BindTexture(gl::TEXTURE_2D, self.id);
+    }
+}
+
+impl Drop for Texture {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.id);
+        }
+    }
+}
+
+/// largest anisotropy the driver supports, `None` without anisotropic filtering
+unsafe fn max_anisotropy() -> Option<f32> {
+    let mut count: GLint = 0;
+    gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut count);
+    let supported = (0..count as GLuint).any(|index| {
+        let name = gl::GetStringi(gl::EXTENSIONS, index);
+        !name.is_null() && ANISOTROPY_EXTENSIONS.iter().any(|&extension| {
+            CStr::from_ptr(name as *const c_char).to_bytes() == extension.as_bytes()
+        })
+    });
+    if !supported {
+        return None;
+    }
+    let mut max_anisotropy: GLfloat = 1.0;
+    gl::GetFloatv(MAX_TEXTURE_MAX_ANISOTROPY, &mut max_anisotropy);
+    Some(max_anisotropy)
+}
@@ -0,0 +1,40 @@
+use cgmath::prelude::*;
+use cgmath::{Deg, Matrix4, Quaternion, Vector3};
+
+use utils::camera::slerp_shortest;
+use utils::interpolation::Interpolate;
+
+/// position and rotation of an object, kept apart so states can be interpolated
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub position: Vector3<f32>,
+    pub rotation: Quaternion<f32>,
+}
+
+impl Transform {
+    pub fn new(position: Vector3<f32>, rotation: Quaternion<f32>) -> Self {
+        Transform { position, rotation }
+    }
+
+    pub fn from_position(position: Vector3<f32>) -> Self {
+        Transform::new(position, Quaternion::one())
+    }
+
+    /// rotate around an axis in the object's own space
+    pub fn rotate_local(&mut self, axis: Vector3<f32>, angle: Deg<f32>) {
+        self.rotation = (self.rotation * Quaternion::from_axis_angle(axis, angle)).normalize();
+    }
+
+    pub fn matrix(&self) -> Matrix4<f32> {
+        Matrix4::from_translation(self.position) * Matrix4::from(self.rotation)
+    }
+}
+
+impl Interpolate for Transform {
+    fn interpolate(&self, other: &Transform, amount: f32) -> Transform {
+        Transform {
+            position: self.position.lerp(other.position, amount),
+            rotation: slerp_shortest(self.rotation, other.rotation, amount),
+        }
+    }
+}
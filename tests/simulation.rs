@@ -0,0 +1,105 @@
+extern crate cgmath;
+extern crate learn_opengl;
+
+use std::path::Path;
+use std::time::Duration;
+
+use cgmath::prelude::*;
+use cgmath::{Point3, Quaternion};
+
+use learn_opengl::simulation::{CubeField, Simulation};
+use learn_opengl::utils::input_script::InputScript;
+
+const STEP: Duration = Duration::from_millis(16);
+// distance covered by one tick at the camera speed of 5 units per second
+const TICK_DISTANCE: f32 = 5.0 * 0.016;
+
+fn assert_close(actual: Point3<f32>, expected: Point3<f32>) {
+    assert!(actual.distance(expected) < 1e-4, "{:?} != {:?}", actual, expected);
+}
+
+fn assert_angle(actual: f32, expected: f32) {
+    assert!((actual - expected).abs() < 1e-3, "{} != {}", actual, expected);
+}
+
+/// cube rotations after `updates` fixed updates of a fresh cube field
+fn cube_rotations(updates: usize) -> Vec<Quaternion<f32>> {
+    let mut cubes = CubeField::new();
+    for _ in 0..updates {
+        cubes.update();
+    }
+    cubes.models().current().iter().map(|cube| cube.rotation).collect()
+}
+
+fn assert_cubes(simulation: &Simulation, updates: usize) {
+    let rotations: Vec<Quaternion<f32>> = simulation.cubes.models().current().iter().map(|cube| cube.rotation).collect();
+    let expected = cube_rotations(updates);
+    assert_eq!(rotations.len(), expected.len());
+    for (rotation, expected) in rotations.iter().zip(expected.iter()) {
+        assert!(rotation.dot(*expected).abs() > 1.0 - 1e-5, "{:?} != {:?}", rotation, expected);
+    }
+}
+
+#[test]
+fn walk_script() {
+    let script = InputScript::load(Path::new("resources/walk.input")).unwrap();
+    let mut simulation = Simulation::new(STEP, None);
+    simulation.run(100, &script);
+    assert_eq!(simulation.ticks(), 100);
+
+    let camera = simulation.camera.current();
+    // 30 ticks forward down the negative z axis, then a 30 degree turn to the right
+    // and 30 ticks strafing left along the turned camera's right vector
+    let (yaw, pitch) = camera.yaw_pitch();
+    assert_angle(yaw, -60.0);
+    assert_angle(pitch, 0.0);
+    let walked = Point3::new(0.0, 0.0, 3.0 - 30.0 * TICK_DISTANCE);
+    let right = cgmath::vec3(30f32.to_radians().cos(), 0.0, 30f32.to_radians().sin());
+    assert_close(camera.position, walked - right * (30.0 * TICK_DISTANCE));
+
+    // the camera keeps moving while paused, the cubes stop at tick 40
+    assert!(simulation.input.paused());
+    assert_cubes(&simulation, 40);
+}
+
+#[test]
+fn inline_script_stops_on_close() {
+    let script = InputScript::parse(
+        "0 press D
+         10 release D
+         10 mouse 0.0 -200.0
+         20 close",
+    ).unwrap();
+    let mut simulation = Simulation::new(STEP, None);
+    simulation.run(100, &script);
+    assert_eq!(simulation.ticks(), 20);
+
+    let camera = simulation.camera.current();
+    let (yaw, pitch) = camera.yaw_pitch();
+    assert_angle(yaw, -90.0);
+    assert_angle(pitch, 20.0);
+    assert_close(camera.position, Point3::new(10.0 * TICK_DISTANCE, 0.0, 3.0));
+    assert_cubes(&simulation, 20);
+
+    // a closed simulation doesn't run any further
+    simulation.run(10, &script);
+    assert_eq!(simulation.ticks(), 20);
+}
+
+#[test]
+fn steps_only_while_paused() {
+    let script = InputScript::parse(
+        "5 press N
+         5 release N
+         10 press P
+         10 release P
+         15 press N
+         15 release N",
+    ).unwrap();
+    let mut simulation = Simulation::new(STEP, None);
+    simulation.run(20, &script);
+
+    // 10 ticks running, the step asked for while running is dropped, one step while paused
+    assert!(simulation.input.paused());
+    assert_cubes(&simulation, 11);
+}